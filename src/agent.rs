@@ -0,0 +1,69 @@
+use std::{net::TcpListener, thread};
+
+use mismem::remote;
+
+
+const USAGE : &str = "\
+Usage: mismem-agent [--listen <ADDR>]
+
+Serves the processes of this machine to `mismem --remote <ADDR>`: listing them,
+their memory regions and modules, reads and writes.
+
+Options:
+  -l, --listen <ADDR>    Address to listen on [default: 127.0.0.1:7780]
+  -h, --help             Print this help
+
+Anyone who can connect can read and write the memory of every process the agent can open,
+only listen on other addresses than localhost on trusted networks (e.g. a container port).";
+
+
+fn main() {
+    let mut address = String::from("127.0.0.1:7780");
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-l" | "--listen" => match args.next() {
+                Some(value) => address = value,
+                None => fail("--listen needs a value")
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            _ => fail(&format!("unexpected argument '{}'", arg))
+        }
+    }
+
+    let listener = match TcpListener::bind(&address) {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("error: can't listen on {}: {}", address, error);
+            std::process::exit(1);
+        }
+    };
+    eprintln!("Listening on {}", listener.local_addr().map(|a| a.to_string()).unwrap_or(address));
+
+    // One thread per mismem connected
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!("Connection failed: {}", error);
+                continue;
+            }
+        };
+        let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+        eprintln!("{} connected", peer);
+
+        thread::spawn(move || match remote::serve(stream) {
+            Ok(()) => eprintln!("{} disconnected", peer),
+            Err(error) => eprintln!("{} disconnected: {}", peer, error),
+        });
+    }
+}
+
+fn fail(error: &str) -> ! {
+    eprintln!("error: {}\n\n{}", error, USAGE);
+    std::process::exit(2);
+}
@@ -1,29 +1,100 @@
+use std::time::{Duration, Instant};
+
 use tui::widgets::{TableState,ListState};
 use tui_input::Input;
 
-//use crate::win::enum_windows;
-use crate::win::{enum_processes,check_process};
-use crate::mem::Memory;
+use crate::os::{check_process, enum_modules, read_process, write_process};
+use crate::proclist::{ProcessList, SortColumn, find_by_name, find_by_pid};
+use crate::mem::{Memory, Datatype};
+use crate::hexview::{HexView, HexState};
+use crate::disasm::{Disassembly, DisasmState};
+use crate::expr::resolve_address;
+use crate::patch::parse_bytes;
+use crate::signature::Signature;
+use crate::watch::{Watch, WatchKind};
+use crate::inspect::Inspector;
+use crate::memmap::{MemoryMap, MapColumn};
+use crate::process::{DebugEvent, Module};
+use crate::launch::Launched;
+use crate::freeze::Freezer;
+use crate::script::Console;
+use crate::snapshot::Change;
 
 
+#[derive(Clone, Copy)]
 pub enum AppState {
     Home,
     SelectProcess,
     EditMemory,
+    HexView,
+    Disassembly,
+    Watch,
+    Inspector,
+    MemoryMap,
+}
+
+// Process picked on the command line, looked up again while it isn't running
+pub struct Target {
+    pub pid: Option<u32>,
+    pub name: Option<String>,
+    pub waiting: bool,
+}
+
+impl Target {
+    pub fn new(pid: Option<u32>, name: Option<String>) -> Target {
+        Target { pid: pid, name: name, waiting: true }
+    }
+
+    pub fn describe(&self) -> String {
+        match (&self.name, self.pid) {
+            (Some(name), _) => name.clone(),
+            (None, Some(pid)) => format!("PID {}", pid),
+            (None, None) => String::from("process"),
+        }
+    }
+
+    // Running pid matching the target
+    pub fn find(&self) -> Option<u32> {
+        match (&self.name, self.pid) {
+            (Some(name), _) => find_by_name(name).map(|p| p.pid),
+            (None, Some(pid)) => check_process(pid).then_some(pid),
+            (None, None) => None,
+        }
+    }
 }
 
+// Attached process that is gone, its results stay on screen read-only
+pub struct Exited {
+    pub pid: u32,
+    pub name: String,
+    // Modules at the time of the exit, to move results into a new instance
+    modules: Vec<Module>,
+}
+
+#[derive(Clone, Copy)]
 pub enum EditState {
     Input,
     Select,
     Edit,
+    Address,
+    Export,
+    Import,
+    Snapshot,
+    Compare,
+    SaveState,
+    RestoreState,
+    Script,
     Busy,
 }
 
 pub struct App<> {
     pub state: AppState,
     pub table_state: TableState,
-    pub processes: Vec<Vec<String>>,
+    pub processes: ProcessList,
     pub selected_process: u32,
+    pub inspector: Inspector,
+    pub memory_map: MemoryMap,
+    map_return: AppState,
 
     pub search_input: Input,
     pub edit_state: EditState,
@@ -35,10 +106,34 @@ pub struct App<> {
 
     pub show_popup: bool,
     pub popup_error : String,
+    pub signature: Option<Signature>,
 
     pub mismem_input: Input,
     pub selected_address: String,
 
+    pub hex_view: HexView,
+    hex_return: AppState,
+    pub disassembly: Disassembly,
+    disasm_return: AppState,
+    pub watch: Watch,
+    watch_return: AppState,
+
+    pub target: Option<Target>,
+    pub launched: Option<Launched>,
+    pub exited: Option<Exited>,
+    process_name: String,
+    modules: Vec<Module>,
+    last_check: Instant,
+
+    pub freezer: Freezer,
+    pub console: Option<Console>,
+
+    // Last snapshot and savestate saved, offered for the next comparison and restore
+    pub snapshot_path: String,
+    pub state_path: String,
+    // Index into Change::NAMES
+    pub change: usize,
+
     first_input: bool,
     pub exiting: bool,
 }
@@ -47,13 +142,17 @@ impl App {
     pub const DATATYPE_OPTS : [&str;7] = ["Byte", "2 Bytes","4 Bytes","8 Bytes","16 Bytes","Float","Double"];
     pub const SEARCH_MODE_OPTS : [&str;2] = ["First Search", "Filter"];
     pub const MATCH_MODE_OPTS : [&str;3] = ["Exact Match", "Less Than", "Greater Than"];
+    const POLL_INTERVAL : Duration = Duration::from_secs(1);
 
     pub fn new() -> App<> {
         let mut app = App {
             state: AppState::SelectProcess,
             table_state: TableState::default(),
-            processes: vec![],
+            processes: ProcessList::new(),
             selected_process: 0,
+            inspector: Inspector::new(),
+            memory_map: MemoryMap::new(),
+            map_return: AppState::EditMemory,
             
             search_input: Input::from("Press i to input..."),
             edit_state: EditState::Select,
@@ -65,10 +164,32 @@ impl App {
 
             show_popup: false,
             popup_error: String::new(),
+            signature: None,
 
             mismem_input: Input::default(),
             selected_address: String::new(),
 
+            hex_view: HexView::new(),
+            hex_return: AppState::EditMemory,
+            disassembly: Disassembly::new(),
+            disasm_return: AppState::EditMemory,
+            watch: Watch::new(),
+            watch_return: AppState::EditMemory,
+
+            target: None,
+            launched: None,
+            exited: None,
+            process_name: String::new(),
+            modules: vec![],
+            last_check: Instant::now() - App::POLL_INTERVAL,
+
+            freezer: Freezer::new(),
+            console: None,
+
+            snapshot_path: String::from("snapshot.snap"),
+            state_path: String::from("state.snap"),
+            change: 0,
+
             first_input: true,
             exiting: false,
         };
@@ -82,7 +203,7 @@ impl App {
     }
 
     pub fn next_process(&mut self) {
-        if self.show_popup { return; }
+        if self.show_popup || self.processes.is_empty() { return; }
 
         self.table_state.select(Some(
             (self.table_state.selected().unwrap_or(self.processes.len() - 1) + 1) % self.processes.len()
@@ -91,7 +212,7 @@ impl App {
     }
 
     pub fn previous_process(&mut self) {
-        if self.show_popup { return; }
+        if self.show_popup || self.processes.is_empty() { return; }
 
         self.table_state.select(Some(
             (self.processes.len() + self.table_state.selected().unwrap_or(0) - 1) % self.processes.len()
@@ -100,15 +221,99 @@ impl App {
 
     pub fn update_process_list(&mut self) {
         if self.show_popup { return; }
-        self.processes.clear();
-        
-        for p in enum_processes() {
-            self.processes.push(vec![p.pid.to_string(), p.name, p.memory.to_string()]);
+        self.processes.refresh();
+        self.clamp_process_selection();
+    }
+
+    fn clamp_process_selection(&mut self) {
+        if self.table_state.selected().is_some_and(|i| i >= self.processes.len()) {
+            self.table_state.select(if self.processes.is_empty() { None } else { Some(self.processes.len() - 1) });
+        }
+    }
+
+    pub fn filter_process_mode(&mut self) {
+        if self.show_popup { return; }
+        self.processes.filtering = true;
+    }
+
+    // Called after every key typed in the filter
+    pub fn filter_processes(&mut self) {
+        self.processes.update();
+        self.table_state.select(if self.processes.is_empty() { None } else { Some(0) });
+    }
+
+    pub fn clear_process_filter(&mut self) {
+        self.processes.filtering = false;
+        self.processes.filter.reset();
+        self.filter_processes();
+    }
+
+    pub fn toggle_process_tree(&mut self) {
+        if self.show_popup { return; }
+        let selected = self.table_state.selected().and_then(|i| self.processes.get(i)).map(|p| p.pid);
+        self.processes.toggle_tree();
+        self.table_state.select(selected.and_then(|pid| self.processes.position(pid)));
+    }
+
+    pub fn collapse_process(&mut self) {
+        if self.show_popup || !self.processes.tree { return; }
+        if let Some(index) = self.table_state.selected().and_then(|i| self.processes.collapse(i)) {
+            self.table_state.select(Some(index));
+        }
+    }
+
+    pub fn expand_process(&mut self) {
+        if self.show_popup || !self.processes.tree { return; }
+        if let Some(index) = self.table_state.selected().and_then(|i| self.processes.expand(i)) {
+            self.table_state.select(Some(index));
+        }
+    }
+
+    pub fn sort_processes(&mut self, column: SortColumn) {
+        if self.show_popup { return; }
+        self.processes.sort_by(column);
+        self.table_state.select(if self.processes.is_empty() { None } else { Some(0) });
+    }
+
+    pub fn on_tick(&mut self) {
+        self.poll_debugger();
+        self.poll_process();
+        self.poll_launched();
+        self.poll_console();
+
+        match self.state {
+            AppState::HexView => self.hex_view.refresh_if_stale(self.selected_process),
+            AppState::Disassembly => self.disassembly.decode_if_resized(self.selected_process),
+            AppState::Watch => self.watch.refresh(self.selected_process),
+            _ => {}
         }
     }
 
     pub fn back(&mut self) {
        match self.state {
+            AppState::HexView => {
+                self.show_popup = false;
+                self.state = self.hex_return;
+            }
+            AppState::Disassembly => {
+                self.show_popup = false;
+                self.state = self.disasm_return;
+            }
+            AppState::Inspector => {
+                self.show_popup = false;
+                self.state = AppState::EditMemory;
+            }
+            AppState::MemoryMap => {
+                self.show_popup = false;
+                self.memory_map.filtering = false;
+                self.state = self.map_return;
+            }
+            AppState::Watch => {
+                self.show_popup = false;
+                self.watch.stop();
+                self.state = self.watch_return;
+                log::info!(" Watchpoint removed.");
+            }
             AppState::EditMemory => {
                 self.show_popup = false;
                 self.state = AppState::SelectProcess;
@@ -128,24 +333,157 @@ impl App {
             return;
         }
 
-        if self.table_state.selected().is_none() { 
-            return; 
-        }
-
-        self.selected_process = self.processes[self.table_state.selected().unwrap_or_default()][0].parse().unwrap();
+        self.processes.filtering = false;
+        let pid = match self.table_state.selected().and_then(|i| self.processes.get(i)) {
+            Some(process) => process.pid,
+            None => return
+        };
         
-        if check_process(self.selected_process) {
-            self.state = AppState::EditMemory;
+        if check_process(pid) {
+            // A manual pick replaces the command line target
+            self.target = None;
             self.memory.clear();
+            self.attach(pid);
         } else {
+            self.selected_process = pid;
             self.show_popup = true;
         }
         self.table_state.select(None);
     }
 
+    // Results are kept so a restarted target can be updated in place
+    pub fn attach(&mut self, pid: u32) {
+        self.watch.stop();
+        self.disassembly.debug.detach();
+        self.selected_process = pid;
+        self.exited = None;
+        self.process_name = find_by_pid(pid).map(|p| p.name).unwrap_or_default();
+        self.modules = enum_modules(pid);
+        if let Some(console) = &self.console {
+            console.attach(pid);
+        }
+        self.memory_map.reset();
+        self.show_popup = false;
+        self.table_state.select(None);
+        self.state = AppState::EditMemory;
+    }
+
+    // Waits for the target to show up and notices when it goes away
+    fn poll_launched(&mut self) {
+        if let Some(launched) = &mut self.launched {
+            if let Some(exit) = launched.poll() {
+                log::warn!(" {} exited: {}.", launched.command, exit);
+            }
+        }
+    }
+
+    pub fn resume_launched(&mut self) {
+        match &mut self.launched {
+            Some(launched) if launched.held => match launched.resume() {
+                Ok(()) => log::info!(" {} is running.", launched.command),
+                Err(error) => log::error!(" Can't resume {}: {}", launched.command, error),
+            },
+            _ => {}
+        }
+    }
+
+    fn poll_process(&mut self) {
+        if self.last_check.elapsed() < App::POLL_INTERVAL {
+            return;
+        }
+        self.last_check = Instant::now();
+
+        if let Some(target) = self.target.as_mut().filter(|t| t.waiting) {
+            let old_pid = self.exited.as_ref().map(|e| e.pid);
+            if let Some(pid) = target.find().filter(|&pid| Some(pid) != old_pid) {
+                target.waiting = false;
+                log::info!(" Attached to {} (PID {}).", target.describe(), pid);
+                self.reattach(pid);
+            }
+            return;
+        }
+
+        if self.exited.is_some() || matches!(self.state, AppState::Home | AppState::SelectProcess) {
+            return;
+        }
+        if !check_process(self.selected_process) {
+            self.process_exited();
+        }
+    }
+
+    fn process_exited(&mut self) {
+        let pid = self.selected_process;
+        self.watch.stop();
+        self.disassembly.debug.detach();
+        self.show_popup = false;
+        self.edit_state = EditState::Select;
+        self.state = AppState::EditMemory;
+        self.freezer.unfreeze_process(pid);
+
+        log::warn!(" Process {} ({}) exited, its {} results are kept read-only.", pid, self.process_name, self.memory.len());
+        match &mut self.target {
+            Some(target) if target.name.is_some() => {
+                target.waiting = true;
+                log::info!(" Waiting for {} to restart.", target.describe());
+            },
+            _ => {
+                self.target = None;
+                log::info!(" Press R to reattach to a new instance of {}.", self.process_name);
+            }
+        }
+
+        self.exited = Some(Exited {
+            pid: pid,
+            name: std::mem::take(&mut self.process_name),
+            modules: std::mem::take(&mut self.modules),
+        });
+    }
+
+    // Waits for the next instance of the exited executable, poll_process attaches to it
+    pub fn reattach_mode(&mut self) {
+        let name = match &self.exited {
+            Some(exited) if !self.target.as_ref().is_some_and(|t| t.waiting) => exited.name.clone(),
+            _ => return
+        };
+        log::info!(" Waiting for a new instance of {}.", name);
+        self.target = Some(Target::new(None, Some(name)));
+        self.last_check = Instant::now() - App::POLL_INTERVAL;
+    }
+
+    // Results inside modules follow them to their new addresses
+    fn reattach(&mut self, pid: u32) {
+        let exited = self.exited.take();
+        self.attach(pid);
+
+        if let Some(exited) = exited {
+            let moved = self.memory.rebase(&exited.modules, &self.modules);
+            let kept = self.memory.len() - moved;
+            log::info!(" Re-resolved {} module entries, {} others kept at their old addresses. Press u to update.", moved, kept);
+        }
+    }
+
+    // Modules loaded after attaching, results in them can follow a restart too
+    pub fn refresh_modules(&mut self) {
+        if self.exited.is_none() {
+            self.modules = enum_modules(self.selected_process);
+        }
+    }
+
+    // Results of an exited process can only be looked at
+    pub fn running(&self) -> bool {
+        match &self.exited {
+            Some(exited) => {
+                log::warn!(" Process {} has exited, results are read-only.", exited.pid);
+                false
+            },
+            None => true
+        }
+    }
+
 
     pub fn select_memory(&mut self) {
-        
+        if !self.running() { return; }
+
         let memory_idx = self.table_state.selected().unwrap_or_default();
         let entry = self.memory.iter().nth(memory_idx);
 
@@ -197,7 +535,253 @@ impl App {
         ));
     }
 
+    pub fn address_mode(&mut self) {
+        if self.show_popup { return; }
+        if !self.running() { return; }
+
+        self.mismem_input.reset();
+        self.edit_state = EditState::Address;
+    }
+
+    pub fn export_mode(&mut self) {
+        if self.show_popup { return; }
+
+        self.mismem_input = Input::from("results.csv");
+        self.edit_state = EditState::Export;
+    }
+
+    pub fn import_mode(&mut self) {
+        if self.show_popup { return; }
+
+        self.mismem_input = Input::from("results.csv");
+        self.edit_state = EditState::Import;
+    }
+
+    pub fn snapshot_mode(&mut self) {
+        if self.show_popup { return; }
+        if !self.running() { return; }
+
+        self.mismem_input = Input::from(self.snapshot_path.as_str());
+        self.edit_state = EditState::Snapshot;
+    }
+
+    // Two snapshots don't need the process
+    pub fn compare_mode(&mut self) {
+        if self.show_popup { return; }
+
+        self.mismem_input = Input::from(self.snapshot_path.as_str());
+        self.edit_state = EditState::Compare;
+    }
+
+    pub fn save_state_mode(&mut self) {
+        if self.show_popup { return; }
+        if !self.running() { return; }
+
+        self.mismem_input = Input::from(self.state_path.as_str());
+        self.edit_state = EditState::SaveState;
+    }
+
+    pub fn restore_state_mode(&mut self) {
+        if self.show_popup { return; }
+        if !self.running() { return; }
+
+        self.mismem_input = Input::from(self.state_path.as_str());
+        self.edit_state = EditState::RestoreState;
+    }
+
+    pub fn next_change(&mut self) {
+        self.change = (self.change + 1) % Change::NAMES.len();
+    }
+
+    pub fn is_typing(&self) -> bool {
+        match self.state {
+            AppState::SelectProcess => self.processes.filtering,
+            AppState::MemoryMap => self.memory_map.filtering,
+            AppState::EditMemory => !matches!(self.edit_state, EditState::Select | EditState::Busy),
+            AppState::HexView => matches!(self.hex_view.state, HexState::Goto | HexState::Edit),
+            AppState::Disassembly => matches!(self.disassembly.state, DisasmState::Goto | DisasmState::Patch | DisasmState::EditRegister),
+            _ => false
+        }
+    }
+
+    fn selected_memory_address(&self) -> Option<usize> {
+        self.selected_memory_location().map(|(address, _)| address)
+    }
+
+    // Entries are formatted as "ADDRESS:type"
+    fn selected_memory_location(&self) -> Option<(usize, Datatype)> {
+        let memory_idx = self.table_state.selected()?;
+        let entry = self.memory.iter().nth(memory_idx)?;
+        let (address, datatype) = entry[0].split_once(':')?;
+        Some((usize::from_str_radix(address, 16).ok()?, Datatype::from_name(datatype)?))
+    }
+
+    // Freezing
+
+    // Freezes the selected result at its current value, or releases it
+    pub fn toggle_freeze(&mut self) {
+        if self.show_popup { return; }
+        if !self.running() { return; }
+
+        let (address, datatype) = match self.selected_memory_location() {
+            Some(location) => location,
+            None => return
+        };
+        if self.freezer.unfreeze(self.selected_process, address) {
+            log::info!(" {:016X} is no longer frozen.", address);
+            return;
+        }
+        let mut bytes = vec![0; datatype.size()];
+        if !read_process(self.selected_process, address, &mut bytes) {
+            log::error!(" Can't read {:016X} to freeze it.", address);
+            return;
+        }
+        // Fails now rather than silently in the freezer
+        match write_process(self.selected_process, address, &bytes) {
+            Ok(()) => {
+                self.freezer.freeze(self.selected_process, address, bytes);
+                log::info!(" {:016X} frozen, press f again to release it.", address);
+            },
+            Err(error) => log::error!(" Can't freeze {:016X}: {}", address, error),
+        }
+    }
+
+    pub fn unfreeze_all(&mut self) {
+        let released = self.freezer.unfreeze_all();
+        if released > 0 {
+            log::info!(" Released {} frozen values.", released);
+        }
+    }
+
+    // Script
+
+    pub fn script_mode(&mut self) {
+        if self.show_popup { return; }
+
+        let (pid, freezer) = (self.selected_process, self.freezer.clone());
+        self.console.get_or_insert_with(|| Console::start(pid, freezer));
+        self.edit_state = EditState::Script;
+    }
+
+    pub fn stop_script(&mut self) {
+        if let Some(console) = &self.console {
+            console.stop();
+        }
+    }
+
+    pub fn script_running(&self) -> bool {
+        self.console.as_ref().is_some_and(|c| c.is_running())
+    }
+
+    // Results passed to show() replace the table
+    fn poll_console(&mut self) {
+        if let Some(memory) = self.console.as_ref().and_then(|c| c.shown_results()) {
+            self.memory = memory;
+            self.table_state.select(None);
+        }
+    }
+
+    // HexView
+
+    pub fn open_hex_view(&mut self) {
+        if self.show_popup { return; }
+        if !self.running() { return; }
+
+        let address = self.selected_memory_address();
+
+        self.hex_return = AppState::EditMemory;
+        self.state = AppState::HexView;
+        match address {
+            Some(address) => self.hex_view.open(self.selected_process, address),
+            None => self.hex_goto_mode()
+        }
+    }
+
+    pub fn scroll_hex(&mut self, rows: isize) {
+        if self.show_popup { return; }
+        self.hex_view.scroll(self.selected_process, rows);
+    }
+
+    pub fn move_hex_cursor(&mut self, bytes: isize) {
+        if self.show_popup { return; }
+        self.hex_view.move_cursor(self.selected_process, bytes);
+    }
+
+    pub fn page_hex(&mut self, pages: isize) {
+        self.move_hex_cursor(pages * (self.hex_view.rows * HexView::BYTES_PER_ROW) as isize);
+    }
+
+    pub fn hex_edit_mode(&mut self) {
+        if self.show_popup { return; }
+        self.hex_view.state = HexState::Edit;
+    }
+
+    pub fn hex_edit_key(&mut self, c: char) {
+        if self.hex_view.ascii {
+            if c.is_ascii() && !c.is_ascii_control() {
+                self.hex_view.edit_ascii(self.selected_process, c as u8);
+            }
+        } else if let Some(digit) = c.to_digit(16) {
+            self.hex_view.edit_nibble(self.selected_process, digit as u8);
+        }
+    }
+
+    pub fn hex_commit(&mut self) {
+        let failed = self.hex_view.commit(self.selected_process);
+
+        if failed.is_empty() {
+            self.hex_view.state = HexState::Browse;
+            log::info!(" Memory write successful.");
+        } else {
+            self.popup_error = format!("Error: {}.", failed.join("; "));
+            self.show_popup = true;
+            log::error!(" Memory write failed: {}", failed.join("; "));
+        }
+    }
+
+    pub fn inspector_mode(&mut self) {
+        if self.show_popup { return; }
+
+        if self.hex_view.inspector_state.selected().is_none() {
+            self.hex_view.inspector_state.select(Some(0));
+        }
+        self.hex_view.state = HexState::Inspect;
+    }
+
+    pub fn add_inspected(&mut self) {
+        match self.hex_view.selected_interpretation() {
+            Some((datatype, bytes)) => {
+                self.memory.insert(self.hex_view.cursor, &datatype, &bytes);
+                log::info!(" Added {:016X}:{} to results.", self.hex_view.cursor, datatype.name());
+            },
+            None => log::warn!(" This interpretation can't be added to results.")
+        }
+    }
+
+    pub fn hex_revert(&mut self) {
+        self.hex_view.revert();
+        self.hex_view.state = HexState::Browse;
+    }
+
+    pub fn hex_goto_mode(&mut self) {
+        if self.show_popup { return; }
+
+        self.mismem_input.reset();
+        self.hex_view.state = HexState::Goto;
+    }
+
+    pub fn hex_goto(&mut self) {
+        match resolve_address(self.selected_process, self.mismem_input.value()) {
+            Ok(address) => self.hex_view.open(self.selected_process, address),
+            Err(e) => {
+                self.popup_error = format!("Address error: {}", e);
+                self.show_popup = true;
+            }
+        }
+    }
+
     pub fn input_mode(&mut self) {
+        if !self.running() { return; }
         if self.first_input {
             self.first_input = false;
             self.search_input.reset();
@@ -205,4 +789,406 @@ impl App {
         self.edit_state = EditState::Input;
     }
 
-}
\ No newline at end of file
+    // Inspector
+
+    pub fn open_inspector(&mut self) {
+        if self.show_popup { return; }
+        if !self.running() { return; }
+        self.inspector.open(self.selected_process);
+        self.memory_map.refresh(self.selected_process);
+        self.state = AppState::Inspector;
+    }
+
+    pub fn refresh_inspector(&mut self) {
+        self.inspector.refresh(self.selected_process);
+        self.memory_map.refresh(self.selected_process);
+    }
+
+    // Back to the search with a first scan limited to the range
+    fn scan_range_mode(&mut self, start: usize, end: usize, name: String) {
+        self.memory_map.include_only(start, end);
+        self.search_mode.select(Some(0));
+        self.state = AppState::EditMemory;
+        self.input_mode();
+        log::info!(" Next first scan limited to {} ({:X}-{:X}).", name, start, end);
+    }
+
+    pub fn scan_region(&mut self) {
+        if let Some(mapping) = self.memory_map.selected() {
+            let (start, end) = (mapping.region.base, mapping.region.base + mapping.region.size);
+            let name = if mapping.path.is_empty() { String::from("region") } else { mapping.path.clone() };
+            self.scan_range_mode(start, end, name);
+        }
+    }
+
+    pub fn scan_module(&mut self) {
+        match self.memory_map.selected_module() {
+            Some((start, end, path)) => {
+                let path = path.to_string();
+                self.scan_range_mode(start, end, path);
+            },
+            None => log::warn!(" Selected region isn't backed by a module.")
+        }
+    }
+
+    pub fn scan_everywhere(&mut self) {
+        self.memory_map.include_default();
+        self.state = AppState::EditMemory;
+        self.input_mode();
+        log::info!(" Scans cover all writable memory again.");
+    }
+
+    // MemoryMap
+
+    pub fn open_memory_map(&mut self) {
+        if self.show_popup { return; }
+        if !self.running() { return; }
+        self.memory_map.refresh(self.selected_process);
+        self.map_return = match self.state {
+            AppState::Inspector => AppState::Inspector,
+            _ => AppState::EditMemory,
+        };
+        self.state = AppState::MemoryMap;
+    }
+
+    pub fn filter_map_mode(&mut self) {
+        self.memory_map.filtering = true;
+    }
+
+    pub fn clear_map_filter(&mut self) {
+        self.memory_map.filtering = false;
+        self.memory_map.filter.reset();
+        self.memory_map.update();
+    }
+
+    pub fn sort_map(&mut self, column: MapColumn) {
+        self.memory_map.sort_by(column);
+    }
+
+    pub fn toggle_map_region(&mut self) {
+        self.memory_map.toggle_selected();
+        self.memory_map.next();
+    }
+
+    pub fn include_map_regions(&mut self, included: bool) {
+        self.memory_map.include_visible(included);
+        log::info!(" {} {} regions {} the next scan.", if included { "Added" } else { "Removed" },
+            self.memory_map.len(), if included { "to" } else { "from" });
+    }
+
+    pub fn reset_map_regions(&mut self) {
+        self.memory_map.include_default();
+        log::info!(" Scans cover all writable memory again.");
+    }
+
+    pub fn hex_view_region(&mut self) {
+        let base = match self.memory_map.selected() {
+            Some(mapping) => mapping.region.base,
+            None => return
+        };
+        self.memory_map.filtering = false;
+        self.hex_return = AppState::MemoryMap;
+        self.state = AppState::HexView;
+        self.hex_view.open(self.selected_process, base);
+    }
+
+    // Disassembly
+
+    pub fn open_disassembly(&mut self) {
+        if self.show_popup { return; }
+        if !self.running() { return; }
+
+        let address = match self.state {
+            AppState::HexView => Some(self.hex_view.cursor),
+            _ => self.selected_memory_address()
+        };
+
+        self.disasm_return = self.state;
+        self.state = AppState::Disassembly;
+        match address {
+            Some(address) => self.disassembly.open(self.selected_process, address),
+            None => {
+                self.disassembly.open(self.selected_process, 0);
+                self.disasm_goto_mode();
+            }
+        }
+    }
+
+    pub fn disasm_goto_mode(&mut self) {
+        if self.show_popup { return; }
+
+        self.mismem_input.reset();
+        self.disassembly.state = DisasmState::Goto;
+    }
+
+    pub fn disasm_goto(&mut self) {
+        match resolve_address(self.selected_process, self.mismem_input.value()) {
+            Ok(address) => self.disassembly.jump(self.selected_process, address),
+            Err(e) => {
+                self.popup_error = format!("Address error: {}", e);
+                self.show_popup = true;
+            }
+        }
+    }
+
+    pub fn next_instruction(&mut self) {
+        if self.show_popup { return; }
+        self.disassembly.next(self.selected_process);
+    }
+
+    pub fn previous_instruction(&mut self) {
+        if self.show_popup { return; }
+        self.disassembly.previous(self.selected_process);
+    }
+
+    pub fn page_disasm(&mut self, pages: isize) {
+        if pages > 0 {
+            self.disassembly.page_down(self.selected_process);
+        } else {
+            self.disassembly.page_up(self.selected_process);
+        }
+    }
+
+    pub fn disasm_follow_back(&mut self) {
+        self.disassembly.follow_back(self.selected_process);
+    }
+
+    pub fn disasm_follow(&mut self) {
+        if !self.disassembly.follow(self.selected_process) {
+            log::warn!(" Selected instruction has no branch target.");
+        }
+    }
+
+    pub fn disasm_nop(&mut self) {
+        if self.show_popup { return; }
+
+        match self.disassembly.nop(self.selected_process) {
+            Ok(()) => log::info!(" Instruction replaced with NOPs."),
+            Err(error) => {
+                self.popup_error = format!("Error: {}.", error);
+                self.show_popup = true;
+                log::error!(" Patch failed: {}", error);
+            }
+        }
+    }
+
+    pub fn disasm_patch_mode(&mut self) {
+        if self.show_popup { return; }
+
+        self.mismem_input.reset();
+        self.disassembly.state = DisasmState::Patch;
+    }
+
+    pub fn disasm_patch(&mut self) {
+        let result = parse_bytes(self.mismem_input.value())
+            .and_then(|bytes| self.disassembly.patch(self.selected_process, &bytes).map_err(|e| e.to_string()));
+
+        match result {
+            Ok(()) => log::info!(" Patch applied."),
+            Err(error) => {
+                self.popup_error = format!("Error: {}.", error);
+                self.show_popup = true;
+                log::error!(" Patch failed: {}", error);
+            }
+        }
+    }
+
+    pub fn disasm_restore(&mut self) {
+        if self.show_popup { return; }
+
+        match self.disassembly.restore(self.selected_process) {
+            Ok(true) => log::info!(" Original bytes restored."),
+            Ok(false) => log::warn!(" Selected instruction is not patched."),
+            Err(error) => {
+                self.popup_error = format!("Error: {}.", error);
+                self.show_popup = true;
+                log::error!(" Restore failed: {}", error);
+            }
+        }
+    }
+
+    // Signatures
+
+    // Address to generate a signature for, the handler runs generate outside the lock
+    pub fn signature_address(&self) -> Option<usize> {
+        if self.show_popup { return None; }
+
+        match self.state {
+            AppState::HexView => Some(self.hex_view.cursor),
+            AppState::Disassembly => self.disassembly.selected().map(|l| l.address),
+            _ => self.selected_memory_address()
+        }
+    }
+
+    pub fn show_signature(&mut self, result: Result<Signature, String>) {
+        match result {
+            Ok(signature) => {
+                log::info!(" Signature for {}+{:X}: {}", signature.module, signature.offset, signature);
+                self.signature = Some(signature);
+            },
+            Err(error) => {
+                self.popup_error = format!("Signature error: {}.", error);
+                self.show_popup = true;
+            }
+        }
+    }
+
+    // Watchpoints
+
+    pub fn start_watch(&mut self, kind: WatchKind) {
+        if self.show_popup { return; }
+        if !self.running() { return; }
+
+        let location = match self.state {
+            AppState::HexView => Some((self.hex_view.cursor, 1)),
+            _ => self.selected_memory_location().map(|(address, datatype)| (address, datatype.size()))
+        };
+        let (address, size) = match location {
+            Some(location) => location,
+            None => return
+        };
+
+        match self.watch.start(self.selected_process, address, size, kind) {
+            Ok(()) => {
+                log::info!(" Watching what {} {:X}.", kind.name(), address);
+                self.watch_return = self.state;
+                self.state = AppState::Watch;
+            },
+            Err(error) => {
+                self.popup_error = format!("Watchpoint error: {}.", error);
+                self.show_popup = true;
+                log::error!(" Watchpoint failed: {}", error);
+            }
+        }
+    }
+
+    pub fn next_watch_hit(&mut self) {
+        if self.show_popup { return; }
+        self.watch.next();
+    }
+
+    pub fn previous_watch_hit(&mut self) {
+        if self.show_popup { return; }
+        self.watch.previous();
+    }
+
+    pub fn disassemble_watch_hit(&mut self) {
+        if self.show_popup { return; }
+
+        if let Some(address) = self.watch.selected().map(|h| h.address) {
+            self.disasm_return = self.state;
+            self.state = AppState::Disassembly;
+            self.disassembly.open(self.selected_process, address);
+        }
+    }
+
+    // Debugger
+
+    fn poll_debugger(&mut self) {
+        let address = match self.disassembly.debug.poll() {
+            Some(DebugEvent::Breakpoint { tid, address }) => {
+                log::info!(" Thread {} hit breakpoint at {:X}.", tid, address);
+                address
+            },
+            Some(DebugEvent::Step { address, .. }) => address,
+            Some(DebugEvent::Exited) => {
+                log::warn!(" Debugged process exited.");
+                return;
+            },
+            None => return
+        };
+
+        // Stops bring the disassembly to the front
+        if !matches!(self.state, AppState::Disassembly) {
+            self.disasm_return = self.state;
+            self.state = AppState::Disassembly;
+        }
+        self.disassembly.reveal(self.selected_process, address);
+    }
+
+    fn debug_result(&mut self, result: std::io::Result<()>) {
+        if let Err(error) = result {
+            self.popup_error = format!("Debugger error: {}.", error);
+            self.show_popup = true;
+            log::error!(" Debugger: {}", error);
+        }
+    }
+
+    pub fn toggle_breakpoint(&mut self) {
+        if self.show_popup { return; }
+
+        let address = match self.disassembly.selected() {
+            Some(line) if !line.bytes.is_empty() => line.address,
+            _ => return
+        };
+
+        let result = self.disassembly.debug.toggle_breakpoint(address).map(|set| {
+            if set {
+                log::info!(" Breakpoint set at {:X}.", address);
+            } else {
+                log::info!(" Breakpoint removed at {:X}.", address);
+            }
+        });
+        self.debug_result(result);
+        self.disassembly.decode(self.selected_process);
+    }
+
+    pub fn debug_step(&mut self) {
+        if self.show_popup { return; }
+        let result = self.disassembly.debug.step();
+        self.debug_result(result);
+    }
+
+    pub fn debug_resume(&mut self) {
+        if self.show_popup { return; }
+        let result = self.disassembly.debug.resume();
+        self.debug_result(result);
+        self.disassembly.decode(self.selected_process);
+    }
+
+    pub fn debug_detach(&mut self) {
+        if self.show_popup { return; }
+
+        if self.disassembly.debug.is_attached() {
+            self.disassembly.debug.detach();
+            self.disassembly.state = DisasmState::Browse;
+            self.disassembly.decode(self.selected_process);
+            log::info!(" Debugger detached.");
+        }
+    }
+
+    pub fn registers_mode(&mut self) {
+        if self.show_popup { return; }
+
+        if !self.disassembly.debug.registers.is_empty() {
+            self.disassembly.state = DisasmState::Registers;
+        }
+    }
+
+    pub fn edit_register_mode(&mut self) {
+        if self.show_popup { return; }
+
+        if let Some(register) = self.disassembly.debug.selected_register() {
+            self.mismem_input = Input::new(format!("0x{:X}", register.value));
+            self.disassembly.state = DisasmState::EditRegister;
+        }
+    }
+
+    pub fn edit_register(&mut self) {
+        match resolve_address(self.selected_process, self.mismem_input.value()) {
+            Ok(value) => {
+                let result = self.disassembly.debug.set_register(value as u64);
+                self.disassembly.state = DisasmState::Registers;
+                self.debug_result(result);
+                if let Some(position) = self.disassembly.debug.position {
+                    self.disassembly.reveal(self.selected_process, position);
+                }
+            },
+            Err(e) => {
+                self.popup_error = format!("Value error: {}", e);
+                self.show_popup = true;
+            }
+        }
+    }
+
+}
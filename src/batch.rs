@@ -0,0 +1,324 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use serde::Serialize;
+
+use crate::cli::Command;
+use crate::coredump;
+use crate::export::{export_memory, import_memory, write_memory, ExportFormat};
+use crate::expr::resolve_address;
+use crate::mem::{Datatype, Memory};
+use crate::os::{check_process, enum_processes, read_process, write_process};
+use crate::proclist::find_by_name;
+use crate::process::ProcessInfo;
+use crate::scan::{filter_memory, scan_memory};
+use crate::savestate::{restore_state, save_state, Mismatch};
+use crate::script;
+use crate::snapshot::{compare_snapshot, filter_snapshot, save_snapshot, Change, Snapshot, Target};
+
+
+// Non-interactive subcommands, everything printed on stdout is JSON except script output
+
+#[derive(Serialize)]
+pub struct ProcessEntry<'a> {
+    pid: u32,
+    ppid: u32,
+    name: &'a str,
+    user: &'a str,
+    memory_kb: f64,
+    start_time: u64,
+    cmdline: &'a str,
+}
+
+#[derive(Serialize)]
+struct Saved<'a> {
+    pid: u32,
+    count: usize,
+    out: &'a str,
+}
+
+// Snapshots and savestates
+#[derive(Serialize)]
+struct SavedMemory<'a> {
+    pid: u32,
+    regions: usize,
+    bytes: usize,
+    out: &'a str,
+}
+
+#[derive(Serialize)]
+struct Restored {
+    pid: u32,
+    regions: usize,
+    bytes: usize,
+    skipped: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct Value {
+    pub pid: u32,
+    pub address: String,
+    #[serde(rename = "type")]
+    pub datatype: &'static str,
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_value: Option<String>,
+}
+
+
+pub fn run(command: Command) -> Result<(), String> {
+    match command {
+        Command::Ps => {
+            let processes = enum_processes();
+            print_json(&process_entries(&processes))
+        },
+        Command::Scan { pid, name, datatype, value, output } => {
+            let pid = find_process(pid, name)?;
+            let bytes = datatype.parse(&value).map_err(|e| format!("invalid value '{}': {}", value, e))?;
+
+            let memory = scan_memory(pid, &bytes, &datatype, None, |_| {})
+                .map_err(|e| format!("can't scan process {}: {}", pid, e))?;
+            print_results(pid, &memory, output.as_deref())
+        },
+        Command::Filter { input, pid, datatype, value, output } => {
+            let (saved_pid, mut memory) = import_memory(Path::new(&input))
+                .map_err(|e| format!("can't read {}: {}", input, e))?;
+            let pid = pid.or(coredump::pid()).unwrap_or(saved_pid);
+            if !check_process(pid) {
+                return Err(format!("process {} from {} is not running", pid, input));
+            }
+
+            let datatype = results_type(datatype, &mut memory, &input)?;
+            let bytes = datatype.parse(&value).map_err(|e| format!("invalid value '{}': {}", value, e))?;
+
+            let exited = filter_memory(pid, &mut memory, &bytes, &datatype, |_| {})
+                .map_err(|e| format!("can't filter process {}: {}", pid, e))?;
+            if exited {
+                return Err(format!("process {} exited while filtering", pid));
+            }
+            print_results(pid, &memory, output.as_deref())
+        },
+        Command::Read { pid, name, address, datatype } => {
+            let pid = find_process(pid, name)?;
+            let address = resolve_address(pid, &address).map_err(|e| format!("invalid address '{}': {}", address, e))?;
+            let value = read_value(pid, address, &datatype)?;
+
+            print_json(&Value {
+                pid: pid,
+                address: format!("{:016X}", address),
+                datatype: datatype.name(),
+                value: value,
+                old_value: None,
+            })
+        },
+        Command::Write { pid, name, address, datatype, value } => {
+            let pid = find_process(pid, name)?;
+            let address = resolve_address(pid, &address).map_err(|e| format!("invalid address '{}': {}", address, e))?;
+            let bytes = datatype.parse(&value).map_err(|e| format!("invalid value '{}': {}", value, e))?;
+
+            let old_value = read_value(pid, address, &datatype)?;
+            write_process(pid, address, &bytes).map_err(|e| format!("can't write process {}: {}", pid, e))?;
+
+            print_json(&Value {
+                pid: pid,
+                address: format!("{:016X}", address),
+                datatype: datatype.name(),
+                value: read_value(pid, address, &datatype)?,
+                old_value: Some(old_value),
+            })
+        },
+        Command::Script { path, pid, name } => {
+            // Scripts may attach by themselves
+            let pid = match (pid, name) {
+                (None, None) => coredump::pid().unwrap_or(0),
+                (pid, name) => find_process(pid, name)?,
+            };
+            script::run_file(&path, pid).map_err(|e| format!("{}: {}", path, e))
+        },
+        Command::Snapshot { pid, name, output } => {
+            let pid = find_process(pid, name)?;
+            let (regions, bytes) = save_snapshot(pid, None, Path::new(&output), |_| {})
+                .map_err(|e| format!("can't save a snapshot of process {} to {}: {}", pid, output, e))?;
+
+            print_json(&SavedMemory { pid: pid, regions: regions, bytes: bytes, out: &output })
+        },
+        Command::Savestate { pid, name, output } => {
+            let pid = find_process(pid, name)?;
+            let (regions, bytes) = save_state(pid, Path::new(&output), |_| {})
+                .map_err(|e| format!("can't save the state of process {} to {}: {}", pid, output, e))?;
+
+            print_json(&SavedMemory { pid: pid, regions: regions, bytes: bytes, out: &output })
+        },
+        Command::Restore { path, pid, name, force } => {
+            let state = Snapshot::load(Path::new(&path)).map_err(|e| format!("can't read {}: {}", path, e))?;
+            let pid = match (pid, name) {
+                (None, None) => state.pid,
+                (pid, name) => find_process(pid, name)?,
+            };
+            if !check_process(pid) {
+                return Err(format!("process {} from {} is not running", pid, path));
+            }
+
+            let restored = restore_state(pid, &state, force, |_| {})
+                .map_err(|e| format!("can't restore process {}: {}", pid, e))?;
+            let skipped = restored.mismatches.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+            if restored.mismatches.iter().any(Mismatch::blocks) && !force {
+                return Err(format!("the memory of process {} doesn't match {} anymore, nothing was restored:\n  {}\n\
+                    Use --force to restore the regions still in place.", pid, path, skipped.join("\n  ")));
+            }
+            print_json(&Restored { pid: pid, regions: restored.regions, bytes: restored.bytes, skipped: skipped })
+        },
+        Command::Compare { old, new, input, pid, name, datatype, change, value, output } => {
+            let load = |path: &str| Snapshot::load(Path::new(path)).map_err(|e| format!("can't read {}: {}", path, e));
+            let old_snapshot = load(&old)?;
+            let new_snapshot = new.as_deref().map(load).transpose()?;
+
+            // Without a second snapshot the process the first one was taken from, as it is now
+            let (pid, target) = match &new_snapshot {
+                Some(snapshot) => (snapshot.pid, Target::Snapshot(snapshot)),
+                None => {
+                    let pid = match (pid, name) {
+                        (None, None) => coredump::pid().unwrap_or(old_snapshot.pid),
+                        (pid, name) => find_process(pid, name)?,
+                    };
+                    if !check_process(pid) {
+                        return Err(format!("process {} from {} is not running", pid, old));
+                    }
+                    (pid, Target::Process(pid))
+                }
+            };
+
+            let memory = match input {
+                Some(input) => {
+                    let (_, mut memory) = import_memory(Path::new(&input))
+                        .map_err(|e| format!("can't read {}: {}", input, e))?;
+                    let change = match (change, value) {
+                        (Some(change), _) => change,
+                        (None, value) => Change::Equal(parse_value(&results_type(datatype, &mut memory, &input)?, &value.unwrap_or_default())?),
+                    };
+                    filter_snapshot(&mut memory, &old_snapshot, target, &change, |_| {})
+                        .map_err(|e| format!("can't compare with process {}: {}", pid, e))?;
+                    memory
+                },
+                None => {
+                    let datatype = datatype.ok_or_else(|| String::from("compare needs --type"))?;
+                    let change = match (change, value) {
+                        (Some(change), _) => change,
+                        (None, value) => Change::Equal(parse_value(&datatype, &value.unwrap_or_default())?),
+                    };
+                    compare_snapshot(&old_snapshot, target, &datatype, &change, |_| {})
+                        .map_err(|e| format!("can't compare with process {}: {}", pid, e))?
+                },
+            };
+            print_results(pid, &memory, output.as_deref())
+        },
+    }
+}
+
+
+// Shared with the JSON-RPC server
+pub fn process_entries(processes: &[ProcessInfo]) -> Vec<ProcessEntry<'_>> {
+    processes.iter().map(|p| ProcessEntry {
+        pid: p.pid,
+        ppid: p.ppid,
+        name: &p.name,
+        user: &p.user,
+        memory_kb: p.memory,
+        start_time: p.start_time,
+        cmdline: &p.cmdline,
+    }).collect()
+}
+
+fn find_process(pid: Option<u32>, name: Option<String>) -> Result<u32, String> {
+    match (pid, name) {
+        (Some(pid), _) if check_process(pid) => Ok(pid),
+        (Some(pid), _) => Err(format!("process {} is not running or can't be opened", pid)),
+        (None, Some(name)) => find_by_name(&name).map(|p| p.pid)
+            .ok_or_else(|| format!("{} is not running", name)),
+        (None, None) => coredump::pid().ok_or_else(|| String::from("no process given")),
+    }
+}
+
+// The type given, or the only one in the results of input. Results of other types are dropped, they
+// can't be compared with a value of this one.
+fn results_type(datatype: Option<Datatype>, memory: &mut Memory, input: &str) -> Result<Datatype, String> {
+    let datatype = match (datatype, memory.datatypes().as_slice()) {
+        (_, []) => return Err(format!("{} has no results", input)),
+        (Some(datatype), types) if !types.iter().any(|d| d.name() == datatype.name()) => {
+            return Err(format!("{} has no {} results", input, datatype.name()));
+        },
+        (Some(datatype), _) => datatype,
+        (None, [datatype]) => *datatype,
+        (None, _) => return Err(format!("{} holds several types, pick one with --type", input)),
+    };
+    memory.retain_type(&datatype);
+    Ok(datatype)
+}
+
+
+fn parse_value(datatype: &Datatype, value: &str) -> Result<Vec<u8>, String> {
+    datatype.parse(value).map_err(|e| format!("invalid value '{}': {}", value, e))
+}
+
+pub fn read_value(pid: u32, address: usize, datatype: &Datatype) -> Result<String, String> {
+    let mut buffer = vec![0; datatype.size()];
+    if !read_process(pid, address, &mut buffer) {
+        return Err(format!("can't read {:X} in process {}", address, pid));
+    }
+    Ok(datatype.format(&buffer, cfg!(target_endian = "big")).unwrap_or_default())
+}
+
+// Results go to a file in the export format, or to stdout as JSON
+fn print_results(pid: u32, memory: &Memory, output: Option<&str>) -> Result<(), String> {
+    match output {
+        Some(output) => {
+            let count = export_memory(pid, memory, Path::new(output))
+                .map_err(|e| format!("can't write {}: {}", output, e))?;
+            print_json(&Saved { pid: pid, count: count, out: output })
+        },
+        None => {
+            stdout_result(write_memory(pid, memory, io::stdout().lock(), ExportFormat::Json).map(|_| ()))
+        }
+    }
+}
+
+fn print_json(value: &impl Serialize) -> Result<(), String> {
+    let mut stdout = io::stdout().lock();
+    stdout_result(serde_json::to_writer_pretty(&mut stdout, value).map_err(io::Error::from)
+        .and_then(|_| writeln!(stdout)))
+}
+
+// A reader that stops early, like `mismem ps | head`, isn't an error
+fn stdout_result(result: io::Result<()>) -> Result<(), String> {
+    match result {
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result.map_err(|e| e.to_string()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mixed() -> Memory {
+        let mut memory = Memory::new();
+        memory.push(0x1000, &Datatype::B1, &[7]);
+        memory.push(0x2000, &Datatype::B4, &7u32.to_ne_bytes());
+        memory
+    }
+
+    #[test]
+    fn type_picked_from_mixed_results() {
+        let mut memory = mixed();
+        assert_eq!(results_type(None, &mut memory, "in.json").err().unwrap(), "in.json holds several types, pick one with --type");
+        assert_eq!(results_type(Some(Datatype::B8), &mut memory, "in.json").err().unwrap(), "in.json has no u64 results");
+        assert_eq!(memory.len(), 2);
+
+        assert_eq!(results_type(Some(Datatype::B4), &mut memory, "in.json").unwrap().name(), "u32");
+        assert_eq!(memory.iter().map(|[address, ..]| address).collect::<Vec<_>>(), ["0000000000002000:u32"]);
+        assert_eq!(results_type(None, &mut memory, "in.json").unwrap().name(), "u32");
+    }
+}
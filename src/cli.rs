@@ -0,0 +1,451 @@
+use crate::mem::Datatype;
+use crate::snapshot::Change;
+
+
+pub const USAGE : &str = "\
+Usage: mismem [OPTIONS]
+       mismem run [RUN OPTIONS] [--] <PROGRAM> [ARGS]...
+       mismem ps
+       mismem scan (--pid <PID> | --name <NAME>) --type <TYPE> --value <VALUE> [--out <FILE>]
+       mismem filter --in <FILE> --value <VALUE> [--type <TYPE>] [--pid <PID>] [--out <FILE>]
+       mismem read (--pid <PID> | --name <NAME>) --addr <ADDRESS> --type <TYPE>
+       mismem write (--pid <PID> | --name <NAME>) --addr <ADDRESS> --type <TYPE> --value <VALUE>
+       mismem script <FILE> [--pid <PID> | --name <NAME>]
+       mismem snapshot (--pid <PID> | --name <NAME>) --out <FILE>
+       mismem compare <SNAPSHOT> [<SNAPSHOT>] --type <TYPE> (--change <CHANGE> | --value <VALUE>) [--in <FILE>] [--pid <PID>] [--out <FILE>]
+       mismem savestate (--pid <PID> | --name <NAME>) --out <FILE>
+       mismem restore <FILE> [--pid <PID> | --name <NAME>] [--force]
+       mismem --core <FILE>
+
+Options:
+  -p, --pid <PID>        Attach to the process with this PID
+  -n, --name <NAME>      Attach to the newest process with this name, again whenever it restarts
+  -w, --wait             Wait until the process is running instead of failing
+  -l, --listen <ADDR>    Serve JSON-RPC on a Unix socket path or a localhost address like 127.0.0.1:7777
+  -r, --remote <ADDR>    Work on the processes of the mismem-agent at ADDR, like 10.0.0.2:7780,
+                         subcommands too except savestate and restore
+  -c, --core <FILE>      Open the process saved in an ELF core file, read-only, instead of a live one,
+                         subcommands then need no --pid or --name
+  -h, --help             Print this help
+
+Run options:
+  -e, --env <KEY=VALUE>  Set an environment variable for the program
+  -C, --cwd <DIR>        Start the program in this directory
+
+Subcommand options:
+  -t, --type <TYPE>      u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, f32 or f64
+  -v, --value <VALUE>    Value to look for or to write
+  -a, --addr <ADDRESS>   Address expression, like game+0x10 or [[game+0x1F0]+0x18]
+  -i, --in <FILE>        Results of a previous scan, JSON or CSV
+  -o, --out <FILE>       Write results to FILE instead of printing them
+  -g, --change <CHANGE>  changed, unchanged, increased or decreased since the first snapshot
+  -f, --force            Restore the regions still in place when others moved
+
+The subcommands print JSON and never start the interface, script runs a Rhai
+script file and prints what it prints. snapshot saves the writable memory of the process,
+compare looks for values that changed from a snapshot to a second one, or to the process
+as it is now (the snapshot's PID unless --pid or --name is given). With --in only those
+results are compared. savestate saves the private writable memory of the process and
+restore writes it back, into the saved PID unless --pid or --name is given. The process is
+stopped meanwhile, nothing is restored if a region moved or changed size.";
+
+
+#[derive(Default)]
+pub struct Options {
+    pub pid: Option<u32>,
+    pub name: Option<String>,
+    pub wait: bool,
+    pub listen: Option<String>,
+    pub remote: Option<String>,
+    pub core: Option<String>,
+    pub help: bool,
+    pub launch: Option<Launch>,
+    pub command: Option<Command>,
+}
+
+// Program for `mismem run`
+#[derive(Default)]
+pub struct Launch {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub cwd: Option<String>,
+}
+
+// Non-interactive subcommands, the process is given by pid or name
+pub enum Command {
+    Ps,
+    Scan { pid: Option<u32>, name: Option<String>, datatype: Datatype, value: String, output: Option<String> },
+    Filter { input: String, pid: Option<u32>, datatype: Option<Datatype>, value: String, output: Option<String> },
+    Read { pid: Option<u32>, name: Option<String>, address: String, datatype: Datatype },
+    Write { pid: Option<u32>, name: Option<String>, address: String, datatype: Datatype, value: String },
+    Script { path: String, pid: Option<u32>, name: Option<String> },
+    Snapshot { pid: Option<u32>, name: Option<String>, output: String },
+    Savestate { pid: Option<u32>, name: Option<String>, output: String },
+    Restore { path: String, pid: Option<u32>, name: Option<String>, force: bool },
+    Compare {
+        old: String,
+        new: Option<String>,
+        input: Option<String>,
+        pid: Option<u32>,
+        name: Option<String>,
+        datatype: Option<Datatype>,
+        change: Option<Change>,
+        value: Option<String>,
+        output: Option<String>,
+    },
+}
+
+// Splits --name=value into flag and value
+fn split_flag(arg: &str) -> (String, Option<String>) {
+    match arg.split_once('=') {
+        Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+        _ => (arg.to_string(), None)
+    }
+}
+
+pub fn parse(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut args = args.peekable();
+    match args.peek().map(|a| a.as_str()) {
+        Some("run") => {
+            args.next();
+            return parse_run(args);
+        },
+        Some(command @ ("ps" | "scan" | "filter" | "read" | "write" | "script" | "snapshot" | "compare" | "savestate" | "restore")) => {
+            let command = command.to_string();
+            args.next();
+            return parse_command(&command, args);
+        },
+        _ => {}
+    }
+
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline) = split_flag(&arg);
+        let mut value = |name: &str| inline.clone().or_else(|| args.next())
+            .ok_or_else(|| format!("{} needs a value", name));
+
+        match flag.as_str() {
+            "-p" | "--pid" => {
+                let pid = value("--pid")?;
+                options.pid = Some(pid.parse().map_err(|_| format!("invalid PID '{}'", pid))?);
+            },
+            "-n" | "--name" => options.name = Some(value("--name")?),
+            "-w" | "--wait" => options.wait = true,
+            "-l" | "--listen" => options.listen = Some(value("--listen")?),
+            "-r" | "--remote" => options.remote = Some(value("--remote")?),
+            "-c" | "--core" => options.core = Some(value("--core")?),
+            "-h" | "--help" => options.help = true,
+            _ => return Err(format!("unexpected argument '{}'", arg))
+        }
+    }
+
+    if options.pid.is_some() && options.name.is_some() {
+        return Err(String::from("--pid and --name can't be used together"));
+    }
+    if options.core.is_some() && (options.pid.is_some() || options.name.is_some() || options.remote.is_some()) {
+        return Err(String::from("--core can't be used with --pid, --name or --remote"));
+    }
+    if options.wait && options.pid.is_none() && options.name.is_none() {
+        return Err(String::from("--wait needs --pid or --name"));
+    }
+    Ok(options)
+}
+
+
+// Flags stop at "--" or at the program, everything after goes to the program
+fn parse_run(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut launch = Launch::default();
+    let mut help = false;
+
+    while let Some(arg) = args.next() {
+        let (flag, inline) = split_flag(&arg);
+        let mut value = |name: &str| inline.clone().or_else(|| args.next())
+            .ok_or_else(|| format!("{} needs a value", name));
+
+        match flag.as_str() {
+            "-e" | "--env" => {
+                let env = value("--env")?;
+                let (key, value) = env.split_once('=').ok_or_else(|| format!("--env expects KEY=VALUE, got '{}'", env))?;
+                launch.env.push((key.to_string(), value.to_string()));
+            },
+            "-C" | "--cwd" => launch.cwd = Some(value("--cwd")?),
+            "-h" | "--help" => help = true,
+            "--" => {
+                launch.program = args.next().unwrap_or_default();
+                break;
+            },
+            _ if arg.starts_with('-') => return Err(format!("unexpected argument '{}'", arg)),
+            _ => {
+                launch.program = arg;
+                break;
+            }
+        }
+    }
+    launch.args = args.collect();
+
+    if launch.program.is_empty() && !help {
+        return Err(String::from("run needs a program to start"));
+    }
+    Ok(Options { help: help, launch: Some(launch), ..Options::default() })
+}
+
+
+#[derive(Default)]
+struct Flags {
+    pid: Option<u32>,
+    name: Option<String>,
+    datatype: Option<Datatype>,
+    value: Option<String>,
+    address: Option<String>,
+    input: Option<String>,
+    output: Option<String>,
+    core: Option<String>,
+    remote: Option<String>,
+    change: Option<Change>,
+    // Positional arguments, the script, the snapshots to compare or the state to restore
+    files: Vec<String>,
+    force: bool,
+    help: bool,
+}
+
+fn parse_command(command: &str, mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let allowed : &[&str] = match command {
+        "ps" => &["--remote"],
+        "scan" => &["--pid", "--name", "--type", "--value", "--out", "--core", "--remote"],
+        "filter" => &["--in", "--pid", "--type", "--value", "--out", "--core", "--remote"],
+        "read" => &["--pid", "--name", "--addr", "--type", "--core", "--remote"],
+        "script" => &["--pid", "--name", "--core", "--remote"],
+        "snapshot" => &["--pid", "--name", "--out", "--core", "--remote"],
+        // The process is stopped with signals from this machine
+        "savestate" => &["--pid", "--name", "--out"],
+        "restore" => &["--pid", "--name"],
+        "compare" => &["--pid", "--name", "--type", "--value", "--change", "--in", "--out", "--core", "--remote"],
+        _ => &["--pid", "--name", "--addr", "--type", "--value", "--core", "--remote"],
+    };
+    let mut flags = Flags::default();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline) = split_flag(&arg);
+        let flag = match flag.as_str() {
+            "-p" | "--pid" => "--pid",
+            "-n" | "--name" => "--name",
+            "-t" | "--type" => "--type",
+            "-v" | "--value" => "--value",
+            "-a" | "--addr" => "--addr",
+            "-i" | "--in" => "--in",
+            "-o" | "--out" => "--out",
+            "-c" | "--core" => "--core",
+            "-r" | "--remote" => "--remote",
+            "-g" | "--change" => "--change",
+            "-h" | "--help" => {
+                flags.help = true;
+                continue;
+            },
+            "-f" | "--force" if command == "restore" => {
+                flags.force = true;
+                continue;
+            },
+            // The script file, the state or up to two snapshots are the only positional arguments
+            _ if !arg.starts_with('-') && match command {
+                "script" | "restore" => flags.files.is_empty(),
+                "compare" => flags.files.len() < 2,
+                _ => false
+            } => {
+                flags.files.push(arg);
+                continue;
+            },
+            _ => return Err(format!("unexpected argument '{}'", arg))
+        };
+        if !allowed.contains(&flag) {
+            return Err(format!("{} doesn't take {}", command, flag));
+        }
+        // Values may start with a dash, like negative numbers
+        let value = inline.or_else(|| args.next()).ok_or_else(|| format!("{} needs a value", flag))?;
+
+        match flag {
+            "--pid" => flags.pid = Some(value.parse().map_err(|_| format!("invalid PID '{}'", value))?),
+            "--name" => flags.name = Some(value),
+            "--type" => flags.datatype = Some(Datatype::from_name(&value).ok_or_else(|| format!("unknown type '{}'", value))?),
+            "--value" => flags.value = Some(value),
+            "--addr" => flags.address = Some(value),
+            "--in" => flags.input = Some(value),
+            "--core" => flags.core = Some(value),
+            "--remote" => flags.remote = Some(value),
+            "--change" => flags.change = Some(Change::from_name(&value)
+                .ok_or_else(|| format!("unknown change '{}', expected {}", value, Change::NAMES.join(", ")))?),
+            _ => flags.output = Some(value),
+        }
+    }
+
+    if flags.help {
+        return Ok(Options { help: true, ..Options::default() });
+    }
+    if flags.pid.is_some() && flags.name.is_some() {
+        return Err(String::from("--pid and --name can't be used together"));
+    }
+    if flags.core.is_some() && flags.remote.is_some() {
+        return Err(String::from("--core can't be used with --remote"));
+    }
+    if !matches!(command, "ps" | "filter" | "script" | "compare" | "restore") && flags.pid.is_none() && flags.name.is_none() && flags.core.is_none() {
+        return Err(match command {
+            // Core files can't be stopped or written
+            "savestate" => String::from("savestate needs --pid or --name"),
+            _ => format!("{} needs --pid, --name or --core", command),
+        });
+    }
+    let required = |value: Option<String>, flag: &str| value.ok_or_else(|| format!("{} needs {}", command, flag));
+    let datatype = flags.datatype.ok_or_else(|| format!("{} needs --type", command));
+
+    let (core, remote) = (flags.core, flags.remote);
+    let command = match command {
+        "ps" => Command::Ps,
+        "scan" => Command::Scan {
+            pid: flags.pid,
+            name: flags.name,
+            datatype: datatype?,
+            value: required(flags.value, "--value")?,
+            output: flags.output,
+        },
+        "filter" => Command::Filter {
+            input: required(flags.input, "--in")?,
+            pid: flags.pid,
+            datatype: flags.datatype,
+            value: required(flags.value, "--value")?,
+            output: flags.output,
+        },
+        "script" => Command::Script {
+            path: flags.files.pop().ok_or_else(|| String::from("script needs a file to run"))?,
+            pid: flags.pid,
+            name: flags.name,
+        },
+        "snapshot" => Command::Snapshot {
+            pid: flags.pid,
+            name: flags.name,
+            output: required(flags.output, "--out")?,
+        },
+        "savestate" => Command::Savestate {
+            pid: flags.pid,
+            name: flags.name,
+            output: required(flags.output, "--out")?,
+        },
+        "restore" => Command::Restore {
+            path: flags.files.pop().ok_or_else(|| String::from("restore needs a state to restore"))?,
+            pid: flags.pid,
+            name: flags.name,
+            force: flags.force,
+        },
+        "compare" => {
+            if flags.change.is_some() == flags.value.is_some() {
+                return Err(String::from("compare needs either --change or --value"));
+            }
+            if flags.datatype.is_none() && flags.input.is_none() {
+                return Err(String::from("compare needs --type"));
+            }
+            let mut files = flags.files.into_iter();
+            Command::Compare {
+                old: files.next().ok_or_else(|| String::from("compare needs a snapshot"))?,
+                new: files.next(),
+                input: flags.input,
+                pid: flags.pid,
+                name: flags.name,
+                datatype: flags.datatype,
+                change: flags.change,
+                value: flags.value,
+                output: flags.output,
+            }
+        },
+        "read" => Command::Read {
+            pid: flags.pid,
+            name: flags.name,
+            address: required(flags.address, "--addr")?,
+            datatype: datatype?,
+        },
+        _ => Command::Write {
+            pid: flags.pid,
+            name: flags.name,
+            address: required(flags.address, "--addr")?,
+            datatype: datatype?,
+            value: required(flags.value, "--value")?,
+        },
+    };
+    Ok(Options { command: Some(command), core: core, remote: remote, ..Options::default() })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Options, String> {
+        parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn pid_and_name() {
+        let options = parse_args(&["-p", "1234", "--wait"]).unwrap();
+        assert_eq!((options.pid, options.name, options.wait), (Some(1234), None, true));
+
+        let options = parse_args(&["--name=game.bin"]).unwrap();
+        assert_eq!(options.name.as_deref(), Some("game.bin"));
+        assert_eq!(parse_args(&["-n", "game.bin"]).unwrap().name.as_deref(), Some("game.bin"));
+        assert!(parse_args(&["--help"]).unwrap().help);
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        assert_eq!(parse_args(&["--pid", "abc"]).err().unwrap(), "invalid PID 'abc'");
+        assert_eq!(parse_args(&["--name"]).err().unwrap(), "--name needs a value");
+        assert_eq!(parse_args(&["--verbose"]).err().unwrap(), "unexpected argument '--verbose'");
+        assert_eq!(parse_args(&["-p", "1", "-n", "x"]).err().unwrap(), "--pid and --name can't be used together");
+        assert_eq!(parse_args(&["-w"]).err().unwrap(), "--wait needs --pid or --name");
+    }
+
+    #[test]
+    fn core_file() {
+        assert_eq!(parse_args(&["--core", "game.core"]).unwrap().core.as_deref(), Some("game.core"));
+        assert_eq!(parse_args(&["-c", "game.core", "-p", "1"]).err().unwrap(), "--core can't be used with --pid, --name or --remote");
+    }
+
+    #[test]
+    fn scan_and_filter_commands() {
+        match parse_args(&["scan", "-n", "game", "--type=i32", "-v", "-5", "-o", "out.json"]).unwrap().command {
+            Some(Command::Scan { pid: None, name: Some(name), datatype, value, output: Some(output) }) => {
+                assert_eq!((name.as_str(), datatype.name(), value.as_str(), output.as_str()), ("game", "i32", "-5", "out.json"));
+            },
+            _ => panic!("expected a scan")
+        }
+
+        match parse_args(&["filter", "--in", "out.json", "--value", "7"]).unwrap().command {
+            Some(Command::Filter { input, pid: None, datatype: None, value, output: None }) => {
+                assert_eq!((input.as_str(), value.as_str()), ("out.json", "7"));
+            },
+            _ => panic!("expected a filter")
+        }
+        assert!(matches!(parse_args(&["ps"]).unwrap().command, Some(Command::Ps)));
+        assert!(parse_args(&["read", "--help"]).unwrap().help);
+    }
+
+    #[test]
+    fn remote_commands() {
+        let options = parse_args(&["read", "-r", "10.0.0.2:7780", "-p", "1", "-a", "0x10", "-t", "u8"]).unwrap();
+        assert_eq!(options.remote.as_deref(), Some("10.0.0.2:7780"));
+        assert!(matches!(options.command, Some(Command::Read { pid: Some(1), .. })));
+        assert_eq!(parse_args(&["ps", "--remote=10.0.0.2:7780"]).unwrap().remote.as_deref(), Some("10.0.0.2:7780"));
+
+        let error = |args: &[&str]| parse_args(args).err().unwrap();
+        assert_eq!(error(&["savestate", "-r", "10.0.0.2:7780", "-p", "1", "-o", "a.state"]), "savestate doesn't take --remote");
+        assert_eq!(error(&["snapshot", "-r", "10.0.0.2:7780", "-c", "game.core", "-o", "a.snap"]), "--core can't be used with --remote");
+    }
+
+    #[test]
+    fn invalid_commands() {
+        let error = |args: &[&str]| parse_args(args).err().unwrap();
+        assert_eq!(error(&["ps", "--pid", "1"]), "ps doesn't take --pid");
+        assert_eq!(error(&["scan", "--type", "u24"]), "unknown type 'u24'");
+        assert_eq!(error(&["read", "--addr", "game+0x10", "--type", "u8"]), "read needs --pid, --name or --core");
+        assert_eq!(error(&["write", "-p", "1", "-a", "0x10", "-t", "u8"]), "write needs --value");
+        assert_eq!(error(&["filter", "--value", "1"]), "filter needs --in");
+    }
+}
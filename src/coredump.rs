@@ -0,0 +1,546 @@
+//! ELF core files from `gcore` or a crash, opened instead of a live process
+//!
+//! After [`open`] every function of [`os`](crate::os) sees the dumped process alone: `PT_LOAD`
+//! segments are its regions and the `NT_FILE` note names its modules. Writes are refused.
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+    sync::{Mutex, OnceLock},
+};
+
+use crate::process::{Mapping, Module, ProcessDetails, ProcessInfo, Region};
+
+
+const PT_LOAD : u32 = 1;
+const PT_NOTE : u32 = 4;
+const NT_PRSTATUS : u32 = 1;
+const NT_PRPSINFO : u32 = 3;
+const NT_FILE : u32 = 0x46494c45;
+
+// Segment of the dump, only the first filesz bytes of it are in the file
+struct Segment {
+    region: Region,
+    offset: u64,
+}
+
+// File mapped in the process, from NT_FILE
+struct FileMapping {
+    start: usize,
+    end: usize,
+    offset: u64,
+    path: String,
+}
+
+struct Core {
+    path: String,
+    file: Mutex<File>,
+    is_64bit: bool,
+    segments: Vec<Segment>,
+    files: Vec<FileMapping>,
+    pid: u32,
+    ppid: u32,
+    uid: u32,
+    gid: u32,
+    name: String,
+    cmdline: String,
+    threads: Vec<u32>,
+}
+
+static CORE : OnceLock<Core> = OnceLock::new();
+
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+// Fields of the file, in its byte order
+struct Reader<'a> {
+    bytes: &'a [u8],
+    big_endian: bool,
+    is_64bit: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, offset: usize, len: usize) -> io::Result<&'a [u8]> {
+        offset.checked_add(len).and_then(|end| self.bytes.get(offset..end)).ok_or_else(|| invalid("truncated core file"))
+    }
+
+    fn u16(&self, offset: usize) -> io::Result<u16> {
+        let bytes = self.bytes(offset, 2)?.try_into().unwrap();
+        Ok(if self.big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    }
+
+    fn u32(&self, offset: usize) -> io::Result<u32> {
+        let bytes = self.bytes(offset, 4)?.try_into().unwrap();
+        Ok(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    fn u64(&self, offset: usize) -> io::Result<u64> {
+        let bytes = self.bytes(offset, 8)?.try_into().unwrap();
+        Ok(if self.big_endian { u64::from_be_bytes(bytes) } else { u64::from_le_bytes(bytes) })
+    }
+
+    // unsigned long of the dumped process
+    fn word(&self, offset: usize) -> io::Result<u64> {
+        if self.is_64bit { self.u64(offset) } else { self.u32(offset).map(u64::from) }
+    }
+
+    fn word_size(&self) -> usize {
+        if self.is_64bit { 8 } else { 4 }
+    }
+
+    fn string(&self, offset: usize, len: usize) -> io::Result<String> {
+        let bytes = self.bytes(offset, len)?;
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(len);
+        Ok(String::from_utf8_lossy(&bytes[..end]).trim().to_string())
+    }
+}
+
+
+/// Opens the core file at path for the rest of the program, returns the pid of the dumped process
+pub fn open(path: &str) -> io::Result<u32> {
+    let core = parse(path)?;
+    let pid = core.pid;
+    CORE.set(core).map_err(|_| io::Error::new(io::ErrorKind::AlreadyExists, "a core file is already open"))?;
+    Ok(pid)
+}
+
+/// Path given to [`open`], None when processes are live
+pub fn path() -> Option<&'static str> {
+    CORE.get().map(|core| core.path.as_str())
+}
+
+/// Pid of the dumped process
+pub fn pid() -> Option<u32> {
+    CORE.get().map(|core| core.pid)
+}
+
+pub(crate) fn is_open() -> bool {
+    CORE.get().is_some()
+}
+
+
+fn parse(path: &str) -> io::Result<Core> {
+    let mut file = File::open(path)?;
+
+    // Headers and notes are at the start, memory is read on demand
+    let mut header = [0u8; 64];
+    file.read_exact(&mut header).map_err(|_| invalid("not an ELF file"))?;
+    if header[..4] != *b"\x7fELF" {
+        return Err(invalid("not an ELF file"));
+    }
+    let is_64bit = header[4] == 2;
+    let big_endian = header[5] == 2;
+    let elf = Reader { bytes: &header, big_endian: big_endian, is_64bit: is_64bit };
+    if elf.u16(16)? != 4 {
+        return Err(invalid("not a core file (ELF type isn't ET_CORE)"));
+    }
+
+    let (phoff, phentsize, phnum) = if is_64bit {
+        (elf.u64(0x20)?, elf.u16(0x36)? as usize, elf.u16(0x38)? as usize)
+    } else {
+        (elf.u32(0x1C)? as u64, elf.u16(0x2A)? as usize, elf.u16(0x2C)? as usize)
+    };
+    let file_size = file.metadata()?.len();
+    if phoff.checked_add((phentsize * phnum) as u64).filter(|&end| end <= file_size).is_none() {
+        return Err(invalid("program headers past the end of the file"));
+    }
+    let mut headers = vec![0u8; phentsize * phnum];
+    file.seek(SeekFrom::Start(phoff))?;
+    file.read_exact(&mut headers)?;
+    let headers = Reader { bytes: &headers, big_endian: big_endian, is_64bit: is_64bit };
+
+    let mut core = Core {
+        path: path.to_string(),
+        file: Mutex::new(file),
+        is_64bit: is_64bit,
+        segments: vec![],
+        files: vec![],
+        pid: 0,
+        ppid: 0,
+        uid: 0,
+        gid: 0,
+        name: Path::new(path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        cmdline: String::new(),
+        threads: vec![],
+    };
+    let mut notes = vec![];
+
+    for i in 0..phnum {
+        let at = i * phentsize;
+        // p_type, p_flags, p_offset, p_vaddr, p_filesz
+        let (kind, flags, offset, vaddr, filesz) = if is_64bit {
+            (headers.u32(at)?, headers.u32(at + 4)?, headers.u64(at + 8)?, headers.u64(at + 16)?, headers.u64(at + 32)?)
+        } else {
+            (headers.u32(at)?, headers.u32(at + 24)?, headers.u32(at + 4)? as u64, headers.u32(at + 8)? as u64, headers.u32(at + 16)? as u64)
+        };
+
+        match kind {
+            // Segments left out of the dump have no bytes in the file
+            PT_LOAD if filesz > 0 => {
+                if vaddr.checked_add(filesz).and_then(|end| usize::try_from(end).ok()).is_none() {
+                    return Err(invalid("PT_LOAD segment past the end of the address space"));
+                }
+                core.segments.push(Segment {
+                    region: Region {
+                        base: vaddr as usize,
+                        size: filesz as usize,
+                        readable: flags & 4 != 0,
+                        writable: flags & 2 != 0,
+                        executable: flags & 1 != 0,
+                        shared: false,
+                    },
+                    offset: offset,
+                })
+            },
+            PT_NOTE => {
+                if offset.checked_add(filesz).filter(|&end| end <= file_size).is_none() {
+                    return Err(invalid("PT_NOTE segment past the end of the file"));
+                }
+                let mut bytes = vec![0u8; filesz as usize];
+                let mut file = core.file.lock().unwrap();
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_exact(&mut bytes)?;
+                notes.extend(bytes);
+            },
+            _ => {}
+        }
+    }
+    core.segments.sort_by_key(|s| s.region.base);
+
+    let notes = Reader { bytes: &notes, big_endian: big_endian, is_64bit: is_64bit };
+    let align = |n: usize| (n + 3) & !3;
+    let mut at = 0;
+    while at + 12 <= notes.bytes.len() {
+        let (namesz, descsz, kind) = (notes.u32(at)? as usize, notes.u32(at + 4)? as usize, notes.u32(at + 8)?);
+        let name = notes.string(at + 12, namesz)?;
+        let desc = at + 12 + align(namesz);
+        at = desc + align(descsz);
+        if name != "CORE" {
+            continue;
+        }
+
+        let word = notes.word_size();
+        match kind {
+            // One per thread, the first is the thread that crashed
+            NT_PRSTATUS => {
+                let tid = notes.u32(desc + 16 + 2 * word)?;
+                core.threads.push(tid);
+            },
+            NT_PRPSINFO if is_64bit => {
+                core.uid = notes.u32(desc + 16)?;
+                core.gid = notes.u32(desc + 20)?;
+                core.pid = notes.u32(desc + 24)?;
+                core.ppid = notes.u32(desc + 28)?;
+                core.name = notes.string(desc + 40, 16)?;
+                core.cmdline = notes.string(desc + 56, 80)?;
+            },
+            NT_PRPSINFO => {
+                core.uid = notes.u16(desc + 8)? as u32;
+                core.gid = notes.u16(desc + 10)? as u32;
+                core.pid = notes.u32(desc + 12)?;
+                core.ppid = notes.u32(desc + 16)?;
+                core.name = notes.string(desc + 28, 16)?;
+                core.cmdline = notes.string(desc + 44, 80)?;
+            },
+            // count, page size, (start, end, page offset) per file, then the paths
+            NT_FILE => {
+                let count = notes.word(desc)? as usize;
+                let page_size = notes.word(desc + word)?;
+                let table = count.checked_mul(3).and_then(|n| n.checked_add(2)).and_then(|n| n.checked_mul(word))
+                    .filter(|&table| table <= descsz)
+                    .ok_or_else(|| invalid("corrupt NT_FILE note"))?;
+                let mut paths = notes.bytes(desc + table, descsz - table)?
+                    .split(|&b| b == 0)
+                    .map(|p| String::from_utf8_lossy(p).to_string());
+                for i in 0..count {
+                    let entry = desc + (2 + 3 * i) * word;
+                    let (start, end) = (notes.word(entry)? as usize, notes.word(entry + word)? as usize);
+                    if end < start {
+                        return Err(invalid("corrupt NT_FILE note"));
+                    }
+                    core.files.push(FileMapping {
+                        start: start,
+                        end: end,
+                        offset: notes.word(entry + 2 * word)?.checked_mul(page_size).ok_or_else(|| invalid("corrupt NT_FILE note"))?,
+                        path: paths.next().unwrap_or_default(),
+                    });
+                }
+            },
+            _ => {}
+        }
+    }
+
+    if core.pid == 0 {
+        core.pid = core.threads.first().copied().ok_or_else(|| invalid("core file without process information"))?;
+    }
+    Ok(core)
+}
+
+
+// Same functions as the live backends, for the one dumped process
+
+fn dumped(pid : u32) -> Option<&'static Core> {
+    CORE.get().filter(|core| core.pid == pid)
+}
+
+pub(crate) fn enum_processes() -> Vec<ProcessInfo> {
+    CORE.get().map(|core| ProcessInfo {
+        name: core.name.clone(),
+        memory: core.segments.iter().map(|s| s.region.size).sum::<usize>() as f64 / 1024.0,
+        pid: core.pid,
+        ppid: core.ppid,
+        user: core.uid.to_string(),
+        cmdline: core.cmdline.clone(),
+        start_time: 0,
+    }).into_iter().collect()
+}
+
+pub(crate) fn process_details(pid : u32) -> io::Result<ProcessDetails> {
+    let core = dumped(pid).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("process {} isn't in the core file", pid)))?;
+    Ok(ProcessDetails {
+        exe: core.files.first().map(|f| f.path.clone()).unwrap_or_default(),
+        cmdline: core.cmdline.clone(),
+        environment: vec![],
+        user: core.uid.to_string(),
+        group: core.gid.to_string(),
+        threads: core.threads.clone(),
+        start_time: 0,
+        resident: (core.segments.iter().map(|s| s.region.size).sum::<usize>() / 1024) as u64,
+        virtual_size: (core.segments.iter().map(|s| s.region.size).sum::<usize>() / 1024) as u64,
+    })
+}
+
+pub(crate) fn check_process(pid : u32) -> bool {
+    dumped(pid).is_some()
+}
+
+pub(crate) fn enum_mappings(pid : u32) -> Vec<Mapping> {
+    let core = match dumped(pid) {
+        Some(core) => core,
+        None => return vec![]
+    };
+    core.segments.iter().map(|s| {
+        let file = core.files.iter().find(|f| f.start <= s.region.base && s.region.base < f.end);
+        Mapping {
+            region: Region { ..s.region },
+            offset: file.map_or(0, |f| f.offset + (s.region.base - f.start) as u64),
+            path: file.map(|f| f.path.clone()).unwrap_or_default(),
+        }
+    }).collect()
+}
+
+// Mappings of the same file are merged into one module, like on Linux
+pub(crate) fn enum_modules(pid : u32) -> Vec<Module> {
+    let mut modules = Vec::<(String, Module)>::new();
+    for file in dumped(pid).map(|core| core.files.as_slice()).unwrap_or_default() {
+        match modules.iter_mut().find(|(path, _)| *path == file.path) {
+            Some((_, module)) => {
+                let end = (module.base + module.size).max(file.end);
+                module.base = module.base.min(file.start);
+                module.size = end - module.base;
+            },
+            None => {
+                let name = Path::new(&file.path).file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| file.path.clone());
+                modules.push((file.path.clone(), Module { name: name, base: file.start, size: file.end - file.start }));
+            }
+        }
+    }
+    modules.into_iter().map(|(_, module)| module).collect()
+}
+
+pub(crate) fn enum_regions(pid : u32) -> Vec<Region> {
+    dumped(pid).map(|core| core.segments.iter().map(|s| Region { ..s.region }).collect()).unwrap_or_default()
+}
+
+pub(crate) fn pointer_size(pid : u32) -> usize {
+    match dumped(pid) {
+        Some(core) if !core.is_64bit => 4,
+        _ => 8
+    }
+}
+
+// Reads may span adjacent segments, bytes left out of the dump can't be read
+pub(crate) fn read_process(pid : u32, address : usize, buffer: &mut [u8]) -> bool {
+    let core = match dumped(pid) {
+        Some(core) => core,
+        None => return false
+    };
+    let mut file = core.file.lock().unwrap();
+
+    let mut done = 0;
+    while done < buffer.len() {
+        let at = address + done;
+        let i = core.segments.partition_point(|s| s.region.base + s.region.size <= at);
+        let segment = match core.segments.get(i).filter(|s| s.region.base <= at) {
+            Some(segment) => segment,
+            None => return false
+        };
+        let len = (buffer.len() - done).min(segment.region.base + segment.region.size - at);
+        let read = file.seek(SeekFrom::Start(segment.offset + (at - segment.region.base) as u64))
+            .and_then(|_| file.read_exact(&mut buffer[done..done + len]));
+        if read.is_err() {
+            return false;
+        }
+        done += len;
+    }
+    true
+}
+
+pub(crate) fn write_process(_pid : u32, address : usize, _target_bytes: &[u8]) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("can't write at {:X}, core dumps are read-only", address)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(kind: u32, desc: &[u8]) -> Vec<u8> {
+        let mut note = vec![];
+        note.extend(5u32.to_le_bytes());
+        note.extend((desc.len() as u32).to_le_bytes());
+        note.extend(kind.to_le_bytes());
+        note.extend(b"CORE\0\0\0\0");
+        note.extend(desc);
+        note.resize((note.len() + 3) & !3, 0);
+        note
+    }
+
+    fn prpsinfo() -> Vec<u8> {
+        let mut desc = vec![0u8; 136];
+        desc[16..20].copy_from_slice(&1000u32.to_le_bytes());
+        desc[20..24].copy_from_slice(&100u32.to_le_bytes());
+        desc[24..28].copy_from_slice(&4321u32.to_le_bytes());
+        desc[28..32].copy_from_slice(&1u32.to_le_bytes());
+        desc[40..44].copy_from_slice(b"game");
+        desc[56..70].copy_from_slice(b"./game --level");
+        desc
+    }
+
+    fn file_note(count: u64, entries: &[(u64, u64, u64)], paths: &[u8]) -> Vec<u8> {
+        let mut desc = vec![];
+        desc.extend(count.to_le_bytes());
+        desc.extend(0x1000u64.to_le_bytes());
+        for &(start, end, page) in entries {
+            desc.extend(start.to_le_bytes());
+            desc.extend(end.to_le_bytes());
+            desc.extend(page.to_le_bytes());
+        }
+        desc.extend(paths);
+        desc
+    }
+
+    // 64-bit little endian core with a PT_NOTE and a PT_LOAD of 16 bytes at 0x400000
+    fn core_file(notes: &[u8], note_size: u64) -> Vec<u8> {
+        let notes_offset = 64 + 2 * 56;
+        let data_offset = notes_offset + notes.len();
+
+        let mut elf = vec![0u8; 64];
+        elf[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        elf[16..18].copy_from_slice(&4u16.to_le_bytes());
+        elf[0x20..0x28].copy_from_slice(&64u64.to_le_bytes());
+        elf[0x36..0x38].copy_from_slice(&56u16.to_le_bytes());
+        elf[0x38..0x3A].copy_from_slice(&2u16.to_le_bytes());
+
+        for (kind, flags, offset, vaddr, filesz) in [(PT_NOTE, 0, notes_offset, 0, note_size), (PT_LOAD, 6, data_offset, 0x400000, 16)] {
+            let mut header = vec![0u8; 56];
+            header[0..4].copy_from_slice(&kind.to_le_bytes());
+            header[4..8].copy_from_slice(&(flags as u32).to_le_bytes());
+            header[8..16].copy_from_slice(&(offset as u64).to_le_bytes());
+            header[16..24].copy_from_slice(&(vaddr as u64).to_le_bytes());
+            header[32..40].copy_from_slice(&filesz.to_le_bytes());
+            elf.extend(header);
+        }
+
+        elf.extend(notes);
+        elf.extend(0..16u8);
+        elf
+    }
+
+    fn parse_bytes(name: &str, bytes: &[u8]) -> io::Result<Core> {
+        let path = std::env::temp_dir().join(format!("mismem-{}-{}.core", std::process::id(), name));
+        std::fs::write(&path, bytes).unwrap();
+        let core = parse(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        core
+    }
+
+    #[test]
+    fn process_information() {
+        let notes = note(NT_PRPSINFO, &prpsinfo());
+        let core = parse_bytes("prpsinfo", &core_file(&notes, notes.len() as u64)).unwrap();
+
+        assert_eq!((core.pid, core.ppid, core.uid, core.gid), (4321, 1, 1000, 100));
+        assert_eq!(core.name, "game");
+        assert_eq!(core.cmdline, "./game --level");
+        assert_eq!(core.segments.len(), 1);
+        assert_eq!((core.segments[0].region.base, core.segments[0].region.size), (0x400000, 16));
+        assert!(core.segments[0].region.writable && !core.segments[0].region.executable);
+    }
+
+    #[test]
+    fn file_mappings() {
+        let files = file_note(2, &[(0x400000, 0x401000, 0), (0x7f0000, 0x7f2000, 3)], b"/bin/game\0/lib/libc.so\0");
+        let mut notes = note(NT_PRPSINFO, &prpsinfo());
+        notes.extend(note(NT_FILE, &files));
+        let core = parse_bytes("files", &core_file(&notes, notes.len() as u64)).unwrap();
+
+        assert_eq!(core.files.len(), 2);
+        assert_eq!((core.files[0].start, core.files[0].end, core.files[0].offset), (0x400000, 0x401000, 0));
+        assert_eq!(core.files[0].path, "/bin/game");
+        assert_eq!((core.files[1].start, core.files[1].end, core.files[1].offset), (0x7f0000, 0x7f2000, 0x3000));
+        assert_eq!(core.files[1].path, "/lib/libc.so");
+    }
+
+    #[test]
+    fn corrupt_file_note() {
+        let mut notes = note(NT_PRPSINFO, &prpsinfo());
+        notes.extend(note(NT_FILE, &file_note(u64::MAX / 2, &[], b"")));
+        let error = parse_bytes("corrupt", &core_file(&notes, notes.len() as u64)).err().unwrap();
+        assert_eq!(error.to_string(), "corrupt NT_FILE note");
+    }
+
+    #[test]
+    fn file_mapping_ends_before_start() {
+        let mut notes = note(NT_PRPSINFO, &prpsinfo());
+        notes.extend(note(NT_FILE, &file_note(1, &[(0x401000, 0x400000, 0)], b"/bin/game\0")));
+        let error = parse_bytes("backwards", &core_file(&notes, notes.len() as u64)).err().unwrap();
+        assert_eq!(error.to_string(), "corrupt NT_FILE note");
+    }
+
+    #[test]
+    fn headers_past_end_of_file() {
+        let notes = note(NT_PRPSINFO, &prpsinfo());
+        let mut elf = core_file(&notes, notes.len() as u64);
+        elf[0x20..0x28].copy_from_slice(&u64::MAX.to_le_bytes());
+        let error = parse_bytes("phoff", &elf).err().unwrap();
+        assert_eq!(error.to_string(), "program headers past the end of the file");
+
+        elf[0x20..0x28].copy_from_slice(&64u64.to_le_bytes());
+        elf[0x38..0x3A].copy_from_slice(&u16::MAX.to_le_bytes());
+        let error = parse_bytes("phnum", &elf).err().unwrap();
+        assert_eq!(error.to_string(), "program headers past the end of the file");
+    }
+
+    #[test]
+    fn load_segment_past_address_space() {
+        let notes = note(NT_PRPSINFO, &prpsinfo());
+        let mut elf = core_file(&notes, notes.len() as u64);
+        // p_vaddr of the PT_LOAD header
+        elf[64 + 56 + 16..64 + 56 + 24].copy_from_slice(&(u64::MAX - 8).to_le_bytes());
+        let error = parse_bytes("vaddr", &elf).err().unwrap();
+        assert_eq!(error.to_string(), "PT_LOAD segment past the end of the address space");
+    }
+
+    #[test]
+    fn note_past_end_of_file() {
+        let notes = note(NT_PRPSINFO, &prpsinfo());
+        let error = parse_bytes("past-end", &core_file(&notes, 1 << 40)).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
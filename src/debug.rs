@@ -0,0 +1,165 @@
+use std::{collections::BTreeMap, io};
+
+use tui::widgets::TableState;
+
+use crate::os::{read_process, Debugger};
+use crate::process::{DebugEvent, Register};
+
+
+// Breakpoints and stopped thread of the debugger, attached on the first breakpoint
+pub struct DebugSession {
+    pub pid: u32,
+    // Address and original byte, the int3 is hidden from the disassembly
+    pub breakpoints: BTreeMap<usize, u8>,
+    pub thread: Option<u32>,
+    pub position: Option<usize>,
+    pub registers: Vec<Register>,
+    pub register_state: TableState,
+
+    debugger: Option<Debugger>,
+}
+
+impl DebugSession {
+    pub fn new() -> DebugSession {
+        DebugSession {
+            pid: 0,
+            breakpoints: BTreeMap::new(),
+            thread: None,
+            position: None,
+            registers: vec![],
+            register_state: TableState::default(),
+
+            debugger: None,
+        }
+    }
+
+    pub fn is_attached(&self) -> bool {
+        self.debugger.is_some()
+    }
+
+    pub fn select_process(&mut self, pid: u32) {
+        if self.pid != pid {
+            self.detach();
+            self.pid = pid;
+        }
+    }
+
+    fn debugger(&self) -> io::Result<&Debugger> {
+        self.debugger.as_ref().ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "debugger is not attached"))
+    }
+
+    fn stopped_thread(&self) -> io::Result<u32> {
+        self.thread.ok_or_else(|| io::Error::other("process is running"))
+    }
+
+    // Returns whether a breakpoint is now set at the address
+    pub fn toggle_breakpoint(&mut self, address: usize) -> io::Result<bool> {
+        if self.breakpoints.contains_key(&address) {
+            self.debugger()?.remove_breakpoint(address)?;
+            self.breakpoints.remove(&address);
+            return Ok(false);
+        }
+
+        let mut original = [0u8];
+        if !read_process(self.pid, address, &mut original) {
+            return Err(io::Error::other(format!("can't read at {:X}", address)));
+        }
+        if self.debugger.is_none() {
+            self.debugger = Some(Debugger::attach(self.pid)?);
+        }
+        self.debugger()?.set_breakpoint(address)?;
+        self.breakpoints.insert(address, original[0]);
+        Ok(true)
+    }
+
+    pub fn poll(&mut self) -> Option<DebugEvent> {
+        let event = self.debugger.as_ref()?.poll()?;
+        match event {
+            DebugEvent::Breakpoint { tid, address } | DebugEvent::Step { tid, address } => {
+                self.thread = Some(tid);
+                self.position = Some(address);
+                self.load_registers();
+            },
+            DebugEvent::Exited => self.detach()
+        }
+        Some(event)
+    }
+
+    fn load_registers(&mut self) {
+        self.registers = match self.thread.map(|tid| self.debugger().and_then(|d| d.registers(tid))) {
+            Some(Ok(registers)) => registers,
+            _ => vec![]
+        };
+        if self.register_state.selected().is_none_or(|i| i >= self.registers.len()) {
+            self.register_state.select(if self.registers.is_empty() { None } else { Some(0) });
+        }
+    }
+
+    pub fn step(&mut self) -> io::Result<()> {
+        let tid = self.stopped_thread()?;
+        self.debugger()?.step(tid)?;
+        self.thread = None;
+        self.position = None;
+        Ok(())
+    }
+
+    pub fn resume(&mut self) -> io::Result<()> {
+        self.stopped_thread()?;
+        self.debugger()?.resume()?;
+        self.thread = None;
+        self.position = None;
+        self.registers.clear();
+        Ok(())
+    }
+
+    pub fn selected_register(&self) -> Option<&Register> {
+        self.registers.get(self.register_state.selected()?)
+    }
+
+    pub fn set_register(&mut self, value: u64) -> io::Result<()> {
+        let tid = self.stopped_thread()?;
+        let name = match self.selected_register() {
+            Some(register) => register.name,
+            None => return Ok(())
+        };
+
+        self.debugger()?.set_register(tid, name, value)?;
+        if name == "rip" {
+            self.position = Some(value as usize);
+        }
+        self.load_registers();
+        Ok(())
+    }
+
+    pub fn next_register(&mut self) {
+        if self.registers.is_empty() { return; }
+        self.register_state.select(Some(
+            self.register_state.selected().map_or(0, |i| (i + 1) % self.registers.len())
+        ));
+    }
+
+    pub fn previous_register(&mut self) {
+        if self.registers.is_empty() { return; }
+        self.register_state.select(Some(
+            self.register_state.selected().map_or(0, |i| (i + self.registers.len() - 1) % self.registers.len())
+        ));
+    }
+
+    // Dropping the debugger restores the original bytes and releases the threads
+    pub fn detach(&mut self) {
+        if let Some(mut debugger) = self.debugger.take() {
+            debugger.detach();
+        }
+        self.breakpoints.clear();
+        self.thread = None;
+        self.position = None;
+        self.registers.clear();
+    }
+
+    // Puts back the bytes hidden by breakpoints
+    pub fn overlay(&self, address: usize, data: &mut [u8]) {
+        for (&breakpoint, &original) in self.breakpoints.range(address..address + data.len()) {
+            data[breakpoint - address] = original;
+        }
+    }
+}
@@ -0,0 +1,414 @@
+use std::io;
+
+use iced_x86::{Decoder, DecoderOptions, FlowControl, Formatter, Instruction, IntelFormatter};
+use tui::widgets::TableState;
+
+use crate::expr::{AddressResolver, ProcessResolver};
+use crate::os::read_process;
+use crate::patch::PatchList;
+use crate::debug::DebugSession;
+
+
+pub enum DisasmState {
+    Browse,
+    Goto,
+    Patch,
+    Registers,
+    EditRegister,
+}
+
+pub struct DisasmLine {
+    pub address: usize,
+    pub bytes: Vec<u8>,
+    pub mnemonic: String,
+    pub operands: String,
+    pub target: Option<usize>,
+    pub annotation: String,
+}
+
+pub struct Disassembly {
+    pub state: DisasmState,
+    pub address: usize,
+    pub rows: usize,
+    pub lines: Vec<DisasmLine>,
+    pub table_state: TableState,
+    pub patches: PatchList,
+    pub debug: DebugSession,
+
+    history: Vec<usize>,
+    resolver: ProcessResolver,
+    bitness: u32,
+    decoded_rows: usize,
+}
+
+impl Disassembly {
+    const MAX_INSTRUCTION_LEN: usize = 15;
+    const LOOKBEHIND: usize = 2 * Disassembly::MAX_INSTRUCTION_LEN;
+    const PAGE_SIZE: usize = 0x1000;
+
+    pub fn new() -> Disassembly {
+        Disassembly {
+            state: DisasmState::Browse,
+            address: 0,
+            rows: 32,
+            lines: vec![],
+            table_state: TableState::default(),
+            patches: PatchList::new(),
+            debug: DebugSession::new(),
+
+            history: vec![],
+            resolver: ProcessResolver::default(),
+            bitness: 64,
+            decoded_rows: 0,
+        }
+    }
+
+    pub fn open(&mut self, pid: u32, address: usize) {
+        self.state = DisasmState::Browse;
+        self.resolver = ProcessResolver::new(pid);
+        self.bitness = self.resolver.pointer_size() as u32 * 8;
+        self.history.clear();
+        self.patches.select_process(pid);
+        self.debug.select_process(pid);
+        self.goto(pid, address);
+    }
+
+    fn goto(&mut self, pid: u32, address: usize) {
+        self.address = address;
+        self.table_state.select(Some(0));
+        self.decode(pid);
+    }
+
+    // Stops at the first unreadable page
+    fn read_code(pid: u32, address: usize, len: usize) -> Vec<u8> {
+        let mut data = vec![];
+        let end = address.saturating_add(len);
+        let mut start = address;
+
+        while start < end {
+            let stop = (start - start % Disassembly::PAGE_SIZE).saturating_add(Disassembly::PAGE_SIZE).min(end);
+            let mut buffer = vec![0u8; stop - start];
+            if !read_process(pid, start, &mut buffer) {
+                break;
+            }
+            data.extend(buffer);
+            start = stop;
+        }
+        data
+    }
+
+    pub fn decode(&mut self, pid: u32) {
+        self.decoded_rows = self.rows;
+        let mut data = Disassembly::read_code(pid, self.address, self.rows * Disassembly::MAX_INSTRUCTION_LEN);
+        self.debug.overlay(self.address, &mut data);
+        let mut decoder = Decoder::with_ip(self.bitness, &data, self.address as u64, DecoderOptions::NONE);
+        let mut formatter = IntelFormatter::new();
+        let mut instr = Instruction::default();
+
+        self.lines.clear();
+        while decoder.can_decode() && self.lines.len() < self.rows {
+            decoder.decode_out(&mut instr);
+
+            let address = instr.ip() as usize;
+            let offset = address - self.address;
+            let bytes = data[offset..(offset + instr.len()).min(data.len())].to_vec();
+            let patched = self.patches.find(address, bytes.len()).is_some();
+
+            if instr.is_invalid() {
+                self.lines.push(DisasmLine {
+                    address: address, bytes: bytes, mnemonic: String::from("(bad)"), operands: String::new(),
+                    target: None, annotation: if patched { String::from("patched") } else { String::new() },
+                });
+                continue;
+            }
+
+            let mut mnemonic = String::new();
+            let mut operands = String::new();
+            formatter.format_mnemonic(&instr, &mut mnemonic);
+            formatter.format_all_operands(&instr, &mut operands);
+
+            let target = match instr.flow_control() {
+                FlowControl::UnconditionalBranch | FlowControl::ConditionalBranch | FlowControl::Call
+                    if instr.near_branch_target() != 0 => Some(instr.near_branch_target() as usize),
+                _ => None
+            };
+
+            let annotation = if self.debug.position == Some(address) {
+                format!("◆ thread {}", self.debug.thread.unwrap_or_default())
+            } else if self.debug.breakpoints.contains_key(&address) {
+                String::from("● breakpoint")
+            } else if patched {
+                String::from("patched")
+            } else if let Some(target) = target {
+                self.resolver.symbolize(target).map(|s| format!("→ {}", s)).unwrap_or_default()
+            } else if instr.is_ip_rel_memory_operand() {
+                self.resolver.symbolize(instr.ip_rel_memory_address() as usize).map(|s| format!("[{}]", s)).unwrap_or_default()
+            } else {
+                String::new()
+            };
+
+            self.lines.push(DisasmLine {
+                address: address, bytes: bytes, mnemonic: mnemonic, operands: operands,
+                target: target, annotation: annotation,
+            });
+        }
+
+        if self.lines.len() < self.rows {
+            self.lines.push(DisasmLine {
+                address: self.address + data.len(), bytes: vec![], mnemonic: String::from("??"), operands: String::new(),
+                target: None, annotation: String::from("unreadable"),
+            });
+        }
+    }
+
+    pub fn decode_if_resized(&mut self, pid: u32) {
+        if self.decoded_rows != self.rows {
+            self.decode(pid);
+        }
+    }
+
+    fn previous_instruction(&self, pid: u32, address: usize) -> usize {
+        instruction_before(pid, self.bitness, address)
+    }
+
+    pub fn selected(&self) -> Option<&DisasmLine> {
+        self.lines.get(self.table_state.selected()?)
+    }
+
+    pub fn next(&mut self, pid: u32) {
+        let selected = self.table_state.selected().unwrap_or(0);
+        if selected + 1 < self.lines.len().min(self.rows) {
+            self.table_state.select(Some(selected + 1));
+        } else if let Some(second) = self.lines.get(1) {
+            self.address = second.address;
+            self.decode(pid);
+        }
+    }
+
+    pub fn previous(&mut self, pid: u32) {
+        let selected = self.table_state.selected().unwrap_or(0);
+        if selected > 0 {
+            self.table_state.select(Some(selected - 1));
+        } else {
+            self.address = self.previous_instruction(pid, self.address);
+            self.decode(pid);
+        }
+    }
+
+    pub fn page_down(&mut self, pid: u32) {
+        if let Some(last) = self.lines.last() {
+            self.address = last.address;
+            self.decode(pid);
+        }
+    }
+
+    pub fn page_up(&mut self, pid: u32) {
+        for _ in 1..self.rows {
+            self.address = self.previous_instruction(pid, self.address);
+        }
+        self.decode(pid);
+    }
+
+    // Enter on a branch or call jumps to its target, Backspace goes back
+    pub fn follow(&mut self, pid: u32) -> bool {
+        match self.selected().and_then(|l| l.target) {
+            Some(target) => {
+                self.history.push(self.address);
+                self.goto(pid, target);
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn follow_back(&mut self, pid: u32) {
+        if let Some(address) = self.history.pop() {
+            self.goto(pid, address);
+        }
+    }
+
+    // Patches
+
+    // Memory under a breakpoint holds its int3, patching or restoring there would save or overwrite it
+    fn check_breakpoints(&self, address: usize, len: usize) -> io::Result<()> {
+        match self.debug.breakpoints.range(address..address + len).next() {
+            Some((&breakpoint, _)) => Err(io::Error::other(format!("remove the breakpoint at {:X} first", breakpoint))),
+            None => Ok(())
+        }
+    }
+
+    pub fn nop(&mut self, pid: u32) -> io::Result<()> {
+        if let Some(line) = self.selected().filter(|l| !l.bytes.is_empty()) {
+            let (address, len) = (line.address, line.bytes.len());
+            self.check_breakpoints(address, len)?;
+            self.patches.nop(address, len)?;
+            self.decode(pid);
+        }
+        Ok(())
+    }
+
+    pub fn patch(&mut self, pid: u32, bytes: &[u8]) -> io::Result<()> {
+        self.state = DisasmState::Browse;
+        if let Some(address) = self.selected().map(|l| l.address) {
+            self.check_breakpoints(address, bytes.len())?;
+            self.patches.apply(address, bytes)?;
+            self.decode(pid);
+        }
+        Ok(())
+    }
+
+    // Restores the patch covering the selected instruction
+    pub fn restore(&mut self, pid: u32) -> io::Result<bool> {
+        let index = self.selected().and_then(|l| {
+            let len = l.bytes.len().max(1);
+            self.patches.patches.iter().position(|p| p.overlaps(l.address, len))
+        });
+
+        match index {
+            Some(index) => {
+                let patch = &self.patches.patches[index];
+                self.check_breakpoints(patch.address, patch.original.len())?;
+                self.patches.restore(index)?;
+                self.decode(pid);
+                Ok(true)
+            },
+            None => Ok(false)
+        }
+    }
+
+    // Selects the address if it's on screen, otherwise disassembles from there
+    pub fn reveal(&mut self, pid: u32, address: usize) {
+        self.decode(pid);
+        match self.lines.iter().take(self.rows).position(|l| l.address == address) {
+            Some(i) => self.table_state.select(Some(i)),
+            None => self.goto(pid, address)
+        }
+    }
+
+    pub fn jump(&mut self, pid: u32, address: usize) {
+        self.history.push(self.address);
+        self.state = DisasmState::Browse;
+        self.goto(pid, address);
+    }
+}
+
+
+// x86 can't be decoded backwards, pick the first start that lines up with the address
+pub fn instruction_before(pid: u32, bitness: u32, address: usize) -> usize {
+    let start = address.saturating_sub(Disassembly::LOOKBEHIND);
+    let data = Disassembly::read_code(pid, start, address - start);
+
+    for offset in 0..data.len() {
+        let mut decoder = Decoder::with_ip(bitness, &data[offset..], (start + offset) as u64, DecoderOptions::NONE);
+        let mut instr = Instruction::default();
+
+        while decoder.can_decode() {
+            decoder.decode_out(&mut instr);
+            if instr.is_invalid() || instr.next_ip() as usize > address {
+                break;
+            }
+            if instr.next_ip() as usize == address {
+                return instr.ip() as usize;
+            }
+        }
+    }
+    address.saturating_sub(1)
+}
+
+pub fn disassemble_at(pid: u32, bitness: u32, address: usize) -> Option<String> {
+    let data = Disassembly::read_code(pid, address, Disassembly::MAX_INSTRUCTION_LEN);
+    let mut decoder = Decoder::with_ip(bitness, &data, address as u64, DecoderOptions::NONE);
+    let instr = decoder.decode();
+    if instr.is_invalid() {
+        return None;
+    }
+
+    let mut output = String::new();
+    IntelFormatter::new().format(&instr, &mut output);
+    Some(output)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // nop; jmp +2; nop; nop; ret; mov rbp, rsp after a run of nops, decoded from the test process itself
+    fn code() -> Vec<u8> {
+        let mut code = vec![0x90; 32];
+        code.extend([0x90, 0xEB, 0x02, 0x90, 0x90, 0xC3, 0x48, 0x89, 0xE5]);
+        code
+    }
+
+    fn open(code: &[u8]) -> (Disassembly, usize) {
+        let start = code.as_ptr() as usize + 32;
+        let mut disassembly = Disassembly::new();
+        disassembly.rows = 6;
+        disassembly.open(std::process::id(), start);
+        (disassembly, start)
+    }
+
+    #[test]
+    fn decode_lines() {
+        let code = code();
+        let (disassembly, start) = open(&code);
+
+        let lines = disassembly.lines.iter().map(|l| (l.address - start, l.mnemonic.as_str())).collect::<Vec<_>>();
+        assert_eq!(lines, [(0, "nop"), (1, "jmp"), (3, "nop"), (4, "nop"), (5, "ret"), (6, "mov")]);
+        assert_eq!(disassembly.lines[1].bytes, [0xEB, 0x02]);
+        assert_eq!(disassembly.lines[1].target, Some(start + 5));
+        assert_eq!(disassembly.lines[5].operands, "rbp,rsp");
+    }
+
+    #[test]
+    fn follow_and_back() {
+        let code = code();
+        let (mut disassembly, start) = open(&code);
+        let pid = std::process::id();
+
+        assert!(!disassembly.follow(pid));
+        disassembly.table_state.select(Some(1));
+        assert!(disassembly.follow(pid));
+        assert_eq!(disassembly.address, start + 5);
+        assert_eq!(disassembly.lines[0].mnemonic, "ret");
+
+        disassembly.follow_back(pid);
+        assert_eq!(disassembly.address, start);
+    }
+
+    #[test]
+    fn previous_instruction() {
+        let code = code();
+        let (mut disassembly, start) = open(&code);
+        let pid = std::process::id();
+
+        disassembly.jump(pid, start + 5);
+        disassembly.previous(pid);
+        assert_eq!(disassembly.address, start + 4);
+    }
+
+    #[test]
+    fn no_patches_over_breakpoints() {
+        let code = code();
+        let (mut disassembly, start) = open(&code);
+        let pid = std::process::id();
+        let read = |i: usize| unsafe { std::ptr::read_volatile(&code[32 + i]) };
+
+        disassembly.debug.breakpoints.insert(start + 2, 0x02);
+        disassembly.table_state.select(Some(1));
+        let error = disassembly.nop(pid).err().unwrap();
+        assert_eq!(error.to_string(), format!("remove the breakpoint at {:X} first", start + 2));
+        assert!(disassembly.patch(pid, &[0xCC, 0xCC, 0xCC]).is_err());
+        assert!(disassembly.patches.patches.is_empty());
+
+        disassembly.debug.breakpoints.clear();
+        disassembly.nop(pid).unwrap();
+        disassembly.debug.breakpoints.insert(start + 1, 0x90);
+        assert!(disassembly.restore(pid).is_err());
+        assert_eq!((read(1), read(2)), (0x90, 0x90));
+
+        disassembly.debug.breakpoints.clear();
+        assert!(disassembly.restore(pid).unwrap());
+        assert_eq!((read(1), read(2)), (0xEB, 0x02));
+    }
+}
@@ -0,0 +1,184 @@
+//! Results saved to and loaded from JSON or CSV files
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::mem::{Datatype, Memory};
+use crate::os::{enum_modules, enum_regions};
+
+
+/// File format of saved results
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn from_path(path: &Path) -> ExportFormat {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ExportFormat::Csv,
+            _ => ExportFormat::Json
+        }
+    }
+}
+
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    pid: u32,
+    address: String,
+    #[serde(rename = "type")]
+    datatype: String,
+    value: String,
+    old_value: String,
+    region: Option<String>,
+    permissions: Option<String>,
+    module: Option<String>,
+    offset: Option<String>,
+}
+
+
+/// Saves results to path, as CSV if it ends in .csv and JSON otherwise, returns how many were written
+pub fn export_memory(pid: u32, memory: &Memory, path: &Path) -> io::Result<usize> {
+    write_memory(pid, memory, BufWriter::new(File::create(path)?), ExportFormat::from_path(path))
+}
+
+
+/// Writes one entry at a time, results are never collected in memory
+pub fn write_memory(pid: u32, memory: &Memory, mut writer: impl Write, format: ExportFormat) -> io::Result<usize> {
+    let regions = enum_regions(pid);
+    let modules = enum_modules(pid);
+
+    let entries = memory.iter().map(|[location, value, old_value]| {
+        let (address, datatype) = location.split_once(':').unwrap_or((&location, ""));
+        let address_value = usize::from_str_radix(address, 16).unwrap_or_default();
+
+        let i = regions.partition_point(|r| r.base + r.size <= address_value);
+        let region = regions.get(i).filter(|r| r.base <= address_value);
+        let module = modules.iter().find(|m| m.base <= address_value && address_value < m.base + m.size);
+
+        Entry {
+            pid: pid,
+            address: address.to_string(),
+            datatype: datatype.to_string(),
+            value: value,
+            old_value: old_value,
+            region: region.map(|r| format!("{:016X}-{:016X}", r.base, r.base + r.size)),
+            permissions: region.map(|r| r.permissions()),
+            module: module.map(|m| m.name.clone()),
+            offset: module.map(|m| format!("{:X}", address_value - m.base)),
+        }
+    });
+
+    let mut count = 0;
+    match format {
+        ExportFormat::Csv => {
+            let mut csv_writer = csv::Writer::from_writer(writer);
+            for entry in entries {
+                csv_writer.serialize(entry)?;
+                count += 1;
+            }
+            csv_writer.flush()?;
+        },
+        ExportFormat::Json => {
+            writer.write_all(b"[")?;
+            for entry in entries {
+                writer.write_all(if count == 0 { b"\n  " } else { b",\n  " })?;
+                serde_json::to_writer(&mut writer, &entry)?;
+                count += 1;
+            }
+            writer.write_all(b"\n]\n")?;
+            writer.flush()?;
+        }
+    }
+
+    Ok(count)
+}
+
+
+/// Loads results saved by export_memory, with the pid they were taken from
+pub fn import_memory(path: &Path) -> io::Result<(u32, Memory)> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut memory = Memory::new();
+    let mut pid = 0;
+
+    let mut add = |entry: Entry| -> io::Result<()> {
+        let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, format!("entry {}: {}", entry.address, e));
+
+        let address = usize::from_str_radix(&entry.address, 16).map_err(|e| invalid(e.to_string()))?;
+        let datatype = Datatype::from_name(&entry.datatype).ok_or_else(|| invalid(format!("unknown type '{}'", entry.datatype)))?;
+        let value = datatype.parse(&entry.value).map_err(invalid)?;
+        let old_value = datatype.parse(&entry.old_value).map_err(invalid)?;
+
+        memory.push_location(address, &datatype, &value, &old_value);
+        pid = entry.pid;
+        Ok(())
+    };
+
+    match ExportFormat::from_path(path) {
+        ExportFormat::Csv => {
+            for entry in csv::Reader::from_reader(reader).deserialize() {
+                add(entry?)?;
+            }
+        },
+        ExportFormat::Json => {
+            for entry in serde_json::from_reader::<_, Vec<Entry>>(reader)? {
+                add(entry)?;
+            }
+        }
+    }
+
+    memory.sort();
+    Ok((pid, memory))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory() -> Memory {
+        let mut memory = Memory::new();
+        memory.push(0x1000, &Datatype::B4, &1234u32.to_ne_bytes());
+        memory.push(0x2008, &Datatype::D, &2.5f64.to_ne_bytes());
+        memory.push_location(0x3000, &Datatype::B1S, &(-3i8).to_ne_bytes(), &7i8.to_ne_bytes());
+        memory
+    }
+
+    fn round_trip(extension: &str) {
+        let path = std::env::temp_dir().join(format!("mismem-{}-export.{}", std::process::id(), extension));
+        let pid = std::process::id();
+
+        assert_eq!(export_memory(pid, &memory(), &path).unwrap(), 3);
+        let (imported_pid, imported) = import_memory(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(imported_pid, pid);
+        assert_eq!(imported.iter().collect::<Vec<_>>(), memory().iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        round_trip("json");
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        round_trip("CSV");
+    }
+
+    #[test]
+    fn unknown_type() {
+        let path = std::env::temp_dir().join(format!("mismem-{}-unknown.json", std::process::id()));
+        std::fs::write(&path, r#"[{"pid":1,"address":"1000","type":"u24","value":"1","old_value":"1",
+            "region":null,"permissions":null,"module":null,"offset":null}]"#).unwrap();
+        let error = import_memory(&path).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,391 @@
+//! Address expressions over module bases and pointers
+
+use std::fmt;
+
+use crate::os::{enum_modules, pointer_size, read_process};
+use crate::process::Module;
+
+// Address expressions, e.g. `[[game.exe+0x1F0]+0x18]+0x4`
+//
+//   expr   := term (('+' | '-') term)*
+//   term   := factor ('*' factor)*
+//   factor := number | module | '"' module '"' | '[' expr ']' | '(' expr ')'
+//
+// Numbers are decimal unless prefixed with `0x`, other names starting with
+// a digit are modules, like `7zip.dll`. Brackets read a pointer of the
+// target pointer size at the enclosed address.
+
+#[derive(Debug)]
+pub enum Expr {
+    Number(usize),
+    Module(String),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Deref(Box<Expr>),
+}
+
+#[derive(Debug)]
+pub enum ExprError {
+    Syntax { position: usize, message: String },
+    UnknownModule(String),
+    InvalidPointer { step: usize, expr: String, address: usize },
+}
+
+/// Where expressions get module bases and pointers from, ProcessResolver reads a live process
+pub trait AddressResolver {
+    fn pointer_size(&self) -> usize;
+    fn module_base(&self, name: &str) -> Option<usize>;
+    fn read(&self, address: usize, buffer: &mut [u8]) -> bool;
+}
+
+
+/// Parses an expression without evaluating it
+pub fn parse(input: &str) -> Result<Expr, ExprError> {
+    let mut parser = Parser { chars: input.char_indices().collect(), pos: 0, len: input.len() };
+    let expr = parser.expr()?;
+    parser.skip_whitespace();
+
+    match parser.peek() {
+        None => Ok(expr),
+        Some(c) => Err(parser.error(format!("unexpected '{}'", c)))
+    }
+}
+
+
+impl Expr {
+    /// Address the expression points to
+    pub fn eval(&self, resolver: &impl AddressResolver) -> Result<usize, ExprError> {
+        let mut step = 0;
+        self.eval_steps(resolver, &mut step)
+    }
+
+    fn eval_steps(&self, resolver: &impl AddressResolver, step: &mut usize) -> Result<usize, ExprError> {
+        let mask = match resolver.pointer_size() {
+            4 => u32::MAX as usize,
+            _ => usize::MAX
+        };
+
+        let value = match self {
+            Expr::Number(n) => *n,
+            Expr::Module(name) => match resolver.module_base(name) {
+                Some(base) => base,
+                None => return Err(ExprError::UnknownModule(name.clone()))
+            },
+            Expr::Add(a, b) => a.eval_steps(resolver, step)?.wrapping_add(b.eval_steps(resolver, step)?),
+            Expr::Sub(a, b) => a.eval_steps(resolver, step)?.wrapping_sub(b.eval_steps(resolver, step)?),
+            Expr::Mul(a, b) => a.eval_steps(resolver, step)?.wrapping_mul(b.eval_steps(resolver, step)?),
+            Expr::Deref(inner) => {
+                let address = inner.eval_steps(resolver, step)?;
+                *step += 1;
+
+                let mut buffer = vec![0u8; resolver.pointer_size()];
+                if !resolver.read(address, &mut buffer) {
+                    return Err(ExprError::InvalidPointer { step: *step, expr: self.to_string(), address: address });
+                }
+
+                buffer.resize(std::mem::size_of::<usize>(), 0);
+                usize::from_le_bytes(buffer.try_into().unwrap())
+            }
+        };
+
+        Ok(value & mask)
+    }
+}
+
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "0x{:X}", n),
+            Expr::Module(name) => if name.chars().all(is_module_char) {
+                write!(f, "{}", name)
+            } else {
+                write!(f, "\"{}\"", name)
+            },
+            Expr::Add(a, b) => write!(f, "{}+{}", a, b),
+            Expr::Sub(a, b) => match **b {
+                Expr::Add(..) | Expr::Sub(..) => write!(f, "{}-({})", a, b),
+                _ => write!(f, "{}-{}", a, b)
+            },
+            Expr::Mul(a, b) => {
+                let wrap = |e: &Expr| match e {
+                    Expr::Add(..) | Expr::Sub(..) => format!("({})", e),
+                    _ => e.to_string()
+                };
+                write!(f, "{}*{}", wrap(a), wrap(b))
+            },
+            Expr::Deref(inner) => write!(f, "[{}]", inner),
+        }
+    }
+}
+
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExprError::Syntax { position, message } => write!(f, "syntax error at {}: {}", position, message),
+            ExprError::UnknownModule(name) => write!(f, "unknown module '{}'", name),
+            ExprError::InvalidPointer { step, expr, address } =>
+                write!(f, "invalid pointer at step {} ({}): can't read {:X}", step, expr, address),
+        }
+    }
+}
+
+
+struct Parser {
+    chars: Vec<(usize, char)>,
+    pos: usize,
+    len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).map(|&(_, c)| c)
+    }
+
+    fn offset(&self) -> usize {
+        self.chars.get(self.pos).map(|&(i, _)| i).unwrap_or(self.len)
+    }
+
+    fn error(&self, message: String) -> ExprError {
+        ExprError::Syntax { position: self.offset(), message: message }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ExprError> {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{}'", c)))
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.term()?;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('+') => { self.pos += 1; lhs = Expr::Add(Box::new(lhs), Box::new(self.term()?)); },
+                Some('-') => { self.pos += 1; lhs = Expr::Sub(Box::new(lhs), Box::new(self.term()?)); },
+                _ => return Ok(lhs)
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.factor()?;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('*') => { self.pos += 1; lhs = Expr::Mul(Box::new(lhs), Box::new(self.factor()?)); },
+                _ => return Ok(lhs)
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Result<Expr, ExprError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('[') => {
+                self.pos += 1;
+                let inner = self.expr()?;
+                self.expect(']')?;
+                Ok(Expr::Deref(Box::new(inner)))
+            },
+            Some('(') => {
+                self.pos += 1;
+                let inner = self.expr()?;
+                self.expect(')')?;
+                Ok(inner)
+            },
+            Some('"') => {
+                self.pos += 1;
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c != '"') {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().map(|&(_, c)| c).collect();
+                self.expect('"')?;
+                Ok(Expr::Module(name))
+            },
+            Some(c) if is_module_char(c) => {
+                let start = self.pos;
+                while self.peek().is_some_and(is_module_char) {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().map(|&(_, c)| c).collect();
+
+                // Digits start a number, unless the name isn't one, like 7zip.dll
+                let hex = name.starts_with("0x") || name.starts_with("0X");
+                if c.is_ascii_digit() && (hex || name.chars().all(|c| c.is_ascii_digit())) {
+                    self.pos = start;
+                    self.number()
+                } else {
+                    Ok(Expr::Module(name))
+                }
+            },
+            Some(c) => Err(self.error(format!("unexpected '{}'", c))),
+            None => Err(self.error(String::from("unexpected end of expression")))
+        }
+    }
+
+    fn number(&mut self) -> Result<Expr, ExprError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+        let literal: String = self.chars[start..self.pos].iter().map(|&(_, c)| c).collect();
+
+        let parsed = match literal.strip_prefix("0x").or(literal.strip_prefix("0X")) {
+            Some(hex) => usize::from_str_radix(hex, 16),
+            None => literal.parse::<usize>()
+        };
+
+        parsed.map(Expr::Number).map_err(|e| ExprError::Syntax {
+            position: self.chars[start].0,
+            message: format!("invalid number '{}': {}", literal, e)
+        })
+    }
+}
+
+fn is_module_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+
+// Resolves expressions against a live process
+
+/// AddressResolver reading a live process
+#[derive(Default)]
+pub struct ProcessResolver {
+    pid: u32,
+    pointer_size: usize,
+    modules: Vec<Module>,
+}
+
+impl ProcessResolver {
+    pub fn new(pid: u32) -> ProcessResolver {
+        ProcessResolver { pid: pid, pointer_size: pointer_size(pid), modules: enum_modules(pid) }
+    }
+
+    /// Inverse of module_base, e.g. `game.exe+1F0`
+    pub fn symbolize(&self, address: usize) -> Option<String> {
+        self.modules.iter()
+            .find(|m| m.base <= address && address < m.base + m.size)
+            .map(|m| format!("{}+{:X}", m.name, address - m.base))
+    }
+}
+
+impl AddressResolver for ProcessResolver {
+    fn pointer_size(&self) -> usize {
+        self.pointer_size
+    }
+
+    fn module_base(&self, name: &str) -> Option<usize> {
+        self.modules.iter().find(|m| m.name.eq_ignore_ascii_case(name)).map(|m| m.base)
+    }
+
+    fn read(&self, address: usize, buffer: &mut [u8]) -> bool {
+        read_process(self.pid, address, buffer)
+    }
+}
+
+
+/// Parses input and evaluates it against process pid
+pub fn resolve_address(pid: u32, input: &str) -> Result<usize, ExprError> {
+    parse(input)?.eval(&ProcessResolver::new(pid))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Modules "game.exe" at 0x400000 and "7zip.dll" at 0x700000, 8-byte pointers at fixed addresses
+    struct FakeResolver {
+        pointers: Vec<(usize, usize)>,
+    }
+
+    impl AddressResolver for FakeResolver {
+        fn pointer_size(&self) -> usize {
+            8
+        }
+
+        fn module_base(&self, name: &str) -> Option<usize> {
+            match name.to_ascii_lowercase().as_str() {
+                "game.exe" => Some(0x400000),
+                "7zip.dll" => Some(0x700000),
+                _ => None
+            }
+        }
+
+        fn read(&self, address: usize, buffer: &mut [u8]) -> bool {
+            match self.pointers.iter().find(|&&(at, _)| at == address) {
+                Some(&(_, value)) => {
+                    buffer.copy_from_slice(&(value as u64).to_le_bytes()[..buffer.len()]);
+                    true
+                },
+                None => false
+            }
+        }
+    }
+
+    fn eval(input: &str) -> Result<usize, ExprError> {
+        let resolver = FakeResolver { pointers: vec![(0x4001F0, 0x10000), (0x10018, 0x20000)] };
+        parse(input)?.eval(&resolver)
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("2+3*4").unwrap(), 14);
+        assert_eq!(eval("(2+3)*4").unwrap(), 20);
+        assert_eq!(eval("10-2-3").unwrap(), 5);
+        assert_eq!(eval("0x10 * 2 - 1").unwrap(), 0x1F);
+    }
+
+    #[test]
+    fn modules() {
+        assert_eq!(eval("game.exe+0x10").unwrap(), 0x400010);
+        assert_eq!(eval("\"GAME.EXE\"").unwrap(), 0x400000);
+        assert!(matches!(eval("other.dll+4"), Err(ExprError::UnknownModule(name)) if name == "other.dll"));
+    }
+
+    #[test]
+    fn module_starting_with_digit() {
+        assert_eq!(eval("7zip.dll+0x10").unwrap(), 0x700010);
+        assert_eq!(eval("7+7zip.dll").unwrap(), 0x700007);
+        assert!(matches!(eval("12abc"), Err(ExprError::UnknownModule(name)) if name == "12abc"));
+    }
+
+    #[test]
+    fn nested_deref() {
+        assert_eq!(eval("[game.exe+0x1F0]").unwrap(), 0x10000);
+        assert_eq!(eval("[[game.exe+0x1F0]+0x18]+0x4").unwrap(), 0x20004);
+    }
+
+    #[test]
+    fn invalid_pointer_step() {
+        match eval("[[[game.exe+0x1F0]+0x18]+0x8]") {
+            Err(ExprError::InvalidPointer { step, address, .. }) => {
+                assert_eq!(step, 3);
+                assert_eq!(address, 0x20008);
+            },
+            other => panic!("expected InvalidPointer, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert!(matches!(parse("game.exe+"), Err(ExprError::Syntax { position: 9, .. })));
+        assert!(matches!(parse("[game.exe"), Err(ExprError::Syntax { .. })));
+        assert!(matches!(parse("0xZZ"), Err(ExprError::Syntax { position: 0, .. })));
+    }
+}
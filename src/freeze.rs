@@ -0,0 +1,85 @@
+use std::{
+    sync::{Arc, Mutex, Weak},
+    thread,
+    time::Duration,
+};
+
+use crate::os::{check_process, write_process};
+
+
+pub struct Frozen {
+    pub pid: u32,
+    pub address: usize,
+    pub bytes: Vec<u8>,
+}
+
+// Values written again and again so the target can't change them, clones share the same list
+#[derive(Clone)]
+pub struct Freezer {
+    frozen: Arc<Mutex<Vec<Frozen>>>,
+}
+
+impl Freezer {
+    const INTERVAL : Duration = Duration::from_millis(100);
+
+    pub fn new() -> Freezer {
+        let frozen = Arc::new(Mutex::new(Vec::new()));
+        let weak = Arc::downgrade(&frozen);
+        thread::spawn(move || Freezer::run(weak));
+        Freezer { frozen: frozen }
+    }
+
+    // Ends with the last clone
+    fn run(frozen: Weak<Mutex<Vec<Frozen>>>) {
+        loop {
+            thread::sleep(Freezer::INTERVAL);
+            let frozen = match frozen.upgrade() {
+                Some(frozen) => frozen,
+                None => return
+            };
+            frozen.lock().unwrap().retain(|f| {
+                if write_process(f.pid, f.address, &f.bytes).is_ok() || check_process(f.pid) {
+                    return true;
+                }
+                log::warn!(" Process {} is gone, {:016X} is no longer frozen.", f.pid, f.address);
+                false
+            });
+        }
+    }
+
+    pub fn freeze(&self, pid: u32, address: usize, bytes: Vec<u8>) {
+        let mut frozen = self.frozen.lock().unwrap();
+        frozen.retain(|f| f.pid != pid || f.address != address);
+        frozen.push(Frozen { pid: pid, address: address, bytes: bytes });
+    }
+
+    pub fn unfreeze(&self, pid: u32, address: usize) -> bool {
+        let mut frozen = self.frozen.lock().unwrap();
+        let len = frozen.len();
+        frozen.retain(|f| f.pid != pid || f.address != address);
+        frozen.len() != len
+    }
+
+    // Returns how many values were released
+    pub fn unfreeze_process(&self, pid: u32) -> usize {
+        let mut frozen = self.frozen.lock().unwrap();
+        let len = frozen.len();
+        frozen.retain(|f| f.pid != pid);
+        len - frozen.len()
+    }
+
+    pub fn unfreeze_all(&self) -> usize {
+        let mut frozen = self.frozen.lock().unwrap();
+        let len = frozen.len();
+        frozen.clear();
+        len
+    }
+
+    pub fn len(&self) -> usize {
+        self.frozen.lock().unwrap().len()
+    }
+
+    pub fn is_frozen(&self, pid: u32, address: usize) -> bool {
+        self.frozen.lock().unwrap().iter().any(|f| f.pid == pid && f.address == address)
+    }
+}
@@ -5,11 +5,22 @@ use crossterm::event::{Event, KeyCode, MouseEventKind};
 use tui_input::backend::crossterm::EventHandler;
 
 use std::time::Instant;
+use std::path::PathBuf;
 
 use crate::{
     app::{App, AppState, EditState}, 
-    win::{scan_process, filter_process, update_process, write_process}, 
-    mem::Datatype
+    hexview::{HexView, HexState},
+    disasm::DisasmState,
+    watch::WatchKind,
+    proclist::SortColumn,
+    memmap::MapColumn,
+    search::{scan_process, filter_process, update_process, snapshot_process, compare_process, save_state_process, restore_state_process},
+    os::{write_process, read_process}, 
+    mem::Datatype,
+    expr::resolve_address,
+    export::{export_memory, import_memory},
+    snapshot::Change,
+    signature::generate
 };
 
 pub struct Handler {
@@ -27,38 +38,85 @@ impl Handler {
 
         match event {
             Event::Key(key) => {
-                if key.code == KeyCode::Char('q') { 
+                // Any key closes the signature popup
+                if app.signature.take().is_some() {
+                    return;
+                }
+
+                if key.code == KeyCode::Char('q') && !app.is_typing() { 
                     app.exiting = true 
                 }
                 
                 match app.state {
-                    AppState::SelectProcess => match key.code {
-                        KeyCode::Down => app.next_process(),
-                        KeyCode::Up => app.previous_process(),
-                        KeyCode::Char('u') => app.update_process_list(),
-                        KeyCode::Enter => app.select_process(),
-                        _ => {}
+                    AppState::SelectProcess => if app.processes.filtering {
+                        match key.code {
+                            KeyCode::Down => app.next_process(),
+                            KeyCode::Up => app.previous_process(),
+                            KeyCode::Enter => app.select_process(),
+                            KeyCode::Esc => app.clear_process_filter(),
+                            _ => {
+                                app.processes.filter.handle_event(&Event::Key(key));
+                                app.filter_processes();
+                            }
+                        }
+                    } else {
+                        match key.code {
+                            KeyCode::Down => app.next_process(),
+                            KeyCode::Up => app.previous_process(),
+                            KeyCode::Char('u') => app.update_process_list(),
+                            KeyCode::Char('/') => app.filter_process_mode(),
+                            KeyCode::Char('t') => app.toggle_process_tree(),
+                            KeyCode::Left => app.collapse_process(),
+                            KeyCode::Right => app.expand_process(),
+                            KeyCode::Char(c @ '1'..='5') => app.sort_processes(SortColumn::ALL[c as usize - '1' as usize]),
+                            KeyCode::Esc => app.clear_process_filter(),
+                            KeyCode::Enter => app.select_process(),
+                            _ => {}
+                        }
                     }
                     AppState::EditMemory => match app.edit_state {
                     
-                        EditState::Select => match key.code {
-                            KeyCode::Down => app.next_memory(),
-                            KeyCode::Up => app.previous_memory(),
-                            KeyCode::Char('i') => app.input_mode(),
-                            KeyCode::Char('s') => app.change_search_mode(),
-                            KeyCode::Char('t') => app.change_search_datatype(),
-                            KeyCode::Char('m') => app.change_search_type(),
-                            KeyCode::Left | KeyCode::Esc => {
-                                app.back()
-                            },
-                            KeyCode::Enter => {
-                                app.select_memory()
-                            },
-                            KeyCode::Char('u') => {
-                                drop(app);
-                                self.update_memory().await;
-                            },
-                            _ => {}
+                        EditState::Select => if app.show_popup {
+                            app.show_popup = false;
+                        } else {
+                            match key.code {
+                                KeyCode::Down => app.next_memory(),
+                                KeyCode::Up => app.previous_memory(),
+                                KeyCode::Char('i') => app.input_mode(),
+                                KeyCode::Char('a') => app.address_mode(),
+                                KeyCode::Char('e') => app.export_mode(),
+                                KeyCode::Char('o') => app.import_mode(),
+                                KeyCode::Char('S') => app.snapshot_mode(),
+                                KeyCode::Char('C') => app.compare_mode(),
+                                KeyCode::Char('k') => app.save_state_mode(),
+                                KeyCode::Char('l') => app.restore_state_mode(),
+                                KeyCode::Char('h') => app.open_hex_view(),
+                                KeyCode::Char('d') => app.open_disassembly(),
+                                KeyCode::Char('w') => app.start_watch(WatchKind::Write),
+                                KeyCode::Char('r') => app.start_watch(WatchKind::Access),
+                                KeyCode::Char('s') => app.change_search_mode(),
+                                KeyCode::Char('t') => app.change_search_datatype(),
+                                KeyCode::Char('m') => app.change_search_type(),
+                                KeyCode::Char('p') => app.open_inspector(),
+                                KeyCode::Char('v') => app.open_memory_map(),
+                                KeyCode::Char('c') => app.resume_launched(),
+                                KeyCode::Char('R') => app.reattach_mode(),
+                                KeyCode::Char('f') => app.toggle_freeze(),
+                                KeyCode::Char('z') => app.unfreeze_all(),
+                                KeyCode::Char('x') => app.script_mode(),
+                                KeyCode::Char('X') => app.stop_script(),
+                                KeyCode::Left | KeyCode::Esc => {
+                                    app.back()
+                                },
+                                KeyCode::Enter => {
+                                    app.select_memory()
+                                },
+                                KeyCode::Char('u') if app.running() => {
+                                    drop(app);
+                                    self.update_memory().await;
+                                },
+                                _ => {}
+                            }
                         },
                         EditState::Input => if app.show_popup { 
                             app.show_popup = false;
@@ -76,13 +134,27 @@ impl Handler {
                                 }
                             }
                         },
-                        EditState::Edit => if app.show_popup { 
+                        EditState::Edit | EditState::Address | EditState::Export | EditState::Import |
+                        EditState::Snapshot | EditState::Compare | EditState::SaveState | EditState::RestoreState => if app.show_popup { 
                             app.show_popup = false;
                         } else {
                             match key.code {
                                 KeyCode::Enter => {
+                                    let state = app.edit_state;
                                     drop(app);
-                                    self.write().await;
+                                    match state {
+                                        EditState::Edit => self.write().await,
+                                        EditState::Address => self.add_address().await,
+                                        EditState::Export => self.export().await,
+                                        EditState::Snapshot => self.snapshot().await,
+                                        EditState::Compare => self.compare().await,
+                                        EditState::SaveState => self.save_state().await,
+                                        EditState::RestoreState => self.restore_state().await,
+                                        _ => self.import().await,
+                                    }
+                                },
+                                KeyCode::Tab if matches!(app.edit_state, EditState::Compare) => {
+                                    app.next_change();
                                 },
                                 KeyCode::Esc => {
                                     app.edit_state = EditState::Select;
@@ -92,8 +164,224 @@ impl Handler {
                                 }
                             }
                         },
+                        EditState::Script => match (key.code, app.console.as_mut()) {
+                            (KeyCode::Esc, _) | (_, None) => app.edit_state = EditState::Select,
+                            (KeyCode::Enter, Some(console)) => console.submit(),
+                            (KeyCode::Up, Some(console)) => console.previous(),
+                            (KeyCode::Down, Some(console)) => console.next(),
+                            (_, Some(console)) => {
+                                console.input.handle_event(&Event::Key(key));
+                            }
+                        },
                         _ => {}
                     }
+                    AppState::HexView => match app.hex_view.state {
+                        HexState::Browse => if app.show_popup {
+                            app.show_popup = false;
+                        } else {
+                            match key.code {
+                                KeyCode::Down => app.move_hex_cursor(HexView::BYTES_PER_ROW as isize),
+                                KeyCode::Up => app.move_hex_cursor(-(HexView::BYTES_PER_ROW as isize)),
+                                KeyCode::Right => app.move_hex_cursor(1),
+                                KeyCode::Left => app.move_hex_cursor(-1),
+                                KeyCode::PageDown => app.page_hex(1),
+                                KeyCode::PageUp => app.page_hex(-1),
+                                KeyCode::Tab => app.hex_view.toggle_column(),
+                                KeyCode::Char('g') => app.hex_goto_mode(),
+                                KeyCode::Char('e') => app.hex_edit_mode(),
+                                KeyCode::Char('i') => app.inspector_mode(),
+                                KeyCode::Char('d') => app.open_disassembly(),
+                                KeyCode::Char('s') => {
+                                    let (pid, address) = (app.selected_process, app.signature_address());
+                                    drop(app);
+                                    self.signature(pid, address);
+                                },
+                                KeyCode::Char('w') => app.start_watch(WatchKind::Write),
+                                KeyCode::Char('r') => app.start_watch(WatchKind::Access),
+                                KeyCode::Esc => app.back(),
+                                _ => {}
+                            }
+                        },
+                        HexState::Edit => if app.show_popup {
+                            app.show_popup = false;
+                        } else {
+                            match key.code {
+                                KeyCode::Down => app.move_hex_cursor(HexView::BYTES_PER_ROW as isize),
+                                KeyCode::Up => app.move_hex_cursor(-(HexView::BYTES_PER_ROW as isize)),
+                                KeyCode::Right => app.move_hex_cursor(1),
+                                KeyCode::Left => app.move_hex_cursor(-1),
+                                KeyCode::PageDown => app.page_hex(1),
+                                KeyCode::PageUp => app.page_hex(-1),
+                                KeyCode::Tab => app.hex_view.toggle_column(),
+                                KeyCode::Char(c) => app.hex_edit_key(c),
+                                KeyCode::Enter => app.hex_commit(),
+                                KeyCode::Esc => app.hex_revert(),
+                                _ => {}
+                            }
+                        },
+                        HexState::Inspect => match key.code {
+                            KeyCode::Down => app.hex_view.next_interpretation(),
+                            KeyCode::Up => app.hex_view.previous_interpretation(),
+                            KeyCode::Left => app.hex_view.select_endianness(false),
+                            KeyCode::Right => app.hex_view.select_endianness(true),
+                            KeyCode::Enter => app.add_inspected(),
+                            KeyCode::Char('i') | KeyCode::Esc => {
+                                app.hex_view.state = HexState::Browse;
+                            },
+                            _ => {}
+                        },
+                        HexState::Goto => if app.show_popup { 
+                            app.show_popup = false;
+                        } else {
+                            match key.code {
+                                KeyCode::Enter => app.hex_goto(),
+                                KeyCode::Esc => {
+                                    app.hex_view.state = HexState::Browse;
+                                },
+                                _ => {
+                                    app.mismem_input.handle_event(&Event::Key(key));
+                                }
+                            }
+                        },
+                    }
+                    AppState::Disassembly => match app.disassembly.state {
+                        DisasmState::Browse => if app.show_popup {
+                            app.show_popup = false;
+                        } else {
+                            match key.code {
+                                KeyCode::Down => app.next_instruction(),
+                                KeyCode::Up => app.previous_instruction(),
+                                KeyCode::PageDown => app.page_disasm(1),
+                                KeyCode::PageUp => app.page_disasm(-1),
+                                KeyCode::Enter => app.disasm_follow(),
+                                KeyCode::Backspace => app.disasm_follow_back(),
+                                KeyCode::Char('g') => app.disasm_goto_mode(),
+                                KeyCode::Char('s') => {
+                                    let (pid, address) = (app.selected_process, app.signature_address());
+                                    drop(app);
+                                    self.signature(pid, address);
+                                },
+                                KeyCode::Char('n') => app.disasm_nop(),
+                                KeyCode::Char('p') => app.disasm_patch_mode(),
+                                KeyCode::Char('r') => app.disasm_restore(),
+                                KeyCode::Char('b') => app.toggle_breakpoint(),
+                                KeyCode::Char('t') => app.debug_step(),
+                                KeyCode::Char('c') => app.debug_resume(),
+                                KeyCode::Char('x') => app.debug_detach(),
+                                KeyCode::Tab => app.registers_mode(),
+                                KeyCode::Left | KeyCode::Esc => app.back(),
+                                _ => {}
+                            }
+                        },
+                        DisasmState::Goto => if app.show_popup { 
+                            app.show_popup = false;
+                        } else {
+                            match key.code {
+                                KeyCode::Enter => app.disasm_goto(),
+                                KeyCode::Esc => {
+                                    app.disassembly.state = DisasmState::Browse;
+                                },
+                                _ => {
+                                    app.mismem_input.handle_event(&Event::Key(key));
+                                }
+                            }
+                        },
+                        DisasmState::Patch => if app.show_popup { 
+                            app.show_popup = false;
+                        } else {
+                            match key.code {
+                                KeyCode::Enter => app.disasm_patch(),
+                                KeyCode::Esc => {
+                                    app.disassembly.state = DisasmState::Browse;
+                                },
+                                _ => {
+                                    app.mismem_input.handle_event(&Event::Key(key));
+                                }
+                            }
+                        },
+                        DisasmState::Registers => if app.show_popup {
+                            app.show_popup = false;
+                        } else {
+                            match key.code {
+                                KeyCode::Down => app.disassembly.debug.next_register(),
+                                KeyCode::Up => app.disassembly.debug.previous_register(),
+                                KeyCode::Enter => app.edit_register_mode(),
+                                KeyCode::Char('t') => app.debug_step(),
+                                KeyCode::Char('c') => app.debug_resume(),
+                                KeyCode::Tab | KeyCode::Esc => {
+                                    app.disassembly.state = DisasmState::Browse;
+                                },
+                                _ => {}
+                            }
+                        },
+                        DisasmState::EditRegister => if app.show_popup {
+                            app.show_popup = false;
+                        } else {
+                            match key.code {
+                                KeyCode::Enter => app.edit_register(),
+                                KeyCode::Esc => {
+                                    app.disassembly.state = DisasmState::Registers;
+                                },
+                                _ => {
+                                    app.mismem_input.handle_event(&Event::Key(key));
+                                }
+                            }
+                        },
+                    }
+                    AppState::Inspector => match key.code {
+                        KeyCode::Down => app.memory_map.next(),
+                        KeyCode::Up => app.memory_map.previous(),
+                        KeyCode::PageDown => app.inspector.scroll_details(5),
+                        KeyCode::PageUp => app.inspector.scroll_details(-5),
+                        KeyCode::Char('s') => app.scan_region(),
+                        KeyCode::Char('m') => app.scan_module(),
+                        KeyCode::Char('a') => app.scan_everywhere(),
+                        KeyCode::Char('v') => app.open_memory_map(),
+                        KeyCode::Char('u') => app.refresh_inspector(),
+                        KeyCode::Left | KeyCode::Esc => app.back(),
+                        _ => {}
+                    }
+                    AppState::MemoryMap => if app.memory_map.filtering {
+                        match key.code {
+                            KeyCode::Down => app.memory_map.next(),
+                            KeyCode::Up => app.memory_map.previous(),
+                            KeyCode::Enter => app.memory_map.filtering = false,
+                            KeyCode::Esc => app.clear_map_filter(),
+                            _ => {
+                                app.memory_map.filter.handle_event(&Event::Key(key));
+                                app.memory_map.update();
+                            }
+                        }
+                    } else {
+                        match key.code {
+                            KeyCode::Down => app.memory_map.next(),
+                            KeyCode::Up => app.memory_map.previous(),
+                            KeyCode::Char('/') => app.filter_map_mode(),
+                            KeyCode::Char(c @ '1'..='4') => app.sort_map(MapColumn::ALL[c as usize - '1' as usize]),
+                            KeyCode::Char(' ') => app.toggle_map_region(),
+                            KeyCode::Char('+') => app.include_map_regions(true),
+                            KeyCode::Char('-') => app.include_map_regions(false),
+                            KeyCode::Char('r') => app.reset_map_regions(),
+                            KeyCode::Char('h') | KeyCode::Enter => app.hex_view_region(),
+                            KeyCode::Char('u') => {
+                                let pid = app.selected_process;
+                                app.memory_map.refresh(pid);
+                            },
+                            KeyCode::Left | KeyCode::Esc => app.back(),
+                            _ => {}
+                        }
+                    }
+                    AppState::Watch => if app.show_popup {
+                        app.show_popup = false;
+                    } else {
+                        match key.code {
+                            KeyCode::Down => app.next_watch_hit(),
+                            KeyCode::Up => app.previous_watch_hit(),
+                            KeyCode::Enter => app.disassemble_watch_hit(),
+                            KeyCode::Left | KeyCode::Esc => app.back(),
+                            _ => {}
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -108,6 +396,26 @@ impl Handler {
                     MouseEventKind::ScrollDown => app.next_memory(),
                     _ => {}
                 },
+                AppState::HexView => match mouse.kind {
+                    MouseEventKind::ScrollUp => app.scroll_hex(-1),
+                    MouseEventKind::ScrollDown => app.scroll_hex(1),
+                    _ => {}
+                },
+                AppState::Disassembly => match mouse.kind {
+                    MouseEventKind::ScrollUp => app.previous_instruction(),
+                    MouseEventKind::ScrollDown => app.next_instruction(),
+                    _ => {}
+                },
+                AppState::Inspector | AppState::MemoryMap => match mouse.kind {
+                    MouseEventKind::ScrollUp => app.memory_map.previous(),
+                    MouseEventKind::ScrollDown => app.memory_map.next(),
+                    _ => {}
+                },
+                AppState::Watch => match mouse.kind {
+                    MouseEventKind::ScrollUp => app.previous_watch_hit(),
+                    MouseEventKind::ScrollDown => app.next_watch_hit(),
+                    _ => {}
+                },
                 _ => {}
             }
             _ => {}
@@ -158,6 +466,7 @@ impl Handler {
         app.edit_state = EditState::Busy;
 
         let sel_proc = app.selected_process;
+        let ranges = app.memory_map.scan_ranges();
         
         // SEARCH_MODE_OPTS = ["First Search", "Filter"];
         let mode = app.search_mode.selected().unwrap_or(0);
@@ -165,7 +474,7 @@ impl Handler {
 
         match mode {
             0 => {
-                scan_process(sel_proc, &value_bytes, &datatype, Arc::clone(&self.app)).await;
+                scan_process(sel_proc, &value_bytes, &datatype, ranges, Arc::clone(&self.app)).await;
             },
             1 => {
                 filter_process(sel_proc, &value_bytes, &datatype, Arc::clone(&self.app)).await;
@@ -175,6 +484,7 @@ impl Handler {
 
         let mut app = self.app.lock().await;
         app.edit_state = EditState::Select;
+        app.refresh_modules();
     }
 
     async fn update_memory(&mut self) {
@@ -195,52 +505,203 @@ impl Handler {
     async fn write(&mut self) {
         let mut app = self.app.lock().await;
 
-        macro_rules! popup_error{
-            ($e:expr)=>{{
-                app.popup_error = format!("Parsing error: {}", $e);
+        let mut tokens = app.selected_address.split(':');
+        let address = usize::from_str_radix(tokens.next().unwrap(), 16).unwrap();
+        let datatype = Datatype::from_name(tokens.next().unwrap()).expect("Unrecognized type name");
+
+        let new_value_bytes = match datatype.parse(app.mismem_input.value()) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                app.popup_error = format!("Parsing error: {}", e);
                 app.show_popup = true; 
                 return;
-            }}
+            }
+        };
+        
+        match write_process(app.selected_process, address, &new_value_bytes) {
+            Ok(()) => log::info!(" Memory write successful."),
+            Err(error) => {
+                app.popup_error = format!("Error: {}.", error);
+                app.show_popup = true; 
+                log::error!(" Memory write failed: {}", error);
+            }
         }
 
-        macro_rules! parse{
-            ($t:ty,$d:expr)=>{ 
-                match app.mismem_input.value().parse::<$t>() {
-                    Ok(r) => (r.to_ne_bytes().to_vec(), $d),
-                    Err(e) => popup_error!(e)
+        app.edit_state = EditState::Busy;
+        drop(app);
+
+        update_process(Arc::clone(&self.app)).await;
+
+        let mut app = self.app.lock().await;
+        app.edit_state = EditState::Select;
+    }
+
+    async fn add_address(&mut self) {
+        let mut app = self.app.lock().await;
+
+        let address = match resolve_address(app.selected_process, app.mismem_input.value()) {
+            Ok(address) => address,
+            Err(e) => {
+                app.popup_error = format!("Address error: {}", e);
+                app.show_popup = true;
+                return;
+            }
+        };
+
+        // DATATYPE_OPTS = ["Byte", "2 Bytes","4 Bytes","8 Bytes","16 Bytes","Float","Double"];
+        let (mut buffer, datatype) = match app.search_datatype.selected().unwrap_or(0) {
+            0 => (vec![0;1], Datatype::B1),
+            1 => (vec![0;2], Datatype::B2),
+            2 => (vec![0;4], Datatype::B4),
+            3 => (vec![0;8], Datatype::B8),
+            4 => (vec![0;16], Datatype::B16),
+            5 => (vec![0;4], Datatype::F),
+            6 => (vec![0;8], Datatype::D),
+            _ => panic!("Illegal Value Type Option.")
+        };
+
+        if !read_process(app.selected_process, address, &mut buffer) {
+            app.popup_error = format!("Error: can't read at address {:X}.", address);
+            app.show_popup = true;
+            return;
+        }
+
+        app.memory.insert(address, &datatype, &buffer);
+        app.edit_state = EditState::Select;
+        log::info!(" Added address {:016X}.", address);
+    }
+
+    async fn export(&mut self) {
+        let mut app = self.app.lock().await;
+        let path = PathBuf::from(app.mismem_input.value());
+
+        match export_memory(app.selected_process, &app.memory, &path) {
+            Ok(count) => {
+                log::info!(" Exported {} entries to {}.", count, path.display());
+                app.edit_state = EditState::Select;
+            },
+            Err(e) => {
+                app.popup_error = format!("Export error: {}", e);
+                app.show_popup = true;
+            }
+        }
+    }
+
+    async fn import(&mut self) {
+        let mut app = self.app.lock().await;
+        let path = PathBuf::from(app.mismem_input.value());
+
+        match import_memory(&path) {
+            Ok((pid, memory)) => {
+                if pid != app.selected_process {
+                    log::warn!(" Results in {} were taken from process {}.", path.display(), pid);
                 }
-            };
+                app.memory = memory;
+                app.table_state.select(None);
+                app.edit_state = EditState::Select;
+                log::info!(" Imported {} entries from {}.", app.memory.len(), path.display());
+            },
+            Err(e) => {
+                app.popup_error = format!("Import error: {}", e);
+                app.show_popup = true;
+            }
         }
+    }
 
-        let mut tokens = app.selected_address.split(':');
-        let address = usize::from_str_radix(tokens.next().unwrap(), 16).unwrap();
-        let (new_value_bytes, _)  = match tokens.next().unwrap() {
-            "u8" => parse!(u8,Datatype::B1),
-            "i8" => parse!(i8, Datatype::B1S),
-            "u16" => parse!(u16, Datatype::B2),
-            "i16" => parse!(i16, Datatype::B2S),
-            "u32" => parse!(u32, Datatype::B4),
-            "i32" => parse!(i32, Datatype::B4S),
-            "u64" => parse!(u64, Datatype::B8),
-            "i64" => parse!(i64, Datatype::B8S),
-            "u128" => parse!(u128, Datatype::B16),
-            "i128" => parse!(i128, Datatype::B16S),
-            "f32" => parse!(f32, Datatype::F),
-            "f64" => parse!(f64, Datatype::D),
-            _ => panic!("Unrecognized type name"),
+    async fn snapshot(&mut self) {
+        let mut app = self.app.lock().await;
+        let path = PathBuf::from(app.mismem_input.value());
+        let pid = app.selected_process;
+        let ranges = app.memory_map.scan_ranges();
+        app.snapshot_path = app.mismem_input.value().to_string();
+        app.edit_state = EditState::Busy;
+        drop(app);
+
+        snapshot_process(pid, ranges, &path, Arc::clone(&self.app)).await;
+
+        let mut app = self.app.lock().await;
+        app.edit_state = EditState::Select;
+    }
+
+    async fn compare(&mut self) {
+        let mut app = self.app.lock().await;
+
+        // One snapshot is compared with the process as it is now, two with each other
+        let paths = app.mismem_input.value().split_whitespace().map(PathBuf::from).collect::<Vec<_>>();
+        let (old, new) = match paths.as_slice() {
+            [old] => (old.clone(), None),
+            [old, new] => (old.clone(), Some(new.clone())),
+            _ => {
+                app.popup_error = String::from("Compare error: give a snapshot, or two to compare with each other.");
+                app.show_popup = true;
+                return;
+            }
         };
-        
-        if !write_process(app.selected_process, address, &new_value_bytes) {
-            app.popup_error = String::from("Error: can't write at target address.");
-            app.show_popup = true; 
-            log::error!(" Memory write failed.");
-        } else {
-            log::info!(" Memory write successful.");
-        }
 
+        // DATATYPE_OPTS = ["Byte", "2 Bytes","4 Bytes","8 Bytes","16 Bytes","Float","Double"];
+        let datatype = match app.search_datatype.selected().unwrap_or(0) {
+            0 => Datatype::B1,
+            1 => Datatype::B2,
+            2 => Datatype::B4,
+            3 => Datatype::B8,
+            4 => Datatype::B16,
+            5 => Datatype::F,
+            6 => Datatype::D,
+            _ => panic!("Illegal Value Type Option.")
+        };
+        let change = Change::from_name(Change::NAMES[app.change]).unwrap();
+
+        // SEARCH_MODE_OPTS = ["First Search", "Filter"];
+        let filter = app.search_mode.selected().unwrap_or(0) == 1;
+        let pid = app.selected_process;
+        app.edit_state = EditState::Busy;
+        drop(app);
+
+        compare_process(pid, &old, new.as_deref(), &datatype, &change, filter, Arc::clone(&self.app)).await;
+
+        let mut app = self.app.lock().await;
+        app.table_state.select(None);
+        app.edit_state = EditState::Select;
+        app.refresh_modules();
+    }
+
+    // A whole module is searched, the result is posted when ready so the interface keeps going
+    fn signature(&self, pid: u32, address: Option<usize>) {
+        let address = match address {
+            Some(address) => address,
+            None => return
+        };
+        let app_mutex = Arc::clone(&self.app);
+        tokio::spawn(async move {
+            let result = tokio::task::spawn_blocking(move || generate(pid, address)).await
+                .unwrap_or_else(|e| Err(e.to_string()));
+            app_mutex.lock().await.show_signature(result);
+        });
+    }
+
+    async fn save_state(&mut self) {
+        let mut app = self.app.lock().await;
+        let path = PathBuf::from(app.mismem_input.value());
+        let pid = app.selected_process;
+        app.state_path = app.mismem_input.value().to_string();
+        app.edit_state = EditState::Busy;
+        drop(app);
+
+        save_state_process(pid, &path, Arc::clone(&self.app)).await;
+
+        let mut app = self.app.lock().await;
+        app.edit_state = EditState::Select;
+    }
+
+    async fn restore_state(&mut self) {
+        let mut app = self.app.lock().await;
+        let path = PathBuf::from(app.mismem_input.value());
+        let pid = app.selected_process;
         app.edit_state = EditState::Busy;
         drop(app);
 
+        restore_state_process(pid, &path, Arc::clone(&self.app)).await;
+        // Results show the restored values
         update_process(Arc::clone(&self.app)).await;
 
         let mut app = self.app.lock().await;
@@ -0,0 +1,482 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use tui::widgets::TableState;
+
+use crate::mem::Datatype;
+use crate::expr::{AddressResolver, ProcessResolver};
+use crate::os::{enum_regions, read_process, write_process};
+use crate::process::Region;
+
+
+pub enum HexState {
+    Browse,
+    Goto,
+    Edit,
+    Inspect,
+}
+
+pub struct HexView {
+    pub state: HexState,
+    pub address: usize,
+    pub rows: usize,
+    pub bytes: Vec<Option<u8>>,
+    pub changed: Vec<bool>,
+
+    pub cursor: usize,
+    pub ascii: bool,
+    pub pending: BTreeMap<usize, u8>,
+    low_nibble: bool,
+
+    pub inspected: Vec<u8>,
+    pub inspector_state: TableState,
+    // Column of the inspector the selection is in
+    pub big_endian: bool,
+
+    regions: Vec<Region>,
+    resolver: ProcessResolver,
+    last_refresh: Instant,
+}
+
+impl HexView {
+    pub const BYTES_PER_ROW: usize = 16;
+    const PAGE_SIZE: usize = 0x1000;
+    const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+    const INSPECT_SIZE: usize = 32;
+
+    pub fn new() -> HexView {
+        HexView {
+            state: HexState::Browse,
+            address: 0,
+            rows: 16,
+            bytes: vec![],
+            changed: vec![],
+
+            cursor: 0,
+            ascii: false,
+            pending: BTreeMap::new(),
+            low_nibble: false,
+
+            inspected: vec![],
+            inspector_state: TableState::default(),
+            big_endian: false,
+
+            regions: vec![],
+            resolver: ProcessResolver::default(),
+            last_refresh: Instant::now(),
+        }
+    }
+
+    pub fn open(&mut self, pid: u32, address: usize) {
+        self.state = HexState::Browse;
+        self.regions = enum_regions(pid);
+        self.resolver = ProcessResolver::new(pid);
+        self.address = self.top(address);
+        self.cursor = address;
+        self.pending.clear();
+        self.low_nibble = false;
+        self.bytes.clear();
+        self.changed.clear();
+        self.refresh(pid);
+    }
+
+    pub fn byte_at(&self, address: usize) -> Option<u8> {
+        address.checked_sub(self.address)
+            .and_then(|i| self.bytes.get(i).copied().flatten())
+    }
+
+    // Start of a view showing address, the last row may end at the top of the address space but not past it
+    fn top(&self, address: usize) -> usize {
+        let len = self.rows.max(1) * HexView::BYTES_PER_ROW;
+        let address = address.min(usize::MAX - len + 1);
+        address - address % HexView::BYTES_PER_ROW
+    }
+
+    fn last_address(&self) -> usize {
+        self.address.saturating_add((self.rows * HexView::BYTES_PER_ROW).saturating_sub(1))
+    }
+
+    pub fn region(&self) -> Option<&Region> {
+        self.regions.iter().find(|r| r.base <= self.address && self.address < r.base + r.size)
+    }
+
+    // Unreadable pages are left as None and rendered as ??
+    pub fn refresh(&mut self, pid: u32) {
+        let len = self.rows * HexView::BYTES_PER_ROW;
+        let end = self.address.saturating_add(len);
+        let mut bytes = vec![None; len];
+
+        let mut page = self.address - self.address % HexView::PAGE_SIZE;
+        while page < end {
+            let start = page.max(self.address);
+            let stop = page.saturating_add(HexView::PAGE_SIZE).min(end);
+
+            let mut buffer = vec![0u8; stop - start];
+            if read_process(pid, start, &mut buffer) {
+                for (i, b) in buffer.into_iter().enumerate() {
+                    bytes[start - self.address + i] = Some(b);
+                }
+            }
+
+            match page.checked_add(HexView::PAGE_SIZE) {
+                Some(next) => page = next,
+                None => break
+            }
+        }
+
+        self.changed = bytes.iter().zip(self.bytes.iter()).map(|(new, old)| {
+            new.is_some() && old.is_some() && new != old
+        }).collect();
+        self.changed.resize(len, false);
+
+        self.bytes = bytes;
+        self.last_refresh = Instant::now();
+        self.inspect(pid);
+    }
+
+    pub fn refresh_if_stale(&mut self, pid: u32) {
+        if self.last_refresh.elapsed() >= HexView::REFRESH_INTERVAL || self.bytes.len() != self.rows * HexView::BYTES_PER_ROW {
+            self.refresh(pid);
+        }
+    }
+
+    // Gaps between regions are skipped so paging moves from one region to the next
+    pub fn scroll(&mut self, pid: u32, rows: isize) {
+        let delta = rows.unsigned_abs() * HexView::BYTES_PER_ROW;
+        let mut address = if rows >= 0 {
+            self.address.saturating_add(delta)
+        } else {
+            self.address.saturating_sub(delta)
+        };
+
+        let mapped = self.regions.iter().any(|r| r.base <= address && address < r.base + r.size);
+        if !mapped {
+            if rows >= 0 {
+                if let Some(next) = self.regions.iter().find(|r| r.base > address) {
+                    address = next.base;
+                }
+            } else if let Some(previous) = self.regions.iter().rev().find(|r| r.base + r.size <= address) {
+                let page = self.rows * HexView::BYTES_PER_ROW;
+                address = (previous.base + previous.size).saturating_sub(page).max(previous.base);
+            }
+        }
+
+        self.address = self.top(address);
+        self.cursor = self.cursor.clamp(self.address, self.last_address());
+        self.bytes.clear();
+        self.refresh(pid);
+    }
+
+    // Scrolls the view when the cursor leaves it
+    pub fn move_cursor(&mut self, pid: u32, delta: isize) {
+        let cursor = if delta >= 0 {
+            self.cursor.saturating_add(delta as usize)
+        } else {
+            self.cursor.saturating_sub(delta.unsigned_abs())
+        };
+        let last = self.last_address();
+
+        if cursor < self.address {
+            let rows = (self.address - cursor).div_ceil(HexView::BYTES_PER_ROW);
+            self.scroll(pid, -(rows as isize));
+        } else if cursor > last {
+            let rows = (cursor - last - 1) / HexView::BYTES_PER_ROW + 1;
+            self.scroll(pid, rows as isize);
+        }
+
+        self.cursor = cursor.clamp(self.address, self.last_address());
+        self.low_nibble = false;
+        self.inspect(pid);
+    }
+
+    pub fn toggle_column(&mut self) {
+        self.ascii = !self.ascii;
+        self.low_nibble = false;
+    }
+
+    // Edits
+
+    pub fn edit_nibble(&mut self, pid: u32, digit: u8) {
+        let current = match self.pending.get(&self.cursor).copied().or(self.byte_at(self.cursor)) {
+            Some(b) => b,
+            None => return
+        };
+
+        if self.low_nibble {
+            self.pending.insert(self.cursor, (current & 0xF0) | digit);
+            self.move_cursor(pid, 1);
+        } else {
+            self.pending.insert(self.cursor, (digit << 4) | (current & 0x0F));
+            self.low_nibble = true;
+        }
+    }
+
+    pub fn edit_ascii(&mut self, pid: u32, c: u8) {
+        if self.byte_at(self.cursor).is_some() {
+            self.pending.insert(self.cursor, c);
+            self.move_cursor(pid, 1);
+        }
+    }
+
+    pub fn revert(&mut self) {
+        self.pending.clear();
+        self.low_nibble = false;
+    }
+
+    // One write per contiguous run, failed runs stay pending
+    pub fn commit(&mut self, pid: u32) -> Vec<String> {
+        let mut runs: Vec<(usize, Vec<u8>)> = vec![];
+        for (&address, &b) in self.pending.iter() {
+            match runs.last_mut() {
+                Some((start, bytes)) if *start + bytes.len() == address => bytes.push(b),
+                _ => runs.push((address, vec![b]))
+            }
+        }
+
+        let mut failed = vec![];
+        for (start, bytes) in runs {
+            match write_process(pid, start, &bytes) {
+                Ok(()) => {
+                    for address in start..start + bytes.len() {
+                        self.pending.remove(&address);
+                    }
+                },
+                Err(error) => failed.push(error.to_string())
+            }
+        }
+
+        self.low_nibble = false;
+        self.refresh(pid);
+        failed
+    }
+
+    // Inspector
+
+    fn inspect(&mut self, pid: u32) {
+        let mut buffer = vec![0u8; HexView::INSPECT_SIZE];
+        self.inspected = if read_process(pid, self.cursor, &mut buffer) {
+            buffer
+        } else {
+            (self.cursor..self.cursor.saturating_add(HexView::INSPECT_SIZE)).map_while(|a| self.byte_at(a)).collect()
+        };
+    }
+
+    fn describe_address(&self, address: usize) -> String {
+        if let Some(symbol) = self.resolver.symbolize(address) {
+            symbol
+        } else if let Some(r) = self.regions.iter().find(|r| r.base <= address && address < r.base + r.size) {
+            format!("{:X}+{:X} {}", r.base, address - r.base, r.permissions())
+        } else {
+            String::from("invalid")
+        }
+    }
+
+    fn pointer_datatype(&self) -> Datatype {
+        if self.resolver.pointer_size() == 4 { Datatype::B4 } else { Datatype::B8 }
+    }
+
+    pub fn interpretations(&self) -> Vec<[String;3]> {
+        let unavailable = || String::from("-");
+
+        let mut rows: Vec<[String;3]> = Datatype::ALL.iter().map(|d| [
+            d.name().to_string(),
+            d.format(&self.inspected, false).unwrap_or_else(unavailable),
+            d.format(&self.inspected, true).unwrap_or_else(unavailable),
+        ]).collect();
+
+        let pointer = self.inspected.get(..self.resolver.pointer_size()).map(|bytes| {
+            let mut bytes = bytes.to_vec();
+            bytes.resize(std::mem::size_of::<usize>(), 0);
+            usize::from_le_bytes(bytes.try_into().unwrap())
+        });
+        rows.push([
+            String::from("pointer"),
+            pointer.map(|p| format!("{:X}", p)).unwrap_or_else(unavailable),
+            pointer.map(|p| self.describe_address(p)).unwrap_or_else(unavailable),
+        ]);
+
+        let string: String = self.inspected.iter()
+            .take_while(|&&b| b != 0)
+            .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+            .collect();
+        rows.push([String::from("string"), format!("\"{}\"", string), String::new()]);
+
+        rows
+    }
+
+    // Datatype and native bytes of the selected interpretation, strings can't be added
+    pub fn selected_interpretation(&self) -> Option<(Datatype, Vec<u8>)> {
+        let i = self.inspector_state.selected()?;
+        let datatype = match i {
+            i if i < Datatype::ALL.len() => Datatype::ALL[i],
+            i if i == Datatype::ALL.len() => self.pointer_datatype(),
+            _ => return None
+        };
+        let mut bytes = self.inspected.get(..datatype.size())?.to_vec();
+
+        // Only values have a big endian column, the pointer's holds its description
+        let big_endian = self.big_endian && i < Datatype::ALL.len();
+        if big_endian != cfg!(target_endian = "big") {
+            bytes.reverse();
+        }
+        Some((datatype, bytes))
+    }
+
+    pub fn select_endianness(&mut self, big_endian: bool) {
+        self.big_endian = big_endian;
+    }
+
+    pub fn next_interpretation(&mut self) {
+        let rows = Datatype::ALL.len() + 2;
+        self.inspector_state.select(Some(
+            self.inspector_state.selected().map_or(0, |i| (i + 1) % rows)
+        ));
+    }
+
+    pub fn previous_interpretation(&mut self) {
+        let rows = Datatype::ALL.len() + 2;
+        self.inspector_state.select(Some(
+            self.inspector_state.selected().map_or(0, |i| (i + rows - 1) % rows)
+        ));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(base: usize, size: usize) -> Region {
+        Region { base: base, size: size, readable: true, writable: true, executable: false, shared: false }
+    }
+
+    // 16 rows of 16 bytes over two regions with a gap between them
+    fn view(address: usize) -> HexView {
+        let mut view = HexView::new();
+        view.regions = vec![region(0x10000, 0x1000), region(0x20000, 0x2000)];
+        view.address = address;
+        view
+    }
+
+    #[test]
+    fn scroll_inside_region() {
+        let mut view = view(0x20000);
+        view.scroll(std::process::id(), 2);
+        assert_eq!(view.address, 0x20020);
+        view.scroll(std::process::id(), -1);
+        assert_eq!(view.address, 0x20010);
+    }
+
+    #[test]
+    fn scroll_skips_gaps() {
+        let mut view = view(0x10F00);
+        view.scroll(std::process::id(), 16);
+        assert_eq!(view.address, 0x20000);
+
+        // Back to the last page of the previous region
+        view.scroll(std::process::id(), -1);
+        assert_eq!(view.address, 0x10F00);
+    }
+
+    #[test]
+    fn scroll_past_last_region() {
+        let mut view = view(0x21F00);
+        view.scroll(std::process::id(), 32);
+        assert_eq!(view.address, 0x22100);
+        assert_eq!(view.bytes.len(), view.rows * HexView::BYTES_PER_ROW);
+    }
+
+    #[test]
+    fn scroll_at_top_of_address_space() {
+        let pid = std::process::id();
+        let mut view = HexView::new();
+        view.open(pid, 0xFFFFFFFFFFFFFFF0);
+        assert_eq!((view.address, view.cursor), (usize::MAX - 0xFF, 0xFFFFFFFFFFFFFFF0));
+        view.regions.clear();
+
+        view.move_cursor(pid, 16);
+        assert_eq!((view.address, view.cursor), (usize::MAX - 0xFF, usize::MAX));
+        view.scroll(pid, 4);
+        assert_eq!(view.address, usize::MAX - 0xFF);
+        view.move_cursor(pid, -0x100);
+        assert_eq!((view.address, view.cursor), (usize::MAX - 0x10F, usize::MAX - 0x100));
+    }
+
+    // Edits are committed into a buffer of the test process itself
+    fn buffer_view(buffer: &[u8]) -> HexView {
+        let mut view = HexView::new();
+        view.open(std::process::id(), buffer.as_ptr() as usize);
+        view
+    }
+
+    fn read(buffer: &[u8]) -> Vec<u8> {
+        buffer.iter().map(|b| unsafe { std::ptr::read_volatile(b) }).collect()
+    }
+
+    #[test]
+    fn edit_nibbles_and_ascii() {
+        let pid = std::process::id();
+        let buffer = vec![0x11u8; 64];
+        let start = buffer.as_ptr() as usize;
+        let mut view = buffer_view(&buffer);
+
+        view.edit_nibble(pid, 0xA);
+        assert_eq!(view.pending.get(&start), Some(&0xA1));
+        view.edit_nibble(pid, 0xB);
+        assert_eq!(view.cursor, start + 1);
+        view.edit_ascii(pid, b'x');
+        view.move_cursor(pid, 2);
+        view.edit_ascii(pid, b'y');
+
+        assert!(view.commit(pid).is_empty());
+        assert!(view.pending.is_empty());
+        assert_eq!(read(&buffer[..6]), [0xAB, b'x', 0x11, 0x11, b'y', 0x11]);
+    }
+
+    #[test]
+    fn failed_run_stays_pending() {
+        let pid = std::process::id();
+        let buffer = vec![0u8; 64];
+        let start = buffer.as_ptr() as usize;
+        let mut view = buffer_view(&buffer);
+
+        view.pending.insert(start, 1);
+        view.pending.insert(start + 1, 2);
+        view.pending.insert(0x10, 3);
+
+        assert_eq!(view.commit(pid).len(), 1);
+        assert_eq!(view.pending.keys().copied().collect::<Vec<_>>(), [0x10]);
+        assert_eq!(read(&buffer[..3]), [1, 2, 0]);
+    }
+
+    #[test]
+    fn revert_drops_edits() {
+        let buffer = vec![0u8; 64];
+        let mut view = buffer_view(&buffer);
+        view.edit_nibble(std::process::id(), 0xF);
+        view.revert();
+        assert!(view.pending.is_empty());
+    }
+
+    #[test]
+    fn big_endian_interpretation() {
+        let mut view = HexView::new();
+        view.inspected = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let u32_row = Datatype::ALL.iter().position(|d| d.name() == "u32").unwrap();
+        view.inspector_state.select(Some(u32_row));
+
+        let (datatype, bytes) = view.selected_interpretation().unwrap();
+        assert_eq!((datatype.name(), u32::from_ne_bytes(bytes.try_into().unwrap())), ("u32", 0x04030201));
+
+        view.select_endianness(true);
+        let (_, bytes) = view.selected_interpretation().unwrap();
+        assert_eq!(u32::from_ne_bytes(bytes.try_into().unwrap()), 0x01020304);
+
+        // The pointer has no big endian value
+        view.inspector_state.select(Some(Datatype::ALL.len()));
+        let (_, bytes) = view.selected_interpretation().unwrap();
+        assert_eq!(bytes, [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+}
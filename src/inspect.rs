@@ -0,0 +1,37 @@
+use crate::os::process_details;
+use crate::process::ProcessDetails;
+
+
+// Details of the selected process, its memory map is the shared MemoryMap
+pub struct Inspector {
+    pub details: Option<ProcessDetails>,
+    pub scroll: u16,
+}
+
+impl Inspector {
+    pub fn new() -> Inspector {
+        Inspector {
+            details: None,
+            scroll: 0,
+        }
+    }
+
+    pub fn open(&mut self, pid: u32) {
+        self.scroll = 0;
+        self.refresh(pid);
+    }
+
+    pub fn refresh(&mut self, pid: u32) {
+        self.details = match process_details(pid) {
+            Ok(details) => Some(details),
+            Err(error) => {
+                log::error!(" Can't read process details: {}", error);
+                None
+            }
+        };
+    }
+
+    pub fn scroll_details(&mut self, lines: i16) {
+        self.scroll = (self.scroll as i16 + lines).max(0) as u16;
+    }
+}
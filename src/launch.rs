@@ -0,0 +1,130 @@
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Read},
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use crate::os::{launch, resume_process};
+
+
+const MAX_LINES : usize = 1000;
+
+pub struct OutputLine {
+    pub text: String,
+    pub stderr: bool,
+}
+
+// Program started by `mismem run`, held at its entry point until resumed
+pub struct Launched {
+    pub command: String,
+    pub held: bool,
+    pub exit: Option<String>,
+    pub output: Arc<Mutex<VecDeque<OutputLine>>>,
+
+    child: Child,
+}
+
+impl Launched {
+    pub fn start(program: &str, args: &[String], env: &[(String, String)], cwd: Option<&str>) -> io::Result<Launched> {
+        let mut command = Command::new(program);
+        command.args(args)
+            .envs(env.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(cwd) = cwd {
+            command.current_dir(cwd);
+        }
+
+        let mut child = launch(&mut command)
+            .map_err(|error| io::Error::new(error.kind(), format!("can't start {}: {}", program, error)))?;
+
+        let output = Arc::new(Mutex::new(VecDeque::new()));
+        if let Some(stdout) = child.stdout.take() {
+            capture(stdout, false, Arc::clone(&output));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            capture(stderr, true, Arc::clone(&output));
+        }
+
+        Ok(Launched {
+            command: std::iter::once(program).chain(args.iter().map(|a| a.as_str())).collect::<Vec<_>>().join(" "),
+            held: true,
+            exit: None,
+            output: output,
+            child: child,
+        })
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+
+    pub fn resume(&mut self) -> io::Result<()> {
+        if self.held {
+            resume_process(self.pid())?;
+            self.held = false;
+        }
+        Ok(())
+    }
+
+    // Returns the exit status once, when the program ends
+    pub fn poll(&mut self) -> Option<String> {
+        if self.exit.is_some() {
+            return None;
+        }
+        let exit = match self.child.try_wait() {
+            Ok(Some(status)) => status.to_string(),
+            Ok(None) => return None,
+            // Reaped by a tracer that missed the exit stop, like after a SIGKILL
+            Err(_) => String::from("exited"),
+        };
+        self.held = false;
+        self.exit = Some(exit.clone());
+        Some(exit)
+    }
+
+    pub fn kill(&mut self) {
+        if self.exit.is_none() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+
+fn capture(stream: impl Read + Send + 'static, stderr: bool, output: Arc<Mutex<VecDeque<OutputLine>>>) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let mut line = vec![];
+        while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+            let text = strip_escapes(String::from_utf8_lossy(&line).trim_end());
+            line.clear();
+
+            let mut output = output.lock().unwrap();
+            if output.len() == MAX_LINES {
+                output.pop_front();
+            }
+            output.push_back(OutputLine { text: text, stderr: stderr });
+        }
+    });
+}
+
+// Colors and cursor movements would garble the pane
+fn strip_escapes(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            result.push(c);
+        } else if chars.next() == Some('[') {
+            // CSI sequences end with a letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() { break; }
+            }
+        }
+    }
+    result
+}
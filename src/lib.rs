@@ -0,0 +1,45 @@
+//! Scanning engine of mismem, usable without its terminal interface.
+//!
+//! - [`os`] opens other processes to read and write their memory, and lists processes,
+//!   modules and memory regions (Linux and Windows)
+//! - [`remote`] points [`os`] at the processes of a machine running `mismem-agent`
+//! - [`coredump`] points [`os`] at the process saved in an ELF core file, read-only
+//! - [`mem`] holds scan results in [`mem::Memory`] and the value types in [`mem::Datatype`]
+//! - [`scan`] runs first scans, filters and updates over a [`mem::Memory`]
+//! - [`expr`] resolves address expressions like `game+0x10` or `[[game+0x1F0]+0x18]`
+//! - [`export`] saves and loads results as JSON or CSV
+//! - [`snapshot`] saves the memory of a process to a file and compares it with a later state
+//! - [`savestate`] saves the private writable memory of a process and writes it back later
+//!
+//! Build with `default-features = false` to leave out the interface and its dependencies.
+//!
+//! ```no_run
+//! use mismem::mem::Datatype;
+//! use mismem::scan::{filter_memory, scan_memory};
+//!
+//! let pid = 1234;
+//! let datatype = Datatype::from_name("u32").unwrap();
+//!
+//! let mut results = scan_memory(pid, &datatype.parse("100")?, &datatype, None, |_| {})?;
+//! // ... the value changes in the target ...
+//! filter_memory(pid, &mut results, &datatype.parse("99")?, &datatype, |_| {})?;
+//!
+//! for [address, value, old_value] in results.iter() {
+//!     println!("{} was {}, now {}", address, old_value, value);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+#[cfg_attr(windows, path = "win.rs")]
+#[cfg_attr(target_os = "linux", path = "lin.rs")]
+mod native;
+pub mod os;
+pub mod remote;
+pub mod coredump;
+pub mod process;
+pub mod mem;
+pub mod scan;
+pub mod expr;
+pub mod export;
+pub mod snapshot;
+pub mod savestate;
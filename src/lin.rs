@@ -0,0 +1,1169 @@
+//! Linux backend, memory goes through `/proc/[pid]/mem` and debugging through ptrace
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{self, Read},
+    os::unix::{fs::FileExt, process::CommandExt},
+    path::Path,
+    process::{self, Child},
+    ptr,
+    sync::{atomic::{AtomicBool, Ordering}, mpsc, Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::os::local_only;
+use crate::process::{ProcessInfo, ProcessDetails, Module, Region, Mapping, Register, DebugEvent};
+
+
+/// Every process the current user can see, unsorted
+pub fn enum_processes() -> Vec<ProcessInfo> {
+    let mut processes = Vec::<ProcessInfo>::new();
+
+    let entries = match fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return processes
+    };
+
+    let users = read_names("/etc/passwd");
+    let boot_time = read_boot_time();
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as u64;
+
+    for entry in entries.flatten() {
+        let pid = match entry.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) {
+            Some(pid) => pid,
+            None => continue
+        };
+
+        // Kernel threads and processes we can't open are skipped like on Windows
+        if !check_process(pid) {
+            continue;
+        }
+
+        let name = fs::read_to_string(format!("/proc/{}/comm", pid))
+            .map(|s| s.trim_end().to_string())
+            .unwrap_or_default();
+
+        let status = fs::read_to_string(format!("/proc/{}/status", pid)).unwrap_or_default();
+        let status_field = |key: &str| status.lines()
+            .find(|l| l.starts_with(key))
+            .and_then(|l| l.split_whitespace().nth(1));
+
+        let mem_usage = status_field("VmRSS:")
+            .and_then(|kb| kb.parse::<f64>().ok())
+            .unwrap_or(0.0);
+
+        let user = status_field("Uid:")
+            .map(|uid| users.get(uid).cloned().unwrap_or_else(|| uid.to_string()))
+            .unwrap_or_default();
+
+        let cmdline = read_strings(&format!("/proc/{}/cmdline", pid)).join(" ");
+
+        let stat = read_stat(pid);
+        let ppid = stat.get(1).and_then(|p| p.parse().ok()).unwrap_or(0);
+        let start_time = stat.get(19)
+            .and_then(|t| t.parse::<u64>().ok())
+            .map(|t| boot_time + t / ticks)
+            .unwrap_or(0);
+
+        processes.push(ProcessInfo{name: name, memory: mem_usage, pid: pid, ppid: ppid, user: user, cmdline: cmdline, start_time: start_time});
+    }
+
+    processes
+}
+
+
+/// Executable, environment, owner and threads of one process
+pub fn process_details(pid : u32) -> io::Result<ProcessDetails> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid))?;
+    let status_field = |key: &str| status.lines()
+        .find(|l| l.starts_with(key))
+        .and_then(|l| l.split_whitespace().nth(1))
+        .unwrap_or("");
+    let lookup = |names: HashMap<String, String>, id: &str| names.get(id).cloned().unwrap_or_else(|| id.to_string());
+
+    let mut threads: Vec<u32> = fs::read_dir(format!("/proc/{}/task", pid))?
+        .flatten()
+        .filter_map(|e| e.file_name().to_str()?.parse().ok())
+        .collect();
+    threads.sort();
+
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as u64;
+    let start_time = read_stat(pid).get(19)
+        .and_then(|t| t.parse::<u64>().ok())
+        .map(|t| read_boot_time() + t / ticks)
+        .unwrap_or(0);
+
+    Ok(ProcessDetails {
+        exe: fs::read_link(format!("/proc/{}/exe", pid)).map(|p| p.to_string_lossy().to_string()).unwrap_or_default(),
+        cmdline: read_strings(&format!("/proc/{}/cmdline", pid)).join(" "),
+        environment: read_strings(&format!("/proc/{}/environ", pid)),
+        user: lookup(read_names("/etc/passwd"), status_field("Uid:")),
+        group: lookup(read_names("/etc/group"), status_field("Gid:")),
+        threads: threads,
+        start_time: start_time,
+        resident: status_field("VmRSS:").parse().unwrap_or(0),
+        virtual_size: status_field("VmSize:").parse().unwrap_or(0),
+    })
+}
+
+
+// NUL separated files like cmdline and environ
+fn read_strings(path : &str) -> Vec<String> {
+    fs::read(path)
+        .map(|c| c.split(|&b| b == 0)
+            .filter(|a| !a.is_empty())
+            .map(|a| String::from_utf8_lossy(a).to_string())
+            .collect())
+        .unwrap_or_default()
+}
+
+
+// Fields of /proc/[pid]/stat after the command name, which may contain spaces
+fn read_stat(pid : u32) -> Vec<String> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap_or_default();
+    match stat.rfind(')') {
+        Some(end) => stat[end + 1..].split_whitespace().map(String::from).collect(),
+        None => vec![]
+    }
+}
+
+fn read_boot_time() -> u64 {
+    fs::read_to_string("/proc/stat").ok()
+        .and_then(|stat| stat.lines()
+            .find(|l| l.starts_with("btime"))
+            .and_then(|l| l.split_whitespace().nth(1))
+            .and_then(|t| t.parse().ok()))
+        .unwrap_or(0)
+}
+
+// Id to name from /etc/passwd or /etc/group
+fn read_names(path : &str) -> HashMap<String, String> {
+    fs::read_to_string(path).unwrap_or_default().lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?;
+            Some((uid.to_string(), name.to_string()))
+        })
+        .collect()
+}
+
+
+/// True if the process is running and its memory can be opened
+///
+/// Zombies still have a mem file but nothing to read
+pub fn check_process(pid : u32) -> bool {
+    OpenOptions::new().read(true).write(true).open(format!("/proc/{}/mem", pid)).is_ok()
+        && read_stat(pid).first().is_some_and(|state| state != "Z")
+}
+
+
+/// Memory map of the process, with the file backing each region
+pub fn enum_mappings(pid : u32) -> Vec<Mapping> {
+    let maps = match fs::read_to_string(format!("/proc/{}/maps", pid)) {
+        Ok(maps) => maps,
+        Err(_) => return vec![]
+    };
+
+    maps.lines().filter_map(|line| {
+        let mut fields = line.splitn(6, ' ');
+        let range = fields.next()?;
+        let perms = fields.next()?.as_bytes();
+        let offset = u64::from_str_radix(fields.next()?, 16).unwrap_or(0);
+        let path = fields.nth(2).unwrap_or("").trim().to_string();
+
+        let (start, end) = range.split_once('-')?;
+        let start = usize::from_str_radix(start, 16).ok()?;
+        let end = usize::from_str_radix(end, 16).ok()?;
+
+        let region = Region {
+            base: start,
+            size: end - start,
+            readable: perms.first() == Some(&b'r'),
+            writable: perms.get(1) == Some(&b'w'),
+            executable: perms.get(2) == Some(&b'x'),
+            shared: perms.get(3) == Some(&b's'),
+        };
+        Some(Mapping { region: region, offset: offset, path: path })
+    }).collect()
+}
+
+
+/// Executable and libraries loaded by the process
+///
+/// Mappings of the same file are merged into one module
+pub fn enum_modules(pid : u32) -> Vec<Module> {
+    let mut modules = Vec::<Module>::new();
+    let mut paths = Vec::<String>::new();
+
+    for Mapping { region, path, .. } in enum_mappings(pid) {
+        if !path.starts_with('/') {
+            continue;
+        }
+
+        match paths.iter().position(|p| *p == path) {
+            Some(i) => {
+                let module = &mut modules[i];
+                let end = (module.base + module.size).max(region.base + region.size);
+                module.base = module.base.min(region.base);
+                module.size = end - module.base;
+            },
+            None => {
+                let name = Path::new(&path).file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.clone());
+                modules.push(Module { name: name, base: region.base, size: region.size });
+                paths.push(path);
+            }
+        }
+    }
+
+    modules
+}
+
+
+/// Committed memory of the process, sorted by address
+///
+/// `[vvar]` and similar mappings can't be read through `/proc/[pid]/mem`
+pub fn enum_regions(pid : u32) -> Vec<Region> {
+    enum_mappings(pid).into_iter()
+        .filter(|m| m.region.readable && m.path != "[vvar]" && m.path != "[vsyscall]")
+        .map(|m| m.region)
+        .collect()
+}
+
+
+/// 4 or 8, depending on the bitness of the process
+pub fn pointer_size(pid : u32) -> usize {
+    let mut header = [0u8; 5];
+    let is_32bit = File::open(format!("/proc/{}/exe", pid))
+        .and_then(|mut f| f.read_exact(&mut header))
+        .map(|_| header[..4] == *b"\x7fELF" && header[4] == 1)
+        .unwrap_or(false);
+
+    if is_32bit { 4 } else { std::mem::size_of::<usize>() }
+}
+
+
+/// Fills buffer from address, false if any of it can't be read
+pub fn read_process(pid : u32, address : usize, buffer: &mut [u8]) -> bool {
+    match Process::open(pid) {
+        Ok(process) => process.read(address, buffer),
+        Err(_) => false
+    }
+}
+
+
+/// Writes all of target_bytes at address
+pub fn write_process(pid : u32, address : usize, target_bytes: &[u8]) -> io::Result<()> {
+    Process::open(pid)?.write(address, target_bytes)
+}
+
+
+/// Starts command held at its entry point, resume_process lets it run
+///
+/// Traced through exec to stop at the first instruction, then left stopped by SIGSTOP for anyone to attach
+pub fn launch(command : &mut process::Command) -> io::Result<Child> {
+    unsafe {
+        command.pre_exec(|| {
+            if libc::ptrace(libc::PTRACE_TRACEME, 0, ptr::null_mut::<libc::c_void>(), ptr::null_mut::<libc::c_void>()) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let mut child = command.spawn()?;
+    let pid = child.id() as i32;
+
+    let mut status = 0;
+    let stopped = unsafe { libc::waitpid(pid, &mut status, 0) } == pid && libc::WIFSTOPPED(status);
+    if !stopped || unsafe { libc::ptrace(libc::PTRACE_DETACH, pid, ptr::null_mut::<libc::c_void>(), libc::SIGSTOP as *mut libc::c_void) } == -1 {
+        let error = io::Error::other(format!("{:?} didn't stop at its entry point", command.get_program()));
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(error);
+    }
+    Ok(child)
+}
+
+
+/// Lets a process started by launch run
+pub fn resume_process(pid : u32) -> io::Result<()> {
+    match unsafe { libc::kill(pid as i32, libc::SIGCONT) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(())
+    }
+}
+
+
+/// Keeps a process stopped until dropped, a process that was stopped already stays stopped
+///
+/// Stopped with SIGSTOP, like Ctrl-Z in a terminal, and continued with SIGCONT
+pub struct Suspended {
+    pid: u32,
+    resume: bool,
+}
+
+impl Suspended {
+    /// Returns once every thread has stopped
+    pub fn new(pid : u32) -> io::Result<Suspended> {
+        local_only("stopping a process")?;
+        // Traced threads are stopped too, zombies never will
+        let stopped = || {
+            let states = thread_states(pid);
+            !states.is_empty() && states.iter().all(|state| matches!(state, 'T' | 't' | 'Z' | 'X'))
+        };
+        if stopped() {
+            return Ok(Suspended { pid: pid, resume: false });
+        }
+
+        if unsafe { libc::kill(pid as i32, libc::SIGSTOP) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let suspended = Suspended { pid: pid, resume: true };
+        for _ in 0..200 {
+            if stopped() {
+                return Ok(suspended);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        Err(io::Error::new(io::ErrorKind::TimedOut, format!("process {} didn't stop", pid)))
+    }
+}
+
+impl Drop for Suspended {
+    fn drop(&mut self) {
+        if self.resume {
+            let _ = resume_process(self.pid);
+        }
+    }
+}
+
+// State of every thread, like R, S or T
+fn thread_states(pid : u32) -> Vec<char> {
+    let tasks = match fs::read_dir(format!("/proc/{}/task", pid)) {
+        Ok(tasks) => tasks,
+        Err(_) => return vec![]
+    };
+    tasks.filter_map(|task| {
+        let stat = fs::read_to_string(task.ok()?.path().join("stat")).ok()?;
+        stat[stat.rfind(')')? + 1..].trim_start().chars().next()
+    }).collect()
+}
+
+
+/// Open process for many reads and writes in a row
+///
+/// `/proc/[pid]/mem` ignores page protection, so code can be patched directly
+pub struct Process {
+    mem: File,
+}
+
+impl Process {
+    pub fn open(pid : u32) -> io::Result<Process> {
+        let path = format!("/proc/{}/mem", pid);
+        let mem = OpenOptions::new().read(true).write(true).open(&path)
+            .or_else(|_| File::open(&path))?;
+        Ok(Process { mem: mem })
+    }
+
+    pub fn read(&self, address : usize, buffer: &mut [u8]) -> bool {
+        let mut done = 0;
+        while done < buffer.len() {
+            match self.mem.read_at(&mut buffer[done..], (address + done) as u64) {
+                Ok(0) | Err(_) => return false,
+                Ok(n) => done += n
+            }
+        }
+        true
+    }
+
+    pub fn write(&self, address : usize, target_bytes: &[u8]) -> io::Result<()> {
+        let num_bytes = target_bytes.len();
+        let mut done = 0;
+        while done < num_bytes {
+            match self.mem.write_at(&target_bytes[done..], (address + done) as u64) {
+                Ok(0) => {
+                    return Err(io::Error::new(io::ErrorKind::WriteZero, format!("only {} of {} bytes written at {:X}", done, num_bytes, address)));
+                },
+                Ok(n) => done += n,
+                Err(error) if done > 0 => {
+                    return Err(io::Error::new(error.kind(), format!("only {} of {} bytes written at {:X}: {}", done, num_bytes, address, error)));
+                },
+                Err(error) => {
+                    return Err(io::Error::new(error.kind(), format!("can't write at {:X}: {}", address, error)));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+
+
+
+// Watchpoints
+//
+// ptrace only accepts requests from the thread that attached, so a dedicated
+// thread seizes every thread of the target, arms DR0/DR7 and collects hits.
+
+/// Hardware watchpoint on a few bytes of the process
+pub struct Watcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watcher {
+    /// Hits are keyed by the instruction pointer after the access
+    pub fn start(pid : u32, address : usize, len : usize, access : bool, hits: Arc<Mutex<BTreeMap<usize, usize>>>) -> io::Result<Watcher> {
+        local_only("watchpoints")?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let thread_stop = Arc::clone(&stop);
+
+        let thread = thread::spawn(move || {
+            tracer::watch(pid, address, len, access, ready_tx, &thread_stop, &hits);
+        });
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Watcher { stop: stop, thread: Some(thread) }),
+            Ok(Err(error)) => {
+                let _ = thread.join();
+                Err(error)
+            },
+            Err(_) => Err(io::Error::other("watchpoint thread died"))
+        }
+    }
+
+    /// False once the target has exited
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|t| !t.is_finished())
+    }
+
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+
+// Debugger
+//
+// Same threading model as the watcher, requests are sent to the tracer thread
+// and stops come back as events. A breakpoint hit stops every thread.
+
+enum Command {
+    SetBreakpoint(usize),
+    RemoveBreakpoint(usize),
+    Registers(u32),
+    SetRegister(u32, &'static str, u64),
+    Step(u32),
+    Resume,
+}
+
+enum Reply {
+    Done,
+    Registers(Vec<Register>),
+}
+
+type Request = (Command, mpsc::Sender<io::Result<Reply>>);
+
+/// Breakpoints and registers of a process, events come through poll
+pub struct Debugger {
+    commands: Option<mpsc::Sender<Request>>,
+    events: mpsc::Receiver<DebugEvent>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Debugger {
+    pub fn attach(pid : u32) -> io::Result<Debugger> {
+        local_only("the debugger")?;
+        let (commands_tx, commands_rx) = mpsc::channel();
+        let (events_tx, events_rx) = mpsc::channel();
+        let (ready_tx, ready_rx) = mpsc::channel();
+
+        let thread = thread::spawn(move || {
+            tracer::debug(pid, ready_tx, &commands_rx, &events_tx);
+        });
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Debugger { commands: Some(commands_tx), events: events_rx, thread: Some(thread) }),
+            Ok(Err(error)) => {
+                let _ = thread.join();
+                Err(error)
+            },
+            Err(_) => Err(io::Error::other("debugger thread died"))
+        }
+    }
+
+    fn request(&self, command : Command) -> io::Result<Reply> {
+        let (reply_tx, reply_rx) = mpsc::channel();
+        let detached = || io::Error::new(io::ErrorKind::BrokenPipe, "debugger is detached");
+
+        self.commands.as_ref().ok_or_else(detached)?
+            .send((command, reply_tx)).map_err(|_| detached())?;
+        reply_rx.recv().map_err(|_| detached())?
+    }
+
+    pub fn set_breakpoint(&self, address : usize) -> io::Result<()> {
+        self.request(Command::SetBreakpoint(address)).map(|_| ())
+    }
+
+    pub fn remove_breakpoint(&self, address : usize) -> io::Result<()> {
+        self.request(Command::RemoveBreakpoint(address)).map(|_| ())
+    }
+
+    pub fn registers(&self, tid : u32) -> io::Result<Vec<Register>> {
+        match self.request(Command::Registers(tid))? {
+            Reply::Registers(registers) => Ok(registers),
+            Reply::Done => Ok(vec![])
+        }
+    }
+
+    pub fn set_register(&self, tid : u32, name : &'static str, value : u64) -> io::Result<()> {
+        self.request(Command::SetRegister(tid, name, value)).map(|_| ())
+    }
+
+    /// The new position is reported as a Step event
+    pub fn step(&self, tid : u32) -> io::Result<()> {
+        self.request(Command::Step(tid)).map(|_| ())
+    }
+
+    pub fn resume(&self) -> io::Result<()> {
+        self.request(Command::Resume).map(|_| ())
+    }
+
+    pub fn poll(&self) -> Option<DebugEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// Breakpoints are removed and every thread is released
+    pub fn detach(&mut self) {
+        self.commands.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Debugger {
+    fn drop(&mut self) {
+        self.detach();
+    }
+}
+
+
+#[cfg(not(target_arch = "x86_64"))]
+mod tracer {
+    use super::*;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(io::ErrorKind::Unsupported, "tracing needs an x86-64 build")
+    }
+
+    pub fn watch(_pid : u32, _address : usize, _len : usize, _access : bool, ready: mpsc::Sender<io::Result<()>>, _stop: &AtomicBool, _hits: &Mutex<BTreeMap<usize, usize>>) {
+        let _ = ready.send(Err(unsupported()));
+    }
+
+    pub fn debug(_pid : u32, ready: mpsc::Sender<io::Result<()>>, _commands: &mpsc::Receiver<Request>, _events: &mpsc::Sender<DebugEvent>) {
+        let _ = ready.send(Err(unsupported()));
+    }
+}
+
+
+#[cfg(target_arch = "x86_64")]
+mod tracer {
+    use super::*;
+
+    const POLL_INTERVAL: Duration = Duration::from_millis(5);
+    const INT3: u8 = 0xCC;
+
+    fn ptrace(request : libc::c_uint, tid : i32, addr : usize, data : usize) -> io::Result<libc::c_long> {
+        // PEEKUSER returns the value, so errors are only told apart through errno
+        unsafe { *libc::__errno_location() = 0 };
+        let result = unsafe { libc::ptrace(request, tid, addr as *mut libc::c_void, data as *mut libc::c_void) };
+        match io::Error::last_os_error() {
+            error if result == -1 && error.raw_os_error() != Some(0) => Err(error),
+            _ => Ok(result)
+        }
+    }
+
+    // Tracer threads pass -1 with __WNOTHREAD, so children of the rest of mismem, like the program
+    // of `mismem run`, are left to Child::wait
+    fn wait(tid : i32, flags : i32) -> io::Result<(i32, i32)> {
+        let mut status = 0;
+        match unsafe { libc::waitpid(tid, &mut status, libc::__WALL | flags) } {
+            -1 => Err(io::Error::last_os_error()),
+            tid => Ok((tid, status))
+        }
+    }
+
+    fn event(status : i32) -> i32 {
+        status >> 16
+    }
+
+    fn exited(status : i32) -> bool {
+        libc::WIFEXITED(status) || libc::WIFSIGNALED(status)
+    }
+
+    // Exiting threads are detached so their exit status goes to the parent, not to the tracer
+    fn exiting(tid : i32, status : i32) -> bool {
+        if event(status) != libc::PTRACE_EVENT_EXIT {
+            return false;
+        }
+        let _ = ptrace(libc::PTRACE_DETACH, tid, 0, 0);
+        true
+    }
+
+    fn group_stop(status : i32) -> bool {
+        event(status) == libc::PTRACE_EVENT_STOP
+            && matches!(libc::WSTOPSIG(status), libc::SIGSTOP | libc::SIGTSTP | libc::SIGTTIN | libc::SIGTTOU)
+    }
+
+    fn event_message(tid : i32) -> io::Result<i32> {
+        let mut message: libc::c_ulong = 0;
+        ptrace(libc::PTRACE_GETEVENTMSG, tid, 0, &mut message as *mut _ as usize)?;
+        Ok(message as i32)
+    }
+
+    fn debug_register(i : usize) -> usize {
+        std::mem::offset_of!(libc::user, u_debugreg) + i * std::mem::size_of::<u64>()
+    }
+
+    fn get_regs(tid : i32) -> io::Result<libc::user_regs_struct> {
+        let mut regs: libc::user_regs_struct = unsafe { std::mem::zeroed() };
+        ptrace(libc::PTRACE_GETREGS, tid, 0, &mut regs as *mut _ as usize)?;
+        Ok(regs)
+    }
+
+    fn set_regs(tid : i32, regs : &libc::user_regs_struct) -> io::Result<()> {
+        ptrace(libc::PTRACE_SETREGS, tid, 0, regs as *const _ as usize).map(|_| ())
+    }
+
+    // Seizes every thread and calls stopped once for each while it's interrupted.
+    // Threads may be spawned meanwhile, so tasks are listed until nothing new shows up.
+    fn seize_all(pid : u32, mut stopped: impl FnMut(i32, i32) -> io::Result<()>) -> io::Result<()> {
+        let mut seized = HashSet::new();
+        loop {
+            let tasks: Vec<i32> = fs::read_dir(format!("/proc/{}/task", pid))?
+                .flatten()
+                .filter_map(|e| e.file_name().to_str().and_then(|s| s.parse().ok()))
+                .filter(|tid| !seized.contains(tid))
+                .collect();
+            if tasks.is_empty() {
+                return Ok(());
+            }
+
+            let mut interrupted = vec![];
+            for tid in tasks {
+                match ptrace(libc::PTRACE_SEIZE, tid, 0, (libc::PTRACE_O_TRACECLONE | libc::PTRACE_O_TRACEEXIT) as usize) {
+                    Ok(_) => {
+                        ptrace(libc::PTRACE_INTERRUPT, tid, 0, 0)?;
+                        interrupted.push(tid);
+                    },
+                    // The thread exited in the meantime
+                    Err(error) if error.raw_os_error() == Some(libc::ESRCH) => continue,
+                    // Cloned by a seized thread, it is already ours and starts stopped
+                    Err(error) if error.raw_os_error() == Some(libc::EPERM) && !seized.is_empty() => {
+                        interrupted.push(tid);
+                    },
+                    Err(error) => {
+                        return Err(io::Error::new(error.kind(), format!("can't attach to thread {}: {}", tid, error)));
+                    }
+                }
+                seized.insert(tid);
+            }
+
+            for tid in interrupted {
+                let (_, status) = wait(tid, 0)?;
+                if !exited(status) && !exiting(tid, status) {
+                    stopped(tid, status)?;
+                }
+            }
+        }
+    }
+
+    // Signal to pass on when resuming from this stop
+    fn pending_signal(status : i32) -> i32 {
+        if event(status) == 0 { libc::WSTOPSIG(status) } else { 0 }
+    }
+
+
+    // Watchpoints
+
+    pub fn watch(pid : u32, address : usize, len : usize, access : bool, ready: mpsc::Sender<io::Result<()>>, stop: &AtomicBool, hits: &Mutex<BTreeMap<usize, usize>>) {
+        let mut watcher = Watch { threads: HashMap::new(), address: address, dr7: debug_control(len, access) };
+
+        if let Err(error) = watcher.attach(pid) {
+            watcher.detach();
+            let _ = ready.send(Err(error));
+            return;
+        }
+        let _ = ready.send(Ok(()));
+
+        watcher.run(stop, hits);
+        watcher.detach();
+    }
+
+    // L0 enable, RW0 = 01 (write) or 11 (read/write), LEN0 = 1, 2, 8 or 4 bytes
+    fn debug_control(len : usize, access : bool) -> usize {
+        let rw = if access { 0b11 } else { 0b01 };
+        let len = match len { 1 => 0b00, 2 => 0b01, 8 => 0b10, _ => 0b11 };
+        1 | (rw << 16) | (len << 18)
+    }
+
+    struct Watch {
+        // Thread id and whether its debug registers are set
+        threads: HashMap<i32, bool>,
+        address: usize,
+        dr7: usize,
+    }
+
+    impl Watch {
+        fn attach(&mut self, pid : u32) -> io::Result<()> {
+            seize_all(pid, |tid, status| {
+                self.arm(tid)?;
+                ptrace(libc::PTRACE_CONT, tid, 0, pending_signal(status) as usize).map(|_| ())
+            })
+        }
+
+        fn arm(&mut self, tid : i32) -> io::Result<()> {
+            ptrace(libc::PTRACE_POKEUSER, tid, debug_register(0), self.address)?;
+            ptrace(libc::PTRACE_POKEUSER, tid, debug_register(7), self.dr7)?;
+            self.threads.insert(tid, true);
+            Ok(())
+        }
+
+        fn run(&mut self, stop: &AtomicBool, hits: &Mutex<BTreeMap<usize, usize>>) {
+            while !stop.load(Ordering::Relaxed) && !self.threads.is_empty() {
+                let (tid, status) = match wait(-1, libc::WNOHANG | libc::__WNOTHREAD) {
+                    Ok((0, _)) => {
+                        thread::sleep(POLL_INTERVAL);
+                        continue;
+                    },
+                    Ok(stopped) => stopped,
+                    Err(_) => break
+                };
+
+                if exited(status) || exiting(tid, status) {
+                    self.threads.remove(&tid);
+                    continue;
+                }
+                if !libc::WIFSTOPPED(status) {
+                    continue;
+                }
+                if group_stop(status) {
+                    let _ = ptrace(libc::PTRACE_LISTEN, tid, 0, 0);
+                    continue;
+                }
+
+                let signal = libc::WSTOPSIG(status);
+                let resume = match event(status) {
+                    libc::PTRACE_EVENT_CLONE => {
+                        if let Ok(new) = event_message(tid) {
+                            self.threads.entry(new).or_insert(false);
+                        }
+                        0
+                    },
+                    // Clones start with this stop
+                    libc::PTRACE_EVENT_STOP => {
+                        if !self.threads.get(&tid).copied().unwrap_or(false) {
+                            let _ = self.arm(tid);
+                        }
+                        0
+                    },
+                    _ if signal == libc::SIGTRAP && self.take_hit(tid) => {
+                        if let Ok(regs) = get_regs(tid) {
+                            *hits.lock().unwrap().entry(regs.rip as usize).or_insert(0) += 1;
+                        }
+                        0
+                    },
+                    _ => signal
+                };
+                let _ = ptrace(libc::PTRACE_CONT, tid, 0, resume as usize);
+            }
+        }
+
+        // DR6 B0 tells our watchpoint apart from breakpoints of the target itself
+        fn take_hit(&self, tid : i32) -> bool {
+            match ptrace(libc::PTRACE_PEEKUSER, tid, debug_register(6), 0) {
+                Ok(dr6) if dr6 & 1 != 0 => {
+                    let _ = ptrace(libc::PTRACE_POKEUSER, tid, debug_register(6), 0);
+                    true
+                },
+                _ => false
+            }
+        }
+
+        // Every thread is interrupted, cleared and released, pending signals are passed on
+        fn detach(&mut self) {
+            for &tid in self.threads.keys() {
+                let _ = ptrace(libc::PTRACE_INTERRUPT, tid, 0, 0);
+            }
+
+            for &tid in self.threads.keys() {
+                let status = match wait(tid, 0) {
+                    Ok((_, status)) if libc::WIFSTOPPED(status) => status,
+                    _ => continue
+                };
+
+                let pending = if libc::WSTOPSIG(status) == libc::SIGTRAP && self.take_hit(tid) {
+                    0
+                } else {
+                    pending_signal(status)
+                };
+
+                let _ = ptrace(libc::PTRACE_POKEUSER, tid, debug_register(7), 0);
+                let _ = ptrace(libc::PTRACE_POKEUSER, tid, debug_register(0), 0);
+                let _ = ptrace(libc::PTRACE_POKEUSER, tid, debug_register(6), 0);
+                let _ = ptrace(libc::PTRACE_DETACH, tid, 0, pending as usize);
+            }
+            self.threads.clear();
+        }
+    }
+
+
+    // Debugger
+
+    macro_rules! registers {
+        ($($name:ident),+) => {
+            fn list_registers(regs : &libc::user_regs_struct) -> Vec<Register> {
+                vec![$(Register { name: stringify!($name), value: regs.$name as u64 }),+]
+            }
+
+            fn write_register(regs : &mut libc::user_regs_struct, name : &str, value : u64) -> bool {
+                match name {
+                    $(stringify!($name) => regs.$name = value as _,)+
+                    _ => return false
+                }
+                true
+            }
+        }
+    }
+
+    registers!(rax, rbx, rcx, rdx, rsi, rdi, rbp, rsp, r8, r9, r10, r11, r12, r13, r14, r15, rip, eflags);
+
+    pub fn debug(pid : u32, ready: mpsc::Sender<io::Result<()>>, commands: &mpsc::Receiver<Request>, events: &mpsc::Sender<DebugEvent>) {
+        let mem = match Process::open(pid) {
+            Ok(mem) => mem,
+            Err(error) => {
+                let _ = ready.send(Err(error));
+                return;
+            }
+        };
+
+        let mut debugger = Debug {
+            mem: mem,
+            threads: HashSet::new(),
+            breakpoints: BTreeMap::new(),
+            pending: HashMap::new(),
+            stopped: None,
+            stepping: None,
+        };
+
+        // A stopped process, like one launched and held at its entry, stays stopped
+        let attached = seize_all(pid, |tid, status| {
+            debugger.threads.insert(tid);
+            let request = if group_stop(status) { libc::PTRACE_LISTEN } else { libc::PTRACE_CONT };
+            ptrace(request, tid, 0, pending_signal(status) as usize).map(|_| ())
+        });
+        if let Err(error) = attached {
+            debugger.detach();
+            let _ = ready.send(Err(error));
+            return;
+        }
+        let _ = ready.send(Ok(()));
+
+        debugger.run(commands, events);
+        debugger.detach();
+    }
+
+    struct Step {
+        tid: i32,
+        // Breakpoint lifted to execute the original instruction
+        reinsert: Option<usize>,
+        // False when stepping over a breakpoint to resume
+        report: bool,
+    }
+
+    struct Debug {
+        mem: Process,
+        threads: HashSet<i32>,
+        // Address and original byte
+        breakpoints: BTreeMap<usize, u8>,
+        // Signals to deliver when the threads resume
+        pending: HashMap<i32, i32>,
+        // Thread that caused the current all-stop
+        stopped: Option<i32>,
+        stepping: Option<Step>,
+    }
+
+    impl Debug {
+        fn run(&mut self, commands: &mpsc::Receiver<Request>, events: &mpsc::Sender<DebugEvent>) {
+            loop {
+                match commands.try_recv() {
+                    Ok((command, reply)) => {
+                        let _ = reply.send(self.handle(command));
+                        continue;
+                    },
+                    Err(mpsc::TryRecvError::Disconnected) => return,
+                    Err(mpsc::TryRecvError::Empty) => {}
+                }
+
+                match wait(-1, libc::WNOHANG | libc::__WNOTHREAD) {
+                    Ok((0, _)) => thread::sleep(POLL_INTERVAL),
+                    Ok((tid, status)) => self.on_stop(tid, status, events),
+                    Err(_) => self.threads.clear()
+                }
+
+                if self.threads.is_empty() {
+                    let _ = events.send(DebugEvent::Exited);
+                    return;
+                }
+            }
+        }
+
+        fn handle(&mut self, command : Command) -> io::Result<Reply> {
+            let stopped = |debugger: &Debug| match debugger.stopped {
+                Some(tid) if debugger.stepping.is_none() => Ok(tid),
+                _ => Err(io::Error::other("process is running"))
+            };
+
+            match command {
+                Command::SetBreakpoint(address) => {
+                    if !self.breakpoints.contains_key(&address) {
+                        let mut original = [0u8];
+                        if !self.mem.read(address, &mut original) {
+                            return Err(io::Error::other(format!("can't read at {:X}", address)));
+                        }
+                        self.mem.write(address, &[INT3])?;
+                        self.breakpoints.insert(address, original[0]);
+                    }
+                },
+                Command::RemoveBreakpoint(address) => {
+                    if let Some(original) = self.breakpoints.remove(&address) {
+                        match self.stepping.as_mut() {
+                            Some(step) if step.reinsert == Some(address) => step.reinsert = None,
+                            _ => self.mem.write(address, &[original])?
+                        }
+                    }
+                },
+                Command::Registers(tid) => {
+                    stopped(self)?;
+                    return Ok(Reply::Registers(list_registers(&get_regs(tid as i32)?)));
+                },
+                Command::SetRegister(tid, name, value) => {
+                    stopped(self)?;
+                    let mut regs = get_regs(tid as i32)?;
+                    if !write_register(&mut regs, name, value) {
+                        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unknown register {}", name)));
+                    }
+                    set_regs(tid as i32, &regs)?;
+                },
+                Command::Step(tid) => {
+                    stopped(self)?;
+                    self.step(tid as i32, true)?;
+                },
+                Command::Resume => {
+                    let tid = stopped(self)?;
+                    let rip = get_regs(tid)?.rip as usize;
+                    // The thread sits on a breakpoint, execute the original instruction first
+                    if self.breakpoints.contains_key(&rip) {
+                        self.step(tid, false)?;
+                    } else {
+                        self.resume_all();
+                    }
+                }
+            }
+            Ok(Reply::Done)
+        }
+
+        fn step(&mut self, tid : i32, report : bool) -> io::Result<()> {
+            let rip = get_regs(tid)?.rip as usize;
+            let reinsert = match self.breakpoints.get(&rip) {
+                Some(&original) => {
+                    self.mem.write(rip, &[original])?;
+                    Some(rip)
+                },
+                None => None
+            };
+
+            self.stepping = Some(Step { tid: tid, reinsert: reinsert, report: report });
+            let signal = self.pending.remove(&tid).unwrap_or(0);
+            ptrace(libc::PTRACE_SINGLESTEP, tid, 0, signal as usize).map(|_| ())
+        }
+
+        fn resume_thread(&self, tid : i32, signal : i32) {
+            let request = match &self.stepping {
+                Some(step) if step.tid == tid => libc::PTRACE_SINGLESTEP,
+                _ => libc::PTRACE_CONT
+            };
+            let _ = ptrace(request, tid, 0, signal as usize);
+        }
+
+        fn resume_all(&mut self) {
+            self.stopped = None;
+            for &tid in self.threads.iter() {
+                let signal = self.pending.remove(&tid).unwrap_or(0);
+                let _ = ptrace(libc::PTRACE_CONT, tid, 0, signal as usize);
+            }
+        }
+
+        // Rewinds a thread that just executed one of our int3
+        fn hit_breakpoint(&self, tid : i32) -> Option<usize> {
+            let mut regs = get_regs(tid).ok()?;
+            let address = (regs.rip as usize).checked_sub(1)?;
+            if !self.breakpoints.contains_key(&address) {
+                return None;
+            }
+            regs.rip = address as u64;
+            set_regs(tid, &regs).ok()?;
+            Some(address)
+        }
+
+        fn on_stop(&mut self, tid : i32, status : i32, events: &mpsc::Sender<DebugEvent>) {
+            if exited(status) || exiting(tid, status) {
+                self.threads.remove(&tid);
+                if self.stepping.as_ref().is_some_and(|s| s.tid == tid) {
+                    let step = self.stepping.take().unwrap();
+                    self.finish_step(step, tid, events);
+                }
+                return;
+            }
+            if !libc::WIFSTOPPED(status) {
+                return;
+            }
+            if group_stop(status) {
+                let _ = ptrace(libc::PTRACE_LISTEN, tid, 0, 0);
+                return;
+            }
+
+            let signal = libc::WSTOPSIG(status);
+            match event(status) {
+                libc::PTRACE_EVENT_CLONE => {
+                    if let Ok(new) = event_message(tid) {
+                        self.threads.insert(new);
+                    }
+                    self.resume_thread(tid, 0);
+                },
+                // Clones start with this stop, they wait with the others during an all-stop
+                libc::PTRACE_EVENT_STOP => {
+                    self.threads.insert(tid);
+                    if self.stopped.is_none() || self.stepping.as_ref().is_some_and(|s| s.tid == tid) {
+                        self.resume_thread(tid, 0);
+                    }
+                },
+                _ if signal == libc::SIGTRAP && self.stepping.as_ref().is_some_and(|s| s.tid == tid) => {
+                    let step = self.stepping.take().unwrap();
+                    self.finish_step(step, tid, events);
+                },
+                _ if signal == libc::SIGTRAP && self.stopped.is_none() => {
+                    match self.hit_breakpoint(tid) {
+                        Some(address) => {
+                            self.stop_all(tid);
+                            self.stopped = Some(tid);
+                            let _ = events.send(DebugEvent::Breakpoint { tid: tid as u32, address: address });
+                        },
+                        None => self.resume_thread(tid, signal)
+                    }
+                },
+                _ => self.resume_thread(tid, signal)
+            }
+        }
+
+        fn finish_step(&mut self, step : Step, tid : i32, events: &mpsc::Sender<DebugEvent>) {
+            if let Some(address) = step.reinsert {
+                let _ = self.mem.write(address, &[INT3]);
+            }
+
+            if step.report && self.threads.contains(&tid) {
+                let address = get_regs(tid).map(|r| r.rip as usize).unwrap_or_default();
+                self.stopped = Some(tid);
+                let _ = events.send(DebugEvent::Step { tid: tid as u32, address: address });
+            } else {
+                self.resume_all();
+            }
+        }
+
+        // Interrupts every other thread and waits until all of them are stopped
+        fn stop_all(&mut self, except : i32) {
+            let others = self.threads.iter().copied().filter(|&t| t != except).collect();
+            self.stop_threads(others);
+        }
+
+        fn stop_threads(&mut self, mut waiting : Vec<i32>) {
+            for &tid in waiting.iter() {
+                let _ = ptrace(libc::PTRACE_INTERRUPT, tid, 0, 0);
+            }
+
+            while let Some(tid) = waiting.pop() {
+                let status = match wait(tid, 0) {
+                    Ok((_, status)) => status,
+                    Err(_) => {
+                        self.threads.remove(&tid);
+                        continue;
+                    }
+                };
+                if exited(status) || exiting(tid, status) {
+                    self.threads.remove(&tid);
+                    continue;
+                }
+
+                match event(status) {
+                    // The parent still has the interrupt pending, the child starts stopped
+                    libc::PTRACE_EVENT_CLONE => {
+                        if let Ok(new) = event_message(tid) {
+                            self.threads.insert(new);
+                            waiting.push(new);
+                        }
+                        let _ = ptrace(libc::PTRACE_CONT, tid, 0, 0);
+                        waiting.push(tid);
+                    },
+                    libc::PTRACE_EVENT_STOP => {},
+                    // Hits racing with the stop are rewound and will trigger again
+                    _ if libc::WSTOPSIG(status) == libc::SIGTRAP && self.hit_breakpoint(tid).is_some() => {},
+                    _ if self.stepping.as_ref().is_some_and(|s| s.tid == tid) => {},
+                    _ => {
+                        self.pending.insert(tid, libc::WSTOPSIG(status));
+                    }
+                }
+            }
+        }
+
+        // Original bytes go back before the threads are released
+        fn detach(&mut self) {
+            // During an all-stop only the stepping thread may be running
+            match (self.stopped, self.stepping.as_ref()) {
+                (None, _) => self.stop_all(0),
+                (Some(_), Some(step)) => self.stop_threads(vec![step.tid]),
+                _ => {}
+            }
+            if let Some(step) = self.stepping.take() {
+                if let Some(address) = step.reinsert {
+                    self.breakpoints.remove(&address);
+                }
+            }
+
+            for (&address, &original) in self.breakpoints.iter() {
+                let _ = self.mem.write(address, &[original]);
+            }
+            self.breakpoints.clear();
+
+            for &tid in self.threads.iter() {
+                let signal = self.pending.remove(&tid).unwrap_or(0);
+                let _ = ptrace(libc::PTRACE_DETACH, tid, 0, signal as usize);
+            }
+            self.threads.clear();
+        }
+    }
+}
@@ -1,8 +1,22 @@
 mod app;
 mod handler;
 mod ui;
-mod win;
-mod mem;
+mod proclist;
+mod search;
+mod hexview;
+mod disasm;
+mod patch;
+mod signature;
+mod watch;
+mod debug;
+mod inspect;
+mod memmap;
+mod cli;
+mod launch;
+mod batch;
+mod freeze;
+mod script;
+mod rpc;
 
 use std::{sync::Arc, error::Error, io, time::{Instant, Duration}};
 
@@ -16,20 +30,117 @@ use tui::{
     backend::CrosstermBackend, Terminal
 };
 
-use app::App;
+// Engine from the library, the modules above reach it as crate::os, crate::scan, ...
+use mismem::{os, process, mem, scan, expr, export, remote, coredump, snapshot, savestate};
+
+use app::{App, Target};
+use launch::Launched;
 use handler::Handler;
 
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    let options = match cli::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("error: {}\n\n{}", error, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+    // A core file stands in for the live processes, the subcommands included
+    let core_pid = match &options.core {
+        Some(path) => match coredump::open(path) {
+            Ok(pid) => Some(pid),
+            Err(error) => {
+                eprintln!("error: can't open core file {}: {}", path, error);
+                std::process::exit(1);
+            }
+        },
+        None => None
+    };
+
+    // Everything below works on the agent's processes from here on, the subcommands included
+    if let Some(address) = &options.remote {
+        if let Err(error) = remote::connect(address) {
+            eprintln!("error: can't connect to the agent at {}: {}", address, error);
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(command) = options.command {
+        if let Err(error) = batch::run(command) {
+            eprintln!("error: {}", error);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let mut app = App::new();
+    if let Some(pid) = core_pid {
+        app.attach(pid);
+    } else if let Some(launch) = options.launch {
+        match Launched::start(&launch.program, &launch.args, &launch.env, launch.cwd.as_deref()) {
+            Ok(launched) => {
+                app.attach(launched.pid());
+                log::info!(" Started {} (PID {}), held at its entry point. Press c to run it.", launched.command, launched.pid());
+                app.launched = Some(launched);
+            },
+            Err(error) => {
+                eprintln!("error: {}", error);
+                std::process::exit(1);
+            }
+        }
+    } else if options.pid.is_some() || options.name.is_some() {
+        let mut target = Target::new(options.pid, options.name);
+        match target.find() {
+            Some(pid) => {
+                target.waiting = false;
+                app.attach(pid);
+            },
+            None if options.wait => {},
+            None => {
+                eprintln!("error: {} is not running or can't be opened", target.describe());
+                std::process::exit(1);
+            }
+        }
+        app.target = Some(target);
+    }
+
+    let listener = match &options.listen {
+        Some(address) => match rpc::Listener::bind(address).await {
+            Ok(listener) => Some(listener),
+            Err(error) => {
+                eprintln!("error: can't listen on {}: {}", address, error);
+                std::process::exit(1);
+            }
+        },
+        None => None
+    };
+
     let (tx, mut rx) = tokio::sync::mpsc::channel::<Event>(100);
 
-    let app = Arc::new(tokio::sync::Mutex::new(App::new()));
+    let app = Arc::new(tokio::sync::Mutex::new(app));
     let app_ui = Arc::clone(&app);
 
     tui_logger::init_logger(log::LevelFilter::Trace).unwrap();
     tui_logger::set_default_level(log::LevelFilter::Trace);
     log::info!(" 😊 Hello!");
+    if let Some(address) = remote::address() {
+        log::info!(" Connected to the agent at {}, watchpoints and the debugger are unavailable.", address);
+    }
+    if let Some(path) = coredump::path() {
+        log::info!(" Opened core file {}, memory is read-only.", path);
+    }
+
+    let socket_path = listener.as_ref().and_then(|l| l.socket_path());
+    if let Some(listener) = listener {
+        log::info!(" Listening for JSON-RPC on {}.", listener.describe());
+        tokio::spawn(listener.serve(Arc::clone(&app)));
+    }
 
     tokio::spawn(async move {
         let mut handler = Handler::new(app);
@@ -41,6 +152,10 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     start_ui(&app_ui, &tx).await?;
 
+    if let Some(path) = socket_path {
+        _ = std::fs::remove_file(path);
+    }
+
     Ok(())
 }
 
@@ -63,15 +178,23 @@ pub async fn start_ui(app: &Arc<tokio::sync::Mutex<App>>, tx: &tokio::sync::mpsc
             _ = tx.send(event).await;
         }
 
+        let mut app = app.lock().await;
+
         if last_tick.elapsed() >= tick_rate {
-            // app.on_tick();
+            app.on_tick();
             last_tick = Instant::now();
         }
 
-        let mut app = app.lock().await;
         terminal.draw(|rect| ui::draw(rect, &mut app))?;
 
         if app.exiting {
+            // Leaves no debug registers or breakpoints armed in the target
+            app.watch.stop();
+            app.disassembly.debug.detach();
+            // A program started by us doesn't outlive the session
+            if let Some(launched) = app.launched.as_mut() {
+                launched.kill();
+            }
             break
         }
     }
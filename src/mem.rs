@@ -1,7 +1,13 @@
+//! Scan results and the types of the values they hold
+
 use std::{fmt, convert::TryInto};
 
+use crate::process::Module;
+
 // TODO compact
 
+/// Type of a scanned value, `B<n>` is n bytes unsigned and `B<n>S` signed, F and D are f32 and f64
+#[derive(Clone, Copy)]
 pub enum Datatype {
     B16,
     B16S,
@@ -17,13 +23,126 @@ pub enum Datatype {
     F,
 }
 
+impl Datatype {
+    pub const ALL : [Datatype;12] = [
+        Datatype::B1, Datatype::B1S, Datatype::B2, Datatype::B2S, Datatype::B4, Datatype::B4S,
+        Datatype::B8, Datatype::B8S, Datatype::B16, Datatype::B16S, Datatype::F, Datatype::D,
+    ];
+
+    /// Parses the short names, like u32 or f64
+    pub fn from_name(name: &str) -> Option<Datatype> {
+        match name {
+            "u8" => Some(Datatype::B1),
+            "i8" => Some(Datatype::B1S),
+            "u16" => Some(Datatype::B2),
+            "i16" => Some(Datatype::B2S),
+            "u32" => Some(Datatype::B4),
+            "i32" => Some(Datatype::B4S),
+            "u64" => Some(Datatype::B8),
+            "i64" => Some(Datatype::B8S),
+            "u128" => Some(Datatype::B16),
+            "i128" => Some(Datatype::B16S),
+            "f32" => Some(Datatype::F),
+            "f64" => Some(Datatype::D),
+            _ => None
+        }
+    }
+
+    /// Short name, like u32 or f64
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Datatype::B1 => "u8",
+            Datatype::B1S => "i8",
+            Datatype::B2 => "u16",
+            Datatype::B2S => "i16",
+            Datatype::B4 => "u32",
+            Datatype::B4S => "i32",
+            Datatype::B8 => "u64",
+            Datatype::B8S => "i64",
+            Datatype::B16 => "u128",
+            Datatype::B16S => "i128",
+            Datatype::F => "f32",
+            Datatype::D => "f64",
+        }
+    }
+
+    /// Size in bytes
+    pub fn size(&self) -> usize {
+        match *self {
+            Datatype::B1 | Datatype::B1S => 1,
+            Datatype::B2 | Datatype::B2S => 2,
+            Datatype::B4 | Datatype::B4S | Datatype::F => 4,
+            Datatype::B8 | Datatype::B8S | Datatype::D => 8,
+            Datatype::B16 | Datatype::B16S => 16,
+        }
+    }
+
+    /// Value stored in the first size() bytes, None if there are fewer
+    pub fn format(&self, bytes: &[u8], big_endian: bool) -> Option<String> {
+        let bytes = bytes.get(..self.size())?;
+
+        macro_rules! format_value{
+            ($t:ty)=>{{
+                let value = if big_endian {
+                    <$t>::from_be_bytes(bytes.try_into().unwrap())
+                } else {
+                    <$t>::from_le_bytes(bytes.try_into().unwrap())
+                };
+                Some(value.to_string())
+            }}
+        }
+
+        match *self {
+            Datatype::B1 => format_value!(u8),
+            Datatype::B1S => format_value!(i8),
+            Datatype::B2 => format_value!(u16),
+            Datatype::B2S => format_value!(i16),
+            Datatype::B4 => format_value!(u32),
+            Datatype::B4S => format_value!(i32),
+            Datatype::B8 => format_value!(u64),
+            Datatype::B8S => format_value!(i64),
+            Datatype::B16 => format_value!(u128),
+            Datatype::B16S => format_value!(i128),
+            Datatype::F => format_value!(f32),
+            Datatype::D => format_value!(f64),
+        }
+    }
+
+    /// Bytes of input in native byte order, ready to scan for or write
+    pub fn parse(&self, input: &str) -> Result<Vec<u8>, String> {
+        macro_rules! parse{
+            ($t:ty)=>{ 
+                input.parse::<$t>().map(|r| r.to_ne_bytes().to_vec()).map_err(|e| e.to_string())
+            }
+        }
+
+        match *self {
+            Datatype::B1 => parse!(u8),
+            Datatype::B1S => parse!(i8),
+            Datatype::B2 => parse!(u16),
+            Datatype::B2S => parse!(i16),
+            Datatype::B4 => parse!(u32),
+            Datatype::B4S => parse!(i32),
+            Datatype::B8 => parse!(u64),
+            Datatype::B8S => parse!(i64),
+            Datatype::B16 => parse!(u128),
+            Datatype::B16S => parse!(i128),
+            Datatype::F => parse!(f32),
+            Datatype::D => parse!(f64),
+        }
+    }
+}
+
+/// One result, old_value is the value before the last filter or update
+#[derive(Clone)]
 pub struct Location<T: fmt::Display> {
     pub address: usize,
     pub value: T,
     pub old_value: T,
 }
 
-#[derive(Default)]
+/// Scan results, one list per type, each sorted by address
+#[derive(Default, Clone)]
 pub struct Memory {
 
     pub mem_i128: Vec<Location<i128>>, 
@@ -89,11 +208,18 @@ impl Memory {
         self.mem_f32.clear();
     }
 
-    pub fn len(&mut self) -> usize {
+    /// Number of results of every type
+    pub fn len(&self) -> usize {
         self.mem_i128.len() + self.mem_u128.len() + self.mem_i64.len() + self.mem_u64.len() + self.mem_i32.len() + self.mem_u32.len() +
         self.mem_i16.len() + self.mem_u16.len() + self.mem_i8.len() + self.mem_u8.len() + self.mem_f64.len() + self.mem_f32.len()
     }
 
+    /// True when there are no results of any type
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends a result, the list of its type must stay sorted
     pub fn push(&mut self, address: usize, target_type: &Datatype, target_bytes: &[u8]) {
         macro_rules! mem_push{
             ($t:ty,$mem:ident)=>{{
@@ -118,7 +244,157 @@ impl Memory {
         }
     }
 
-    pub fn iter(&self) -> MemoryIterator {
+    /// Appends a result with an old value, like push
+    pub fn push_location(&mut self, address: usize, target_type: &Datatype, value_bytes: &[u8], old_value_bytes: &[u8]) {
+        macro_rules! mem_push{
+            ($t:ty,$mem:ident)=>{{
+                let value = <$t>::from_ne_bytes(value_bytes.try_into().unwrap());
+                let old_value = <$t>::from_ne_bytes(old_value_bytes.try_into().unwrap());
+                self.$mem.push(Location::<$t>{address: address, value: value, old_value: old_value});
+            }}
+        }
+
+        match *target_type {
+            Datatype::B1 => mem_push!(u8,mem_u8),
+            Datatype::B1S => mem_push!(i8,mem_i8),
+            Datatype::B2 => mem_push!(u16,mem_u16),
+            Datatype::B2S => mem_push!(i16,mem_i16),
+            Datatype::B4 => mem_push!(u32,mem_u32),
+            Datatype::B4S => mem_push!(i32,mem_i32),
+            Datatype::B8 => mem_push!(u64,mem_u64),
+            Datatype::B8S => mem_push!(i64,mem_i64),
+            Datatype::B16 => mem_push!(u128,mem_u128),
+            Datatype::B16S => mem_push!(i128,mem_i128),
+            Datatype::F => mem_push!(f32,mem_f32),
+            Datatype::D => mem_push!(f64,mem_f64)
+        }
+    }
+
+    /// Sorts every list by address after out of order pushes
+    pub fn sort(&mut self) {
+        self.mem_i128.sort_by_key(|l| l.address);
+        self.mem_u128.sort_by_key(|l| l.address);
+    
+        self.mem_i64.sort_by_key(|l| l.address);
+        self.mem_u64.sort_by_key(|l| l.address);
+    
+        self.mem_i32.sort_by_key(|l| l.address);
+        self.mem_u32.sort_by_key(|l| l.address);
+    
+        self.mem_i16.sort_by_key(|l| l.address);
+        self.mem_u16.sort_by_key(|l| l.address);
+    
+        self.mem_i8.sort_by_key(|l| l.address);
+        self.mem_u8.sort_by_key(|l| l.address);
+    
+        self.mem_f64.sort_by_key(|l| l.address);
+        self.mem_f32.sort_by_key(|l| l.address);
+    }
+
+    /// Adds or replaces a result keeping its list sorted
+    pub fn insert(&mut self, address: usize, target_type: &Datatype, target_bytes: &[u8]) {
+        macro_rules! mem_insert{
+            ($t:ty,$mem:ident)=>{{
+                let value = <$t>::from_ne_bytes(target_bytes.try_into().unwrap());
+                match self.$mem.binary_search_by_key(&address, |l| l.address) {
+                    Ok(i) => self.$mem[i] = Location::<$t>{address: address, value: value, old_value: value},
+                    Err(i) => self.$mem.insert(i, Location::<$t>{address: address, value: value, old_value: value}),
+                }
+            }}
+        }
+
+        match *target_type {
+            Datatype::B1 => mem_insert!(u8,mem_u8),
+            Datatype::B1S => mem_insert!(i8,mem_i8),
+            Datatype::B2 => mem_insert!(u16,mem_u16),
+            Datatype::B2S => mem_insert!(i16,mem_i16),
+            Datatype::B4 => mem_insert!(u32,mem_u32),
+            Datatype::B4S => mem_insert!(i32,mem_i32),
+            Datatype::B8 => mem_insert!(u64,mem_u64),
+            Datatype::B8S => mem_insert!(i64,mem_i64),
+            Datatype::B16 => mem_insert!(u128,mem_u128),
+            Datatype::B16S => mem_insert!(i128,mem_i128),
+            Datatype::F => mem_insert!(f32,mem_f32),
+            Datatype::D => mem_insert!(f64,mem_f64)
+        }
+    }
+
+    /// Types with at least one entry
+    pub fn datatypes(&self) -> Vec<Datatype> {
+        [
+            (self.mem_u8.is_empty(), Datatype::B1), (self.mem_i8.is_empty(), Datatype::B1S),
+            (self.mem_u16.is_empty(), Datatype::B2), (self.mem_i16.is_empty(), Datatype::B2S),
+            (self.mem_u32.is_empty(), Datatype::B4), (self.mem_i32.is_empty(), Datatype::B4S),
+            (self.mem_u64.is_empty(), Datatype::B8), (self.mem_i64.is_empty(), Datatype::B8S),
+            (self.mem_u128.is_empty(), Datatype::B16), (self.mem_i128.is_empty(), Datatype::B16S),
+            (self.mem_f32.is_empty(), Datatype::F), (self.mem_f64.is_empty(), Datatype::D),
+        ].into_iter().filter(|(empty, _)| !empty).map(|(_, datatype)| datatype).collect()
+    }
+
+    /// Drops the entries of every other type
+    pub fn retain_type(&mut self, datatype: &Datatype) {
+        let mut kept = Memory::new();
+
+        macro_rules! mem_keep{
+            ($mem:ident)=>{ kept.$mem = std::mem::take(&mut self.$mem) }
+        }
+
+        match *datatype {
+            Datatype::B1 => mem_keep!(mem_u8),
+            Datatype::B1S => mem_keep!(mem_i8),
+            Datatype::B2 => mem_keep!(mem_u16),
+            Datatype::B2S => mem_keep!(mem_i16),
+            Datatype::B4 => mem_keep!(mem_u32),
+            Datatype::B4S => mem_keep!(mem_i32),
+            Datatype::B8 => mem_keep!(mem_u64),
+            Datatype::B8S => mem_keep!(mem_i64),
+            Datatype::B16 => mem_keep!(mem_u128),
+            Datatype::B16S => mem_keep!(mem_i128),
+            Datatype::F => mem_keep!(mem_f32),
+            Datatype::D => mem_keep!(mem_f64)
+        }
+        *self = kept;
+    }
+
+    /// Moves entries inside a module to the same offset in its new mapping, returns how many moved
+    pub fn rebase(&mut self, old: &[Module], new: &[Module]) -> usize {
+        let rebase = |address: usize| -> Option<usize> {
+            let module = old.iter().find(|m| m.base <= address && address < m.base + m.size)?;
+            let moved = new.iter().find(|m| m.name == module.name)?;
+            Some(moved.base + (address - module.base))
+        };
+        let mut moved = 0;
+
+        macro_rules! mem_rebase{
+            ($mem:ident)=>{
+                for l in self.$mem.iter_mut() {
+                    if let Some(address) = rebase(l.address) {
+                        l.address = address;
+                        moved += 1;
+                    }
+                }
+            }
+        }
+
+        mem_rebase!(mem_i128);
+        mem_rebase!(mem_u128);
+        mem_rebase!(mem_i64);
+        mem_rebase!(mem_u64);
+        mem_rebase!(mem_i32);
+        mem_rebase!(mem_u32);
+        mem_rebase!(mem_i16);
+        mem_rebase!(mem_u16);
+        mem_rebase!(mem_i8);
+        mem_rebase!(mem_u8);
+        mem_rebase!(mem_f64);
+        mem_rebase!(mem_f32);
+
+        self.sort();
+        moved
+    }
+
+    /// Results of every type by address, as `[address:type, value, old value]` strings
+    pub fn iter(&self) -> MemoryIterator<'_> {
         MemoryIterator { memory: self, curs: [0;12] }
     }
 /* 
@@ -130,6 +406,7 @@ impl Memory {
     */
 }
 
+/// Iterator returned by Memory::iter
 pub struct MemoryIterator<'a> {
     memory : &'a Memory,
     curs : [usize;12],
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use tui::widgets::TableState;
+use tui_input::Input;
+
+use crate::os::enum_mappings;
+use crate::process::Mapping;
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MapColumn {
+    Address,
+    Size,
+    Permissions,
+    File,
+}
+
+impl MapColumn {
+    pub const ALL : [MapColumn;4] = [MapColumn::Address, MapColumn::Size, MapColumn::Permissions, MapColumn::File];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MapColumn::Address => "Address Range",
+            MapColumn::Size => "Size",
+            MapColumn::Permissions => "Perm",
+            MapColumn::File => "Backing File",
+        }
+    }
+}
+
+// Regions of the target and which of them the next first scan reads
+pub struct MemoryMap {
+    pub mappings: Vec<Mapping>,
+    pub table_state: TableState,
+    pub filter: Input,
+    pub filtering: bool,
+    pub sort: MapColumn,
+    pub descending: bool,
+
+    // Region base -> included, regions without an entry use the default
+    overrides: HashMap<usize, bool>,
+    // Indices into mappings, in display order
+    visible: Vec<usize>,
+}
+
+impl MemoryMap {
+    pub fn new() -> MemoryMap {
+        MemoryMap {
+            mappings: vec![],
+            table_state: TableState::default(),
+            filter: Input::default(),
+            filtering: false,
+            sort: MapColumn::Address,
+            descending: false,
+
+            overrides: HashMap::new(),
+            visible: vec![],
+        }
+    }
+
+    pub fn refresh(&mut self, pid: u32) {
+        self.mappings = enum_mappings(pid);
+        self.update();
+    }
+
+    // Forgets filter and scan selection of the previous process
+    pub fn reset(&mut self) {
+        self.overrides.clear();
+        self.filter.reset();
+        self.filtering = false;
+        self.table_state.select(None);
+        self.mappings.clear();
+        self.visible.clear();
+    }
+
+    pub fn update(&mut self) {
+        let filter = self.filter.value().to_lowercase();
+        self.visible = (0..self.mappings.len())
+            .filter(|&i| {
+                let m = &self.mappings[i];
+                filter.is_empty()
+                    || m.path.to_lowercase().contains(&filter)
+                    || m.region.permissions().contains(&filter)
+                    || format!("{:x}", m.region.base).contains(&filter)
+            })
+            .collect();
+
+        let mappings = &self.mappings;
+        let sort = self.sort;
+        self.visible.sort_by(|&a, &b| {
+            let (a, b) = (&mappings[a], &mappings[b]);
+            match sort {
+                MapColumn::Address => a.region.base.cmp(&b.region.base),
+                MapColumn::Size => a.region.size.cmp(&b.region.size),
+                MapColumn::Permissions => a.region.permissions().cmp(&b.region.permissions()),
+                MapColumn::File => backing(a).cmp(backing(b)),
+            }.then(a.region.base.cmp(&b.region.base))
+        });
+        if self.descending {
+            self.visible.reverse();
+        }
+
+        if self.table_state.selected().is_none_or(|i| i >= self.visible.len()) {
+            self.table_state.select(if self.visible.is_empty() { None } else { Some(0) });
+        }
+    }
+
+    pub fn sort_by(&mut self, column: MapColumn) {
+        if self.sort == column {
+            self.descending = !self.descending;
+        } else {
+            self.sort = column;
+            self.descending = column == MapColumn::Size;
+        }
+        self.update();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Mapping> {
+        self.visible.iter().map(|&i| &self.mappings[i])
+    }
+
+    pub fn len(&self) -> usize {
+        self.visible.len()
+    }
+
+    pub fn selected(&self) -> Option<&Mapping> {
+        self.mappings.get(*self.visible.get(self.table_state.selected()?)?)
+    }
+
+    // Span of every mapping of the selected file
+    pub fn selected_module(&self) -> Option<(usize, usize, &str)> {
+        let path = &self.selected()?.path;
+        if path.is_empty() || path.starts_with('[') {
+            return None;
+        }
+        let mappings = self.mappings.iter().filter(|m| m.path == *path);
+        let start = mappings.clone().map(|m| m.region.base).min()?;
+        let end = mappings.map(|m| m.region.base + m.region.size).max()?;
+        Some((start, end, path))
+    }
+
+    pub fn next(&mut self) {
+        if self.visible.is_empty() { return; }
+        self.table_state.select(Some(
+            self.table_state.selected().map_or(0, |i| (i + 1) % self.visible.len())
+        ));
+    }
+
+    pub fn previous(&mut self) {
+        if self.visible.is_empty() { return; }
+        self.table_state.select(Some(
+            self.table_state.selected().map_or(0, |i| (i + self.visible.len() - 1) % self.visible.len())
+        ));
+    }
+
+    // Scans read writable memory unless told otherwise
+    pub fn is_included(&self, mapping: &Mapping) -> bool {
+        self.overrides.get(&mapping.region.base).copied()
+            .unwrap_or(mapping.region.readable && mapping.region.writable)
+    }
+
+    pub fn is_customized(&self) -> bool {
+        !self.overrides.is_empty()
+    }
+
+    pub fn toggle_selected(&mut self) {
+        if let Some(mapping) = self.selected() {
+            let (base, included) = (mapping.region.base, self.is_included(mapping));
+            self.overrides.insert(base, !included);
+        }
+    }
+
+    // Applies to the regions passing the filter
+    pub fn include_visible(&mut self, included: bool) {
+        for &i in &self.visible {
+            self.overrides.insert(self.mappings[i].region.base, included);
+        }
+    }
+
+    // Only regions inside start..end are scanned
+    pub fn include_only(&mut self, start: usize, end: usize) {
+        self.overrides = self.mappings.iter()
+            .map(|m| (m.region.base, m.region.base < end && start < m.region.base + m.region.size))
+            .collect();
+    }
+
+    pub fn include_default(&mut self) {
+        self.overrides.clear();
+    }
+
+    // None keeps the default of scanning every writable region
+    pub fn scan_ranges(&self) -> Option<Vec<(usize, usize)>> {
+        if !self.is_customized() {
+            return None;
+        }
+        Some(self.mappings.iter()
+            .filter(|m| self.is_included(m))
+            .map(|m| (m.region.base, m.region.base + m.region.size))
+            .collect())
+    }
+}
+
+
+pub fn backing(mapping: &Mapping) -> &str {
+    if mapping.path.is_empty() { "anon" } else { &mapping.path }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::Region;
+
+    fn mapping(base: usize, size: usize, permissions: &str, path: &str) -> Mapping {
+        Mapping {
+            region: Region {
+                base: base, size: size, readable: permissions.contains('r'),
+                writable: permissions.contains('w'), executable: permissions.contains('x'), shared: false,
+            },
+            offset: 0,
+            path: path.to_string(),
+        }
+    }
+
+    fn map() -> MemoryMap {
+        let mut map = MemoryMap::new();
+        map.mappings = vec![
+            mapping(0x400000, 0x1000, "r-x", "/usr/bin/game"),
+            mapping(0x401000, 0x2000, "rw-", "/usr/bin/game"),
+            mapping(0x600000, 0x8000, "rw-", ""),
+            mapping(0x7f0000, 0x1000, "r--", "[vdso]"),
+        ];
+        map.update();
+        map
+    }
+
+    fn bases(map: &MemoryMap) -> Vec<usize> {
+        map.iter().map(|m| m.region.base).collect()
+    }
+
+    #[test]
+    fn default_scans_writable_regions() {
+        let map = map();
+        assert!(map.scan_ranges().is_none());
+        let included = map.mappings.iter().filter(|m| map.is_included(m)).map(|m| m.region.base).collect::<Vec<_>>();
+        assert_eq!(included, [0x401000, 0x600000]);
+    }
+
+    #[test]
+    fn toggle_selected_region() {
+        let mut map = map();
+        map.toggle_selected();
+        map.next();
+        map.toggle_selected();
+        assert_eq!(map.scan_ranges(), Some(vec![(0x400000, 0x401000), (0x600000, 0x608000)]));
+
+        map.include_default();
+        assert!(map.scan_ranges().is_none());
+    }
+
+    #[test]
+    fn include_only_overlapping() {
+        let mut map = map();
+        map.include_only(0x400800, 0x401001);
+        assert_eq!(map.scan_ranges(), Some(vec![(0x400000, 0x401000), (0x401000, 0x403000)]));
+    }
+
+    #[test]
+    fn include_visible_after_filter() {
+        let mut map = map();
+        map.filter = Input::new(String::from("game"));
+        map.update();
+        assert_eq!(bases(&map), [0x400000, 0x401000]);
+
+        map.include_visible(false);
+        assert_eq!(map.scan_ranges(), Some(vec![(0x600000, 0x608000)]));
+    }
+
+    #[test]
+    fn sort_and_selected_module() {
+        let mut map = map();
+        map.sort_by(MapColumn::Size);
+        assert_eq!(bases(&map), [0x600000, 0x401000, 0x7f0000, 0x400000]);
+        map.sort_by(MapColumn::File);
+        assert_eq!(bases(&map), [0x400000, 0x401000, 0x7f0000, 0x600000]);
+
+        map.table_state.select(Some(1));
+        assert_eq!(map.selected_module(), Some((0x400000, 0x403000, "/usr/bin/game")));
+        map.table_state.select(Some(2));
+        assert_eq!(map.selected_module(), None);
+    }
+}
@@ -0,0 +1,115 @@
+//! Other processes: listing them, their modules and memory regions, reading and writing their memory
+//!
+//! Works on this machine (Linux and Windows), on the machine of a `mismem-agent` once
+//! [`remote::connect`] was called, or on the process dumped in the core file given to
+//! [`coredump::open`]. Launching and stopping programs, watchpoints and the debugger are local only.
+
+use std::io;
+
+use crate::coredump;
+use crate::native;
+use crate::process::{Mapping, Module, ProcessDetails, ProcessInfo, Region};
+use crate::remote;
+
+pub use crate::native::{launch, resume_process, Debugger, Suspended, Watcher};
+
+
+// Same function on this machine, on the agent or in the core file
+macro_rules! forward{
+    ($(#[$doc:meta])* $name:ident($($arg:ident : $t:ty),*) -> $r:ty)=>{
+        $(#[$doc])*
+        pub fn $name($($arg : $t),*) -> $r {
+            if remote::is_remote() {
+                remote::$name($($arg),*)
+            } else if coredump::is_open() {
+                coredump::$name($($arg),*)
+            } else {
+                native::$name($($arg),*)
+            }
+        }
+    }
+}
+
+forward!(
+    /// Every process the current user can see, unsorted
+    enum_processes() -> Vec<ProcessInfo>);
+forward!(
+    /// Everything the inspector shows about the process
+    process_details(pid : u32) -> io::Result<ProcessDetails>);
+forward!(
+    /// True if the process is running and its memory can be opened
+    check_process(pid : u32) -> bool);
+forward!(
+    /// Memory map of the process, with the file backing each region
+    enum_mappings(pid : u32) -> Vec<Mapping>);
+forward!(
+    /// Executable and libraries loaded by the process
+    enum_modules(pid : u32) -> Vec<Module>);
+forward!(
+    /// Committed memory of the process, sorted by address
+    enum_regions(pid : u32) -> Vec<Region>);
+forward!(
+    /// 4 or 8, depending on the bitness of the process
+    pointer_size(pid : u32) -> usize);
+forward!(
+    /// Fills buffer from address, false if any of it can't be read
+    read_process(pid : u32, address : usize, buffer : &mut [u8]) -> bool);
+forward!(
+    /// Writes all of target_bytes at address
+    write_process(pid : u32, address : usize, target_bytes : &[u8]) -> io::Result<()>);
+
+
+// ptrace and thread suspension can't reach the processes of a mismem-agent or of a core file
+pub(crate) fn local_only(feature : &str) -> io::Result<()> {
+    if let Some(address) = remote::address() {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} can't be used through the agent at {}", feature, address)));
+    }
+    if let Some(path) = coredump::path() {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} can't be used on the core file {}", feature, path)));
+    }
+    Ok(())
+}
+
+
+/// Open process for many reads and writes in a row
+pub enum Process {
+    Local(native::Process),
+    /// Every read and write is a request to the agent
+    Remote(u32),
+    /// Reads come from the core file, writes fail
+    Core(u32),
+}
+
+impl Process {
+    pub fn open(pid : u32) -> io::Result<Process> {
+        if coredump::is_open() {
+            return match coredump::check_process(pid) {
+                true => Ok(Process::Core(pid)),
+                false => Err(io::Error::new(io::ErrorKind::NotFound, format!("process {} isn't in the core file", pid)))
+            };
+        }
+        if !remote::is_remote() {
+            return native::Process::open(pid).map(Process::Local);
+        }
+        match remote::check_process(pid) {
+            true => Ok(Process::Remote(pid)),
+            false => Err(io::Error::new(io::ErrorKind::NotFound, format!("process {} is not running on the agent", pid)))
+        }
+    }
+
+    pub fn read(&self, address : usize, buffer: &mut [u8]) -> bool {
+        match self {
+            Process::Local(process) => process.read(address, buffer),
+            Process::Remote(pid) => remote::read_process(*pid, address, buffer),
+            Process::Core(pid) => coredump::read_process(*pid, address, buffer),
+        }
+    }
+
+    pub fn write(&self, address : usize, target_bytes: &[u8]) -> io::Result<()> {
+        match self {
+            Process::Local(process) => process.write(address, target_bytes),
+            Process::Remote(pid) => remote::write_process(*pid, address, target_bytes),
+            Process::Core(pid) => coredump::write_process(*pid, address, target_bytes),
+        }
+    }
+}
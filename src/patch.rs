@@ -0,0 +1,182 @@
+use std::io;
+
+use crate::os::{read_process, write_process};
+
+
+pub struct Patch {
+    pub address: usize,
+    pub original: Vec<u8>,
+    pub patched: Vec<u8>,
+}
+
+impl Patch {
+    pub fn end(&self) -> usize {
+        self.address + self.patched.len()
+    }
+
+    pub fn overlaps(&self, address: usize, len: usize) -> bool {
+        self.address < address + len && address < self.end()
+    }
+}
+
+// Patches applied to one process, kept so they can be restored later
+pub struct PatchList {
+    pub pid: u32,
+    pub patches: Vec<Patch>,
+}
+
+impl PatchList {
+    const NOP: u8 = 0x90;
+
+    pub fn new() -> PatchList {
+        PatchList { pid: 0, patches: vec![] }
+    }
+
+    // Patches of a previous process are meaningless once another one is opened
+    pub fn select_process(&mut self, pid: u32) {
+        if self.pid != pid {
+            self.pid = pid;
+            self.patches.clear();
+        }
+    }
+
+    pub fn find(&self, address: usize, len: usize) -> Option<&Patch> {
+        self.patches.iter().find(|p| p.overlaps(address, len))
+    }
+
+    // Overlapping patches are merged so the first original bytes are never lost
+    pub fn apply(&mut self, address: usize, bytes: &[u8]) -> io::Result<()> {
+        let mut start = address;
+        let mut end = address + bytes.len();
+        for p in self.patches.iter().filter(|p| p.overlaps(address, bytes.len())) {
+            start = start.min(p.address);
+            end = end.max(p.end());
+        }
+
+        let mut original = vec![0u8; end - start];
+        if !read_process(self.pid, start, &mut original) {
+            return Err(io::Error::other(format!("can't read at {:X}", start)));
+        }
+
+        let mut patched = original.clone();
+        patched[address - start..address - start + bytes.len()].copy_from_slice(bytes);
+        write_process(self.pid, address, bytes)?;
+
+        for p in self.patches.iter().filter(|p| p.overlaps(address, bytes.len())) {
+            original[p.address - start..p.end() - start].copy_from_slice(&p.original);
+        }
+        self.patches.retain(|p| !p.overlaps(address, bytes.len()));
+
+        let i = self.patches.partition_point(|p| p.address < start);
+        self.patches.insert(i, Patch { address: start, original: original, patched: patched });
+        Ok(())
+    }
+
+    pub fn nop(&mut self, address: usize, len: usize) -> io::Result<()> {
+        self.apply(address, &vec![PatchList::NOP; len])
+    }
+
+    pub fn restore(&mut self, index: usize) -> io::Result<()> {
+        if let Some(p) = self.patches.get(index) {
+            write_process(self.pid, p.address, &p.original)?;
+            self.patches.remove(index);
+        }
+        Ok(())
+    }
+}
+
+
+// Accepts "90 90", "9090" or "0x90 0x90"
+pub fn parse_bytes(text: &str) -> Result<Vec<u8>, String> {
+    let digits: String = text.split_whitespace()
+        .map(|t| t.trim_start_matches("0x").trim_start_matches("0X"))
+        .collect();
+
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("invalid bytes '{}'", text));
+    }
+    if digits.is_empty() {
+        return Err(String::from("no bytes given"));
+    }
+    if digits.len() % 2 != 0 {
+        return Err(String::from("odd number of hex digits"));
+    }
+
+    (0..digits.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_byte_formats() {
+        assert_eq!(parse_bytes("90 90").unwrap(), [0x90, 0x90]);
+        assert_eq!(parse_bytes("EB05").unwrap(), [0xEB, 0x05]);
+        assert_eq!(parse_bytes("0x48 0X31 c0").unwrap(), [0x48, 0x31, 0xC0]);
+        assert!(parse_bytes("").is_err());
+        assert!(parse_bytes("909").is_err());
+        assert!(parse_bytes("zz").is_err());
+    }
+
+    fn read(buffer: &[u8]) -> Vec<u8> {
+        buffer.iter().map(|b| unsafe { std::ptr::read_volatile(b) }).collect()
+    }
+
+    // Patches are applied to a buffer of the test process itself
+    fn patch_list() -> PatchList {
+        let mut patches = PatchList::new();
+        patches.select_process(std::process::id());
+        patches
+    }
+
+    #[test]
+    fn overlapping_patches_merge() {
+        let buffer: Vec<u8> = (0..8).collect();
+        let start = buffer.as_ptr() as usize;
+        let mut patches = patch_list();
+
+        patches.nop(start + 2, 2).unwrap();
+        patches.apply(start + 3, &[0xCC, 0xCC]).unwrap();
+        assert_eq!(read(&buffer), [0, 1, 0x90, 0xCC, 0xCC, 5, 6, 7]);
+
+        assert_eq!(patches.patches.len(), 1);
+        let patch = &patches.patches[0];
+        assert_eq!((patch.address, patch.end()), (start + 2, start + 5));
+        assert_eq!(patch.original, [2, 3, 4]);
+        assert_eq!(patch.patched, [0x90, 0xCC, 0xCC]);
+    }
+
+    #[test]
+    fn restore_original_bytes() {
+        let buffer: Vec<u8> = (0..8).collect();
+        let start = buffer.as_ptr() as usize;
+        let mut patches = patch_list();
+
+        patches.nop(start + 5, 2).unwrap();
+        patches.nop(start, 1).unwrap();
+        assert_eq!(patches.patches.iter().map(|p| p.address - start).collect::<Vec<_>>(), [0, 5]);
+        assert!(patches.find(start + 6, 1).is_some());
+        assert!(patches.find(start + 1, 4).is_none());
+
+        patches.restore(1).unwrap();
+        assert_eq!(read(&buffer), [0x90, 1, 2, 3, 4, 5, 6, 7]);
+        patches.restore(0).unwrap();
+        assert_eq!(read(&buffer), [0, 1, 2, 3, 4, 5, 6, 7]);
+        assert!(patches.patches.is_empty());
+    }
+
+    #[test]
+    fn other_process_drops_patches() {
+        let buffer = [0u8; 4];
+        let mut patches = patch_list();
+        patches.nop(buffer.as_ptr() as usize, 1).unwrap();
+        patches.select_process(std::process::id());
+        assert_eq!(patches.patches.len(), 1);
+        patches.select_process(0);
+        assert!(patches.patches.is_empty());
+    }
+}
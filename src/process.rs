@@ -0,0 +1,87 @@
+//! Types shared by the platform backends in `os`
+
+use serde::{Deserialize, Serialize};
+
+/// Process as listed by enum_processes, memory is the resident size in kB
+#[derive(Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub name: String,
+    pub memory: f64,
+    pub pid: u32,
+    /// 0 when the parent is unknown
+    pub ppid: u32,
+    pub user: String,
+    pub cmdline: String,
+    /// Seconds since the Unix epoch, 0 when unknown
+    pub start_time: u64,
+}
+
+/// Everything the inspector shows about one process
+#[derive(Serialize, Deserialize)]
+pub struct ProcessDetails {
+    pub exe: String,
+    pub cmdline: String,
+    pub environment: Vec<String>,
+    pub user: String,
+    pub group: String,
+    pub threads: Vec<u32>,
+    pub start_time: u64,
+    /// kB
+    pub resident: u64,
+    pub virtual_size: u64,
+}
+
+/// Executable or library mapped in a process
+#[derive(Serialize, Deserialize)]
+pub struct Module {
+    pub name: String,
+    pub base: usize,
+    pub size: usize,
+}
+
+/// Range of committed memory with its protection
+#[derive(Serialize, Deserialize)]
+pub struct Region {
+    pub base: usize,
+    pub size: usize,
+    pub readable: bool,
+    pub writable: bool,
+    pub executable: bool,
+    /// Shared with other processes or written through to a file, copy-on-write memory is private
+    #[serde(default)]
+    pub shared: bool,
+}
+
+impl Region {
+    pub fn permissions(&self) -> String {
+        format!("{}{}{}",
+            if self.readable { 'r' } else { '-' },
+            if self.writable { 'w' } else { '-' },
+            if self.executable { 'x' } else { '-' })
+    }
+
+    pub fn contains(&self, address: usize) -> bool {
+        self.base <= address && address < self.base + self.size
+    }
+}
+
+/// A region with what backs it, file offset is 0 for anonymous memory
+#[derive(Serialize, Deserialize)]
+pub struct Mapping {
+    pub region: Region,
+    pub offset: u64,
+    pub path: String,
+}
+
+/// Register of a stopped thread, as shown and edited by the debugger
+pub struct Register {
+    pub name: &'static str,
+    pub value: u64,
+}
+
+/// What a Debugger reports through poll
+pub enum DebugEvent {
+    Breakpoint { tid: u32, address: usize },
+    Step { tid: u32, address: usize },
+    Exited,
+}
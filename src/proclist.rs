@@ -0,0 +1,408 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tui_input::Input;
+
+use crate::os::enum_processes;
+use crate::process::ProcessInfo;
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortColumn {
+    Pid,
+    Name,
+    User,
+    Memory,
+    Started,
+}
+
+impl SortColumn {
+    pub const ALL : [SortColumn;5] = [SortColumn::Pid, SortColumn::Name, SortColumn::User, SortColumn::Memory, SortColumn::Started];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortColumn::Pid => "PID",
+            SortColumn::Name => "Process Name",
+            SortColumn::User => "User",
+            SortColumn::Memory => "Memory [kB]",
+            SortColumn::Started => "Started",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Branch {
+    Leaf,
+    Expanded,
+    Collapsed,
+}
+
+struct Node {
+    index: usize,
+    depth: usize,
+    branch: Branch,
+}
+
+// Running processes narrowed by the filter and sorted on one column, flat or as a tree
+pub struct ProcessList {
+    pub filter: Input,
+    pub filtering: bool,
+    pub sort: SortColumn,
+    pub descending: bool,
+    pub tree: bool,
+
+    processes: Vec<ProcessInfo>,
+    collapsed: HashSet<u32>,
+    // Display order
+    visible: Vec<Node>,
+}
+
+impl ProcessList {
+    pub fn new() -> ProcessList {
+        ProcessList {
+            filter: Input::default(),
+            filtering: false,
+            sort: SortColumn::Memory,
+            descending: true,
+            tree: false,
+
+            processes: vec![],
+            collapsed: HashSet::new(),
+            visible: vec![],
+        }
+    }
+
+    pub fn refresh(&mut self) {
+        self.processes = enum_processes();
+        self.update();
+    }
+
+    // Re-applies filter and sort order
+    pub fn update(&mut self) {
+        let filter = self.filter.value().to_lowercase();
+        let matches: Vec<bool> = self.processes.iter()
+            .map(|p| filter.is_empty()
+                || p.pid.to_string().contains(&filter)
+                || p.name.to_lowercase().contains(&filter)
+                || p.cmdline.to_lowercase().contains(&filter))
+            .collect();
+
+        if !self.tree {
+            let mut visible: Vec<usize> = (0..self.processes.len()).filter(|&i| matches[i]).collect();
+            visible.sort_by(|&a, &b| self.compare(a, b));
+            self.visible = visible.into_iter()
+                .map(|i| Node { index: i, depth: 0, branch: Branch::Leaf })
+                .collect();
+            return;
+        }
+
+        // Children by parent index, PIDs get reused so a parent must not be younger than its child
+        let by_pid: HashMap<u32, usize> = self.processes.iter().enumerate().map(|(i, p)| (p.pid, i)).collect();
+        let mut roots = vec![];
+        let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, p) in self.processes.iter().enumerate() {
+            match by_pid.get(&p.ppid) {
+                Some(&parent) if p.ppid != p.pid && self.processes[parent].start_time <= p.start_time => {
+                    children.entry(parent).or_default().push(i)
+                }
+                _ => roots.push(i)
+            }
+        }
+
+        // A process stays when it or one of its descendants matches the filter
+        let mut keep = matches.clone();
+        for i in (0..self.processes.len()).filter(|&i| matches[i]) {
+            let mut pid = self.processes[i].ppid;
+            let mut depth = 0;
+            while let Some(&parent) = by_pid.get(&pid) {
+                if keep[parent] || depth > self.processes.len() { break; }
+                keep[parent] = true;
+                pid = self.processes[parent].ppid;
+                depth += 1;
+            }
+        }
+
+        roots.retain(|&i| keep[i]);
+        roots.sort_by(|&a, &b| self.compare(a, b));
+        for list in children.values_mut() {
+            list.retain(|&i| keep[i]);
+            list.sort_by(|&a, &b| self.compare(a, b));
+        }
+
+        // Collapsed branches open up while filtering so matches are never hidden
+        let mut visible = vec![];
+        let mut stack: Vec<(usize, usize)> = roots.into_iter().rev().map(|i| (i, 0)).collect();
+        while let Some((i, depth)) = stack.pop() {
+            let kids = children.get(&i).map(|c| c.as_slice()).unwrap_or(&[]);
+            let branch = if kids.is_empty() {
+                Branch::Leaf
+            } else if filter.is_empty() && self.collapsed.contains(&self.processes[i].pid) {
+                Branch::Collapsed
+            } else {
+                Branch::Expanded
+            };
+            visible.push(Node { index: i, depth: depth, branch: branch });
+            if branch == Branch::Expanded {
+                stack.extend(kids.iter().rev().map(|&c| (c, depth + 1)));
+            }
+        }
+        self.visible = visible;
+    }
+
+    fn compare(&self, a: usize, b: usize) -> Ordering {
+        let (a, b) = (&self.processes[a], &self.processes[b]);
+        let order = match self.sort {
+            SortColumn::Pid => a.pid.cmp(&b.pid),
+            SortColumn::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortColumn::User => a.user.cmp(&b.user),
+            SortColumn::Memory => a.memory.total_cmp(&b.memory),
+            SortColumn::Started => a.start_time.cmp(&b.start_time),
+        }.then(a.pid.cmp(&b.pid));
+        if self.descending { order.reverse() } else { order }
+    }
+
+    pub fn toggle_tree(&mut self) {
+        self.tree = !self.tree;
+        self.update();
+    }
+
+    // Returns the new position of the row, which stays selected
+    pub fn collapse(&mut self, index: usize) -> Option<usize> {
+        let node = self.visible.get(index)?;
+        match node.branch {
+            Branch::Expanded => {
+                let pid = self.processes[node.index].pid;
+                self.collapsed.insert(pid);
+                self.update();
+                self.position(pid)
+            }
+            // Moves up to the parent like a file tree
+            _ => {
+                let depth = node.depth;
+                (0..index).rev().find(|&i| self.visible[i].depth < depth)
+            }
+        }
+    }
+
+    pub fn expand(&mut self, index: usize) -> Option<usize> {
+        let pid = self.get(index)?.pid;
+        if self.collapsed.remove(&pid) {
+            self.update();
+        }
+        self.position(pid)
+    }
+
+    pub fn position(&self, pid: u32) -> Option<usize> {
+        self.visible.iter().position(|n| self.processes[n.index].pid == pid)
+    }
+
+    // Selecting the current column again flips the order
+    pub fn sort_by(&mut self, column: SortColumn) {
+        if self.sort == column {
+            self.descending = !self.descending;
+        } else {
+            self.sort = column;
+            self.descending = matches!(column, SortColumn::Memory | SortColumn::Started);
+        }
+        self.update();
+    }
+
+    pub fn len(&self) -> usize {
+        self.visible.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.visible.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&ProcessInfo> {
+        self.processes.get(self.visible.get(index)?.index)
+    }
+
+    // Rows with their depth in the tree, always 0 in the flat list
+    pub fn iter(&self) -> impl Iterator<Item = (&ProcessInfo, usize, Branch)> {
+        self.visible.iter().map(|n| (&self.processes[n.index], n.depth, n.branch))
+    }
+}
+
+
+// Newest process called name, the executable path counts too since Linux cuts names at 15 characters
+pub fn find_by_name(name: &str) -> Option<ProcessInfo> {
+    let matches = |p: &ProcessInfo| {
+        let exe = p.cmdline.split_whitespace().next()
+            .and_then(|exe| Path::new(exe).file_name())
+            .map(|exe| exe.to_string_lossy().to_string())
+            .unwrap_or_default();
+        [p.name.as_str(), p.name.trim_end_matches(".exe"), exe.as_str()].iter()
+            .any(|n| n.eq_ignore_ascii_case(name))
+    };
+
+    enum_processes().into_iter()
+        .filter(|p| matches(p))
+        .max_by_key(|p| (p.start_time, p.pid))
+}
+
+
+pub fn find_by_pid(pid: u32) -> Option<ProcessInfo> {
+    enum_processes().into_iter().find(|p| p.pid == pid)
+}
+
+
+// Time since the process started, like "3d 04h" or "12m 05s"
+pub fn format_age(start_time: u64) -> String {
+    if start_time == 0 {
+        return String::from("?");
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let age = now.saturating_sub(start_time);
+
+    match age {
+        0..=59 => format!("{}s", age),
+        60..=3599 => format!("{}m {:02}s", age / 60, age % 60),
+        3600..=86399 => format!("{}h {:02}m", age / 3600, age % 3600 / 60),
+        _ => format!("{}d {:02}h", age / 86400, age % 86400 / 3600),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, name: &str, memory: f64, cmdline: &str) -> ProcessInfo {
+        ProcessInfo {
+            name: name.to_string(), memory: memory, pid: pid, ppid: 0, user: String::from("root"),
+            cmdline: cmdline.to_string(), start_time: pid as u64,
+        }
+    }
+
+    fn list() -> ProcessList {
+        let mut list = ProcessList::new();
+        list.processes = vec![
+            process(300, "Game.exe", 500.0, "game.exe -windowed"),
+            process(12, "shell", 20.0, "/bin/shell"),
+            process(1300, "server", 100.0, "server --port 3000"),
+        ];
+        list.update();
+        list
+    }
+
+    fn pids(list: &ProcessList) -> Vec<u32> {
+        list.iter().map(|(p, _, _)| p.pid).collect()
+    }
+
+    #[test]
+    fn memory_descending_by_default() {
+        assert_eq!(pids(&list()), [300, 1300, 12]);
+    }
+
+    #[test]
+    fn filter_name_pid_and_cmdline() {
+        let mut list = list();
+        list.filter = Input::new(String::from("GAME"));
+        list.update();
+        assert_eq!(pids(&list), [300]);
+
+        list.filter = Input::new(String::from("300"));
+        list.update();
+        assert_eq!(pids(&list), [300, 1300]);
+
+        list.filter = Input::new(String::from("/bin"));
+        list.update();
+        assert_eq!(pids(&list), [12]);
+        assert_eq!(list.get(0).map(|p| p.name.as_str()), Some("shell"));
+        assert!(list.get(1).is_none());
+    }
+
+    #[test]
+    fn sort_columns() {
+        let mut list = list();
+        list.sort_by(SortColumn::Name);
+        assert_eq!(pids(&list), [300, 1300, 12]);
+        list.sort_by(SortColumn::Name);
+        assert_eq!(pids(&list), [12, 1300, 300]);
+
+        list.sort_by(SortColumn::Pid);
+        assert_eq!(pids(&list), [12, 300, 1300]);
+        list.sort_by(SortColumn::Started);
+        assert_eq!(pids(&list), [1300, 300, 12]);
+    }
+
+    #[test]
+    fn find_test_process_by_name() {
+        // The test binary's name is longer than the 15 characters Linux keeps
+        let exe = std::env::current_exe().unwrap();
+        let name = exe.file_name().unwrap().to_string_lossy().to_uppercase();
+        assert_eq!(find_by_name(&name).map(|p| p.pid), Some(std::process::id()));
+        assert!(find_by_name("no-such-process-name").is_none());
+    }
+
+    #[test]
+    fn ages() {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        assert_eq!(format_age(0), "?");
+        assert_eq!(format_age(now - 3725), "1h 02m");
+        assert_eq!(format_age(now - 2 * 86400 - 7200), "2d 02h");
+    }
+
+    // init
+    // ├ server
+    // └ shell
+    //   └ Game.exe
+    // orphan, whose parent PID was reused by the younger Game.exe
+    fn tree() -> ProcessList {
+        let mut list = ProcessList::new();
+        list.processes = [(1, 0, "init", 10.0), (12, 1, "shell", 20.0), (300, 12, "Game.exe", 500.0),
+                          (1300, 1, "server", 100.0), (5, 300, "orphan", 1.0)]
+            .into_iter()
+            .map(|(pid, ppid, name, memory)| ProcessInfo { ppid: ppid, ..process(pid, name, memory, "") })
+            .collect();
+        list.toggle_tree();
+        list
+    }
+
+    fn rows(list: &ProcessList) -> Vec<(u32, usize)> {
+        list.iter().map(|(p, depth, _)| (p.pid, depth)).collect()
+    }
+
+    #[test]
+    fn tree_order_and_depth() {
+        let mut list = tree();
+        assert_eq!(rows(&list), [(1, 0), (1300, 1), (12, 1), (300, 2), (5, 0)]);
+
+        list.toggle_tree();
+        assert_eq!(rows(&list), [(300, 0), (1300, 0), (12, 0), (1, 0), (5, 0)]);
+    }
+
+    #[test]
+    fn collapse_and_expand() {
+        let mut list = tree();
+        assert_eq!(list.collapse(2), Some(2));
+        assert_eq!(rows(&list), [(1, 0), (1300, 1), (12, 1), (5, 0)]);
+        assert!(list.iter().nth(2).is_some_and(|(_, _, branch)| branch == Branch::Collapsed));
+
+        // Collapsing a collapsed branch or a leaf selects the parent
+        assert_eq!(list.collapse(2), Some(0));
+        assert_eq!(list.collapse(1), Some(0));
+
+        assert_eq!(list.expand(2), Some(2));
+        assert_eq!(rows(&list), [(1, 0), (1300, 1), (12, 1), (300, 2), (5, 0)]);
+    }
+
+    #[test]
+    fn filter_keeps_ancestors() {
+        let mut list = tree();
+        list.collapse(2);
+        list.filter = Input::new(String::from("game"));
+        list.update();
+        assert_eq!(rows(&list), [(1, 0), (12, 1), (300, 2)]);
+
+        list.filter = Input::new(String::new());
+        list.update();
+        assert_eq!(rows(&list), [(1, 0), (1300, 1), (12, 1), (5, 0)]);
+    }
+}
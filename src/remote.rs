@@ -0,0 +1,365 @@
+//! Processes of another machine or container, served by `mismem-agent`
+//!
+//! After [`connect`] every function of [`os`](crate::os) works on the agent's machine, [`serve`] is
+//! the agent side. Requests and responses are JSON lines, memory follows them as raw bytes.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::native;
+use crate::process::{Mapping, Module, ProcessDetails, ProcessInfo, Region};
+
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Request {
+    Processes,
+    Details { pid: u32 },
+    Check { pid: u32 },
+    Mappings { pid: u32 },
+    Modules { pid: u32 },
+    Regions { pid: u32 },
+    PointerSize { pid: u32 },
+    // Answered by Data and len bytes
+    Read { pid: u32, address: usize, len: usize },
+    // Followed by len bytes
+    Write { pid: u32, address: usize, len: usize },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Response {
+    Processes(Vec<ProcessInfo>),
+    Details(ProcessDetails),
+    Mappings(Vec<Mapping>),
+    Modules(Vec<Module>),
+    Regions(Vec<Region>),
+    Number(usize),
+    Bool(bool),
+    Data,
+    Done,
+    Error(String),
+}
+
+
+struct Agent {
+    address: String,
+    stream: Mutex<Option<BufReader<TcpStream>>>,
+}
+
+static AGENT : OnceLock<Agent> = OnceLock::new();
+
+const TIMEOUT : Duration = Duration::from_secs(30);
+
+// Largest read or write in one request, the client splits bigger ones
+const MAX_TRANSFER : usize = 64 << 20;
+
+fn open(address: &str) -> io::Result<BufReader<TcpStream>> {
+    let address = address.to_socket_addrs()?.next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("can't resolve {}", address)))?;
+    let stream = TcpStream::connect_timeout(&address, Duration::from_secs(5))?;
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    Ok(BufReader::new(stream))
+}
+
+/// Connects to a `mismem-agent` at address, like `10.0.0.2:7780`, for the rest of the program
+pub fn connect(address: &str) -> io::Result<()> {
+    let stream = open(address)?;
+    AGENT.set(Agent { address: address.to_string(), stream: Mutex::new(Some(stream)) })
+        .map_err(|_| io::Error::new(io::ErrorKind::AlreadyExists, "already connected to an agent"))
+}
+
+/// Address of the agent given to [`connect`], None when processes are local
+pub fn address() -> Option<&'static str> {
+    AGENT.get().map(|agent| agent.address.as_str())
+}
+
+pub(crate) fn is_remote() -> bool {
+    AGENT.get().is_some()
+}
+
+
+fn read_message<T: DeserializeOwned>(stream: &mut impl BufRead) -> io::Result<Option<T>> {
+    let mut line = String::new();
+    if stream.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    serde_json::from_str(&line).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message(stream: &mut impl Write, message: &impl Serialize, payload: &[u8]) -> io::Result<()> {
+    let mut bytes = serde_json::to_vec(message)?;
+    bytes.push(b'\n');
+    bytes.extend_from_slice(payload);
+    stream.write_all(&bytes)
+}
+
+// A dropped connection is opened again on the next call
+fn call(request: Request, payload: &[u8], data: &mut [u8]) -> io::Result<Response> {
+    let agent = AGENT.get().ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "no agent"))?;
+    let mut stream = agent.stream.lock().unwrap();
+    if stream.is_none() {
+        *stream = Some(open(&agent.address)?);
+    }
+
+    let result = exchange(stream.as_mut().unwrap(), &request, payload, data);
+    if result.is_err() {
+        *stream = None;
+    }
+    match result? {
+        Response::Error(error) => Err(io::Error::other(error)),
+        response => Ok(response),
+    }
+}
+
+fn exchange(connection: &mut BufReader<TcpStream>, request: &Request, payload: &[u8], data: &mut [u8]) -> io::Result<Response> {
+    write_message(connection.get_mut(), request, payload)?;
+    let response = read_message(connection)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "agent closed the connection"))?;
+    if let Response::Data = response {
+        connection.read_exact(data)?;
+    }
+    Ok(response)
+}
+
+fn unexpected<T>() -> io::Result<T> {
+    Err(io::Error::new(io::ErrorKind::InvalidData, "unexpected response from the agent"))
+}
+
+
+// Client side of every function of os
+
+pub(crate) fn enum_processes() -> Vec<ProcessInfo> {
+    match call(Request::Processes, &[], &mut []) {
+        Ok(Response::Processes(processes)) => processes,
+        _ => vec![]
+    }
+}
+
+pub(crate) fn process_details(pid : u32) -> io::Result<ProcessDetails> {
+    match call(Request::Details { pid: pid }, &[], &mut [])? {
+        Response::Details(details) => Ok(details),
+        _ => unexpected()
+    }
+}
+
+pub(crate) fn check_process(pid : u32) -> bool {
+    matches!(call(Request::Check { pid: pid }, &[], &mut []), Ok(Response::Bool(true)))
+}
+
+pub(crate) fn enum_mappings(pid : u32) -> Vec<Mapping> {
+    match call(Request::Mappings { pid: pid }, &[], &mut []) {
+        Ok(Response::Mappings(mappings)) => mappings,
+        _ => vec![]
+    }
+}
+
+pub(crate) fn enum_modules(pid : u32) -> Vec<Module> {
+    match call(Request::Modules { pid: pid }, &[], &mut []) {
+        Ok(Response::Modules(modules)) => modules,
+        _ => vec![]
+    }
+}
+
+pub(crate) fn enum_regions(pid : u32) -> Vec<Region> {
+    match call(Request::Regions { pid: pid }, &[], &mut []) {
+        Ok(Response::Regions(regions)) => regions,
+        _ => vec![]
+    }
+}
+
+pub(crate) fn pointer_size(pid : u32) -> usize {
+    match call(Request::PointerSize { pid: pid }, &[], &mut []) {
+        Ok(Response::Number(size)) => size,
+        _ => std::mem::size_of::<usize>()
+    }
+}
+
+pub(crate) fn read_process(pid : u32, address : usize, buffer: &mut [u8]) -> bool {
+    read_chunks(address, buffer, MAX_TRANSFER, |address, chunk| {
+        let request = Request::Read { pid: pid, address: address, len: chunk.len() };
+        matches!(call(request, &[], chunk), Ok(Response::Data))
+    })
+}
+
+pub(crate) fn write_process(pid : u32, address : usize, target_bytes: &[u8]) -> io::Result<()> {
+    write_chunks(address, target_bytes, MAX_TRANSFER, |address, chunk| {
+        let request = Request::Write { pid: pid, address: address, len: chunk.len() };
+        match call(request, chunk, &mut [])? {
+            Response::Done => Ok(()),
+            _ => unexpected()
+        }
+    })
+}
+
+// One request per max bytes, reads stop at the first chunk that fails
+fn read_chunks(address: usize, buffer: &mut [u8], max: usize, mut read: impl FnMut(usize, &mut [u8]) -> bool) -> bool {
+    buffer.chunks_mut(max).enumerate().all(|(i, chunk)| read(address + i * max, chunk))
+}
+
+fn write_chunks(address: usize, bytes: &[u8], max: usize, mut write: impl FnMut(usize, &[u8]) -> io::Result<()>) -> io::Result<()> {
+    bytes.chunks(max).enumerate().try_for_each(|(i, chunk)| write(address + i * max, chunk))
+}
+
+
+/// Answers the requests of one client with the processes of this machine, until it disconnects
+pub fn serve(stream: TcpStream) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    while let Some(request) = read_message::<Request>(&mut reader)? {
+        let mut data = vec![];
+        let response = match request {
+            Request::Processes => Response::Processes(native::enum_processes()),
+            Request::Details { pid } => match native::process_details(pid) {
+                Ok(details) => Response::Details(details),
+                Err(error) => Response::Error(error.to_string()),
+            },
+            Request::Check { pid } => Response::Bool(native::check_process(pid)),
+            Request::Mappings { pid } => Response::Mappings(native::enum_mappings(pid)),
+            Request::Modules { pid } => Response::Modules(native::enum_modules(pid)),
+            Request::Regions { pid } => Response::Regions(native::enum_regions(pid)),
+            Request::PointerSize { pid } => Response::Number(native::pointer_size(pid)),
+            Request::Read { len, .. } | Request::Write { len, .. } if len > MAX_TRANSFER => {
+                // The payload of a write is skipped to keep the stream in step
+                if let Request::Write { .. } = request {
+                    io::copy(&mut (&mut reader).take(len as u64), &mut io::sink())?;
+                }
+                Response::Error(format!("{} bytes is more than the {} a request can transfer", len, MAX_TRANSFER))
+            },
+            Request::Read { pid, address, len } => {
+                data = vec![0; len];
+                if native::read_process(pid, address, &mut data) {
+                    Response::Data
+                } else {
+                    data.clear();
+                    Response::Error(format!("can't read {} bytes at {:X}", len, address))
+                }
+            },
+            Request::Write { pid, address, len } => {
+                let mut bytes = vec![0; len];
+                reader.read_exact(&mut bytes)?;
+                match native::write_process(pid, address, &bytes) {
+                    Ok(()) => Response::Done,
+                    Err(error) => Response::Error(error.to_string()),
+                }
+            },
+        };
+        write_message(&mut writer, &response, &data)?;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Cursor, net::TcpListener, thread};
+
+    #[test]
+    fn message_framing() {
+        let mut bytes = vec![];
+        write_message(&mut bytes, &Request::Write { pid: 1, address: 0x10, len: 3 }, &[1, 2, 3]).unwrap();
+        write_message(&mut bytes, &Request::Processes, &[]).unwrap();
+        assert!(bytes.starts_with(br#"{"op":"write","pid":1,"address":16,"len":3}"#));
+
+        let mut stream = Cursor::new(bytes);
+        match read_message(&mut stream).unwrap() {
+            Some(Request::Write { pid: 1, address: 0x10, len: 3 }) => {},
+            _ => panic!("expected the write request")
+        }
+        let mut payload = [0; 3];
+        stream.read_exact(&mut payload).unwrap();
+        assert_eq!(payload, [1, 2, 3]);
+        assert!(matches!(read_message(&mut stream).unwrap(), Some(Request::Processes)));
+        assert!(read_message::<Request>(&mut stream).unwrap().is_none());
+
+        let error = read_message::<Request>(&mut Cursor::new(b"{\"op\":\"format\"}\n")).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    // The agent serves the test process to itself over loopback
+    #[test]
+    fn agent_reads_and_writes() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || serve(listener.accept().unwrap().0));
+
+        let mut connection = open(&address).unwrap();
+        let buffer = [0u8; 8];
+        let (pid, target) = (std::process::id(), buffer.as_ptr() as usize);
+
+        let request = Request::Write { pid: pid, address: target + 2, len: 4 };
+        assert!(matches!(exchange(&mut connection, &request, &[9, 8, 7, 6], &mut []).unwrap(), Response::Done));
+        assert_eq!(unsafe { std::ptr::read_volatile(&buffer) }, [0, 0, 9, 8, 7, 6, 0, 0]);
+
+        let mut data = [0; 8];
+        let request = Request::Read { pid: pid, address: target, len: 8 };
+        assert!(matches!(exchange(&mut connection, &request, &[], &mut data).unwrap(), Response::Data));
+        assert_eq!(data, [0, 0, 9, 8, 7, 6, 0, 0]);
+
+        let request = Request::Read { pid: pid, address: 0, len: 8 };
+        assert!(matches!(exchange(&mut connection, &request, &[], &mut data).unwrap(), Response::Error(_)));
+        let request = Request::PointerSize { pid: pid };
+        assert!(matches!(exchange(&mut connection, &request, &[], &mut []).unwrap(), Response::Number(8)));
+    }
+
+    // Chunks of 3 bytes stand in for MAX_TRANSFER
+    #[test]
+    fn transfers_split_in_chunks() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || serve(listener.accept().unwrap().0));
+
+        let mut connection = open(&address).unwrap();
+        let buffer = [0u8; 8];
+        let (pid, target) = (std::process::id(), buffer.as_ptr() as usize);
+
+        let mut requests = vec![];
+        write_chunks(target, &[1, 2, 3, 4, 5, 6, 7, 8], 3, |address, chunk| {
+            requests.push((address - target, chunk.len()));
+            let request = Request::Write { pid: pid, address: address, len: chunk.len() };
+            match exchange(&mut connection, &request, chunk, &mut [])? {
+                Response::Done => Ok(()),
+                _ => unexpected()
+            }
+        }).unwrap();
+        assert_eq!(requests, [(0, 3), (3, 3), (6, 2)]);
+        assert_eq!(unsafe { std::ptr::read_volatile(&buffer) }, [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let mut data = [0; 8];
+        assert!(read_chunks(target, &mut data, 3, |address, chunk| {
+            let request = Request::Read { pid: pid, address: address, len: chunk.len() };
+            matches!(exchange(&mut connection, &request, &[], chunk), Ok(Response::Data))
+        }));
+        assert_eq!(data, [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        // A failing chunk fails the whole read without asking for the rest
+        let mut calls = 0;
+        assert!(!read_chunks(target, &mut data, 3, |_, _| { calls += 1; calls < 2 }));
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn oversized_request_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || serve(listener.accept().unwrap().0));
+
+        let mut connection = open(&address).unwrap();
+        let pid = std::process::id();
+        let request = Request::Read { pid: pid, address: 0x1000, len: MAX_TRANSFER + 1 };
+        assert!(matches!(exchange(&mut connection, &request, &[], &mut []).unwrap(), Response::Error(_)));
+        // The connection is still in step
+        let request = Request::PointerSize { pid: pid };
+        assert!(matches!(exchange(&mut connection, &request, &[], &mut []).unwrap(), Response::Number(8)));
+    }
+}
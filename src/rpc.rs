@@ -0,0 +1,518 @@
+use std::{
+    collections::HashMap,
+    io,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value as Json};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpListener,
+    sync::{mpsc, Mutex},
+    task::JoinHandle,
+};
+#[cfg(unix)]
+use tokio::net::UnixListener;
+
+use crate::app::{App, EditState};
+use crate::batch::{process_entries, read_value, Value};
+use crate::expr::resolve_address;
+use crate::mem::Datatype;
+use crate::os::{check_process, enum_processes, write_process};
+use crate::proclist::find_by_name;
+use crate::search::{filter_process, scan_process};
+
+
+// JSON-RPC 2.0 control server, one request or response per line:
+//
+//   ps                              attach {pid} or {name}
+//   scan {type, value}              filter {value, type?}
+//   results {offset?, limit?}       read {address, type}
+//   write {address, type, value}    subscribe {address, type, interval?}
+//   unsubscribe {subscription}
+//
+// Requests work on the same App as the keyboard, so the TUI shows what they do.
+// Subscriptions send a "value" notification whenever the value changes.
+
+const PARSE_ERROR : i64 = -32700;
+const INVALID_REQUEST : i64 = -32600;
+const METHOD_NOT_FOUND : i64 = -32601;
+const INVALID_PARAMS : i64 = -32602;
+const FAILED : i64 = -32000;
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> RpcError {
+        RpcError { code: code, message: message.into() }
+    }
+
+    fn failed(message: impl Into<String>) -> RpcError {
+        RpcError::new(FAILED, message)
+    }
+}
+
+
+#[derive(Deserialize)]
+struct AttachParams {
+    pid: Option<u32>,
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ScanParams {
+    #[serde(rename = "type")]
+    datatype: String,
+    value: Json,
+}
+
+#[derive(Deserialize)]
+struct FilterParams {
+    #[serde(rename = "type")]
+    datatype: Option<String>,
+    value: Json,
+}
+
+#[derive(Deserialize)]
+struct ResultsParams {
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct ReadParams {
+    address: Json,
+    #[serde(rename = "type")]
+    datatype: String,
+}
+
+#[derive(Deserialize)]
+struct WriteParams {
+    address: Json,
+    #[serde(rename = "type")]
+    datatype: String,
+    value: Json,
+}
+
+#[derive(Deserialize)]
+struct SubscribeParams {
+    address: Json,
+    #[serde(rename = "type")]
+    datatype: String,
+    // Milliseconds between reads
+    interval: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct UnsubscribeParams {
+    subscription: u64,
+}
+
+
+fn params<T: DeserializeOwned>(params: Json) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn datatype(name: &str) -> Result<Datatype, RpcError> {
+    Datatype::from_name(name).ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("unknown type '{}'", name)))
+}
+
+// Values may be sent as numbers or strings
+fn to_bytes(value: &Json, datatype: &Datatype) -> Result<Vec<u8>, RpcError> {
+    let value = match value {
+        Json::String(value) => value.clone(),
+        value => value.to_string(),
+    };
+    datatype.parse(&value).map_err(|e| RpcError::new(INVALID_PARAMS, format!("invalid {} '{}': {}", datatype.name(), value, e)))
+}
+
+// Addresses are numbers or expressions like "game+0x10"
+fn address(pid: u32, address: &Json) -> Result<usize, RpcError> {
+    match address {
+        Json::Number(number) => number.as_u64().map(|a| a as usize)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("invalid address {}", number))),
+        Json::String(expr) => resolve_address(pid, expr)
+            .map_err(|e| RpcError::new(INVALID_PARAMS, format!("invalid address '{}': {}", expr, e))),
+        _ => Err(RpcError::new(INVALID_PARAMS, "an address is a number or an expression string")),
+    }
+}
+
+// Attached process that is still running
+fn attached(app: &App) -> Result<u32, RpcError> {
+    match &app.exited {
+        Some(exited) => Err(RpcError::failed(format!("process {} exited, results are read-only", exited.pid))),
+        None if app.selected_process == 0 => Err(RpcError::failed("no process attached")),
+        None => Ok(app.selected_process),
+    }
+}
+
+
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl Listener {
+    // Addresses like 127.0.0.1:7777 are TCP, anything else is a Unix socket path
+    pub async fn bind(address: &str) -> io::Result<Listener> {
+        let tcp = address.strip_prefix("localhost:").map(|port| format!("127.0.0.1:{}", port));
+        if let Ok(address) = tcp.as_deref().unwrap_or(address).parse::<SocketAddr>() {
+            if !address.ip().is_loopback() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "only localhost addresses are allowed"));
+            }
+            return Ok(Listener::Tcp(TcpListener::bind(address).await?));
+        }
+
+        #[cfg(unix)]
+        return Ok(Listener::Unix(UnixListener::bind(address)?, PathBuf::from(address)));
+        #[cfg(not(unix))]
+        Err(io::Error::new(io::ErrorKind::InvalidInput, "expected a localhost address like 127.0.0.1:7777"))
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Listener::Tcp(listener) => listener.local_addr().map(|a| a.to_string()).unwrap_or_default(),
+            #[cfg(unix)]
+            Listener::Unix(_, path) => path.display().to_string(),
+        }
+    }
+
+    // Removed when mismem exits
+    pub fn socket_path(&self) -> Option<PathBuf> {
+        match self {
+            #[cfg(unix)]
+            Listener::Unix(_, path) => Some(path.clone()),
+            _ => None,
+        }
+    }
+
+    pub async fn serve(self, app: Arc<Mutex<App>>) {
+        loop {
+            let accepted = match &self {
+                Listener::Tcp(listener) => listener.accept().await
+                    .map(|(stream, _)| tokio::spawn(serve_connection(stream, Arc::clone(&app)))),
+                #[cfg(unix)]
+                Listener::Unix(listener, _) => listener.accept().await
+                    .map(|(stream, _)| tokio::spawn(serve_connection(stream, Arc::clone(&app)))),
+            };
+            if let Err(error) = accepted {
+                log::error!(" JSON-RPC connection failed: {}", error);
+            }
+        }
+    }
+}
+
+
+async fn serve_connection(stream: impl AsyncRead + AsyncWrite + Send + 'static, app: Arc<Mutex<App>>) {
+    let (reader, mut writer) = tokio::io::split(stream);
+
+    // Responses and notifications share the writer
+    let (output, mut lines) = mpsc::unbounded_channel::<String>();
+    tokio::spawn(async move {
+        while let Some(line) = lines.recv().await {
+            if writer.write_all(format!("{}\n", line).as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut connection = Connection { app: app, output: output, subscriptions: HashMap::new(), next_id: 1 };
+    let mut reader = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = reader.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = connection.handle(&line).await {
+            _ = connection.output.send(response.to_string());
+        }
+    }
+
+    for (_, subscription) in connection.subscriptions.drain() {
+        subscription.abort();
+    }
+}
+
+
+struct Connection {
+    app: Arc<Mutex<App>>,
+    output: mpsc::UnboundedSender<String>,
+    subscriptions: HashMap<u64, JoinHandle<()>>,
+    next_id: u64,
+}
+
+impl Connection {
+    // Requests without an id are notifications and get no response
+    async fn handle(&mut self, line: &str) -> Option<Json> {
+        let request : Json = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(error) => return Some(response(Json::Null, Err(RpcError::new(PARSE_ERROR, error.to_string())))),
+        };
+        let id = request.get("id").cloned();
+        let method = match request.get("method").and_then(|m| m.as_str()) {
+            Some(method) => method.to_string(),
+            None => return Some(response(id.unwrap_or_default(), Err(RpcError::new(INVALID_REQUEST, "missing method")))),
+        };
+        let result = self.call(&method, request.get("params").cloned().unwrap_or_default()).await;
+        Some(response(id?, result))
+    }
+
+    async fn call(&mut self, method: &str, params_json: Json) -> Result<Json, RpcError> {
+        match method {
+            "ps" => Ok(json!(process_entries(&enum_processes()))),
+            "attach" => {
+                let p : AttachParams = params(params_json)?;
+                let pid = match (p.pid, p.name) {
+                    (Some(pid), _) if check_process(pid) => pid,
+                    (Some(pid), _) => return Err(RpcError::failed(format!("process {} is not running or can't be opened", pid))),
+                    (None, Some(name)) => find_by_name(&name).map(|p| p.pid)
+                        .ok_or_else(|| RpcError::failed(format!("{} is not running", name)))?,
+                    (None, None) => return Err(RpcError::new(INVALID_PARAMS, "attach needs pid or name")),
+                };
+
+                let mut app = self.app.lock().await;
+                if matches!(app.edit_state, EditState::Busy) {
+                    return Err(RpcError::failed("a scan is running"));
+                }
+                app.target = None;
+                app.memory.clear();
+                app.attach(pid);
+                log::info!(" Attached to process {} over JSON-RPC.", pid);
+                Ok(json!({ "pid": pid }))
+            },
+            "scan" => {
+                let p : ScanParams = params(params_json)?;
+                let datatype = datatype(&p.datatype)?;
+                let bytes = to_bytes(&p.value, &datatype)?;
+
+                let (pid, previous) = self.start_search().await?;
+                let ranges = self.app.lock().await.memory_map.scan_ranges();
+                scan_process(pid, &bytes, &datatype, ranges, Arc::clone(&self.app)).await;
+                Ok(json!({ "count": self.end_search(previous).await }))
+            },
+            "filter" => {
+                let p : FilterParams = params(params_json)?;
+                let datatype = match p.datatype {
+                    Some(name) => datatype(&name)?,
+                    None => match self.app.lock().await.memory.datatypes().as_slice() {
+                        [datatype] => *datatype,
+                        [] => return Err(RpcError::failed("nothing to filter, scan first")),
+                        _ => return Err(RpcError::new(INVALID_PARAMS, "results hold several types, pick one with type")),
+                    }
+                };
+                let bytes = to_bytes(&p.value, &datatype)?;
+
+                let (pid, previous) = self.start_search().await?;
+                filter_process(pid, &bytes, &datatype, Arc::clone(&self.app)).await;
+                Ok(json!({ "count": self.end_search(previous).await }))
+            },
+            "results" => {
+                let p : ResultsParams = params(params_json)?;
+                let app = self.app.lock().await;
+                let results : Vec<Json> = app.memory.iter().skip(p.offset).take(p.limit.unwrap_or(1000))
+                    .map(|[location, value, old_value]| {
+                        let (address, datatype) = location.split_once(':').unwrap_or((&location, ""));
+                        json!({ "address": address, "type": datatype, "value": value, "old_value": old_value })
+                    })
+                    .collect();
+                Ok(json!({ "count": app.memory.len(), "results": results }))
+            },
+            "read" => {
+                let p : ReadParams = params(params_json)?;
+                let pid = attached(&*self.app.lock().await)?;
+                let (address, datatype) = (address(pid, &p.address)?, datatype(&p.datatype)?);
+
+                Ok(json!(Value {
+                    pid: pid,
+                    address: format!("{:016X}", address),
+                    datatype: datatype.name(),
+                    value: read_value(pid, address, &datatype).map_err(RpcError::failed)?,
+                    old_value: None,
+                }))
+            },
+            "write" => {
+                let p : WriteParams = params(params_json)?;
+                let pid = attached(&*self.app.lock().await)?;
+                let (address, datatype) = (address(pid, &p.address)?, datatype(&p.datatype)?);
+                let bytes = to_bytes(&p.value, &datatype)?;
+
+                let old_value = read_value(pid, address, &datatype).map_err(RpcError::failed)?;
+                write_process(pid, address, &bytes).map_err(|e| RpcError::failed(format!("can't write {:X}: {}", address, e)))?;
+                log::info!(" {:016X} written over JSON-RPC.", address);
+
+                Ok(json!(Value {
+                    pid: pid,
+                    address: format!("{:016X}", address),
+                    datatype: datatype.name(),
+                    value: read_value(pid, address, &datatype).map_err(RpcError::failed)?,
+                    old_value: Some(old_value),
+                }))
+            },
+            "subscribe" => {
+                let p : SubscribeParams = params(params_json)?;
+                let pid = attached(&*self.app.lock().await)?;
+                let (address, datatype) = (address(pid, &p.address)?, datatype(&p.datatype)?);
+                let interval = Duration::from_millis(p.interval.unwrap_or(500).max(50));
+
+                let id = self.next_id;
+                self.next_id += 1;
+                let subscription = subscribe(id, address, datatype, interval, Arc::clone(&self.app), self.output.clone());
+                self.subscriptions.insert(id, subscription);
+                Ok(json!({ "subscription": id }))
+            },
+            "unsubscribe" => {
+                let p : UnsubscribeParams = params(params_json)?;
+                let subscription = self.subscriptions.remove(&p.subscription);
+                if let Some(subscription) = &subscription {
+                    subscription.abort();
+                }
+                Ok(json!(subscription.is_some()))
+            },
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method '{}'", method))),
+        }
+    }
+
+    // Scans show up in the TUI like the ones started from the keyboard
+    async fn start_search(&self) -> Result<(u32, EditState), RpcError> {
+        let mut app = self.app.lock().await;
+        let pid = attached(&app)?;
+        if matches!(app.edit_state, EditState::Busy) {
+            return Err(RpcError::failed("a scan is already running"));
+        }
+        let previous = app.edit_state;
+        app.edit_state = EditState::Busy;
+        Ok((pid, previous))
+    }
+
+    async fn end_search(&self, previous: EditState) -> usize {
+        let mut app = self.app.lock().await;
+        app.edit_state = previous;
+        app.refresh_modules();
+        app.memory.len()
+    }
+}
+
+
+fn response(id: Json, result: Result<Json, RpcError>) -> Json {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": error.code, "message": error.message } }),
+    }
+}
+
+// Reads the attached process, so subscriptions follow a reattach, value is null while unreadable
+fn subscribe(id: u64, address: usize, datatype: Datatype, interval: Duration, app: Arc<Mutex<App>>, output: mpsc::UnboundedSender<String>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        let mut last = None;
+
+        loop {
+            ticks.tick().await;
+            let pid = app.lock().await.selected_process;
+            let value = read_value(pid, address, &datatype).ok();
+            if last.as_ref() == Some(&value) {
+                continue;
+            }
+
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": "value",
+                "params": {
+                    "subscription": id,
+                    "pid": pid,
+                    "address": format!("{:016X}", address),
+                    "type": datatype.name(),
+                    "value": value,
+                },
+            });
+            if output.send(notification.to_string()).is_err() {
+                return;
+            }
+            last = Some(value);
+        }
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection() -> (Connection, mpsc::UnboundedReceiver<String>) {
+        let (output, lines) = mpsc::unbounded_channel();
+        let app = Arc::new(Mutex::new(App::new()));
+        (Connection { app: app, output: output, subscriptions: HashMap::new(), next_id: 1 }, lines)
+    }
+
+    async fn request(connection: &mut Connection, method: &str, params: Json) -> Json {
+        let line = json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": params }).to_string();
+        let response = connection.handle(&line).await.unwrap();
+        assert_eq!(response["id"], 7);
+        response
+    }
+
+    #[tokio::test]
+    async fn protocol_errors() {
+        let (mut connection, _) = connection();
+        assert_eq!(connection.handle("{").await.unwrap()["error"]["code"], PARSE_ERROR);
+        assert_eq!(connection.handle(r#"{"id": 1}"#).await.unwrap()["error"]["code"], INVALID_REQUEST);
+        assert_eq!(request(&mut connection, "launch", Json::Null).await["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(request(&mut connection, "attach", json!({})).await["error"]["code"], INVALID_PARAMS);
+        assert_eq!(request(&mut connection, "read", json!({ "address": 16 })).await["error"]["code"], INVALID_PARAMS);
+
+        // Notifications get no response, even when they fail
+        assert!(connection.handle(r#"{"method": "launch"}"#).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn read_and_write_attached_process() {
+        let (mut connection, _) = connection();
+        let buffer = [42u32, 0];
+        let address = buffer.as_ptr() as usize;
+
+        let response = request(&mut connection, "read", json!({ "address": address, "type": "u32" })).await;
+        assert_eq!(response["error"]["message"], "no process attached");
+
+        let pid = std::process::id();
+        assert_eq!(request(&mut connection, "attach", json!({ "pid": pid })).await["result"]["pid"], pid);
+
+        let response = request(&mut connection, "read", json!({ "address": address, "type": "u32" })).await;
+        assert_eq!(response["result"]["value"], "42");
+
+        let params = json!({ "address": format!("0x{:X}+4", address), "type": "u32", "value": 7 });
+        let response = request(&mut connection, "write", params).await;
+        assert_eq!((&response["result"]["old_value"], &response["result"]["value"]), (&json!("0"), &json!("7")));
+        assert_eq!(unsafe { std::ptr::read_volatile(&buffer[1]) }, 7);
+
+        let params = json!({ "address": address, "type": "u8", "value": 256 });
+        assert_eq!(request(&mut connection, "write", params).await["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn subscriptions() {
+        let (mut connection, mut lines) = connection();
+        let value = 1234u16;
+        let pid = std::process::id();
+        request(&mut connection, "attach", json!({ "pid": pid })).await;
+
+        let params = json!({ "address": &value as *const u16 as usize, "type": "u16" });
+        let id = request(&mut connection, "subscribe", params).await["result"]["subscription"].clone();
+        let notification : Json = serde_json::from_str(&lines.recv().await.unwrap()).unwrap();
+        assert_eq!((&notification["method"], &notification["params"]["value"]), (&json!("value"), &json!("1234")));
+
+        let response = request(&mut connection, "unsubscribe", json!({ "subscription": id })).await;
+        assert_eq!(response["result"], true);
+        let response = request(&mut connection, "unsubscribe", json!({ "subscription": id })).await;
+        assert_eq!(response["result"], false);
+    }
+}
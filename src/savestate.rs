@@ -0,0 +1,184 @@
+//! Private writable memory of a process, saved while it is stopped and written back later
+//!
+//! Savestates are [`snapshot`](crate::snapshot) files of the regions only the process can write:
+//! heap, stacks and the data of its modules. Registers and open files aren't saved. The process is
+//! stopped while saving and restoring, and a restore first checks that the saved regions are still
+//! where they were.
+
+use std::{fmt, io, path::Path};
+
+use crate::os::{enum_mappings, enum_regions, Process, Suspended};
+use crate::process::{Mapping, Region};
+use crate::snapshot::{save_regions, Snapshot};
+
+
+/// Saves every private writable region of pid, returns how many regions and bytes were saved
+pub fn save_state(pid: u32, path: &Path, progress: impl FnMut(f64)) -> io::Result<(usize, usize)> {
+    let _suspended = Suspended::new(pid)?;
+    let regions = enum_regions(pid).into_iter().filter(is_private).collect();
+    save_regions(pid, regions, path, progress)
+}
+
+fn is_private(region: &Region) -> bool {
+    region.readable && region.writable && !region.shared
+}
+
+
+/// Saved region that isn't in the process the same way anymore
+pub enum Mismatch {
+    /// The same file is mapped with the same size at another address
+    Moved { path: String, from: usize, to: usize },
+    /// Starts at the same address with another size
+    Resized { base: usize, from: usize, to: usize },
+    /// No private writable region starts there anymore
+    Missing { base: usize, size: usize },
+    /// Private writable region that wasn't saved, it's left as it is and doesn't stop a restore
+    Appeared { base: usize, size: usize },
+}
+
+impl Mismatch {
+    /// Whether nothing is restored because of it, unless forced
+    pub fn blocks(&self) -> bool {
+        !matches!(self, Mismatch::Appeared { .. })
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::Moved { path, from, to } => write!(f, "{} moved from {:X} to {:X}", path, from, to),
+            Mismatch::Resized { base, from, to } => write!(f, "region at {:X} changed size from {:X} to {:X}", base, from, to),
+            Mismatch::Missing { base, size } => write!(f, "region {:X}-{:X} is gone", base, base + size),
+            Mismatch::Appeared { base, size } => write!(f, "region {:X}-{:X} is new, it's left as it is", base, base + size),
+        }
+    }
+}
+
+fn check_region(saved: &Mapping, current: &[Mapping]) -> Option<Mismatch> {
+    let region = &saved.region;
+    if current.iter().any(|m| m.region.base == region.base && m.region.size == region.size) {
+        return None;
+    }
+    if let Some(resized) = current.iter().find(|m| m.region.base == region.base) {
+        return Some(Mismatch::Resized { base: region.base, from: region.size, to: resized.region.size });
+    }
+    match current.iter().find(|m| !saved.path.is_empty() && m.path == saved.path && m.region.size == region.size) {
+        Some(moved) => Some(Mismatch::Moved { path: saved.path.clone(), from: region.base, to: moved.region.base }),
+        None => Some(Mismatch::Missing { base: region.base, size: region.size }),
+    }
+}
+
+// Current regions no saved one starts at or moved to
+fn new_regions(saved: &[Mapping], current: &[Mapping]) -> Vec<Mismatch> {
+    current.iter()
+        .filter(|m| !saved.iter().any(|s| s.region.base == m.region.base
+            || (!s.path.is_empty() && s.path == m.path && s.region.size == m.region.size)))
+        .map(|m| Mismatch::Appeared { base: m.region.base, size: m.region.size })
+        .collect()
+}
+
+
+/// What restore_state wrote back and which regions it couldn't
+pub struct Restored {
+    pub regions: usize,
+    pub bytes: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// Writes the regions of a savestate back into pid
+///
+/// If any region moved, changed size or is gone nothing is written, unless force is set: then the regions
+/// still in place are restored. Regions that appeared since are reported but don't stop the restore. The
+/// mismatches are returned either way.
+pub fn restore_state(pid: u32, state: &Snapshot, force: bool, mut progress: impl FnMut(f64)) -> io::Result<Restored> {
+    let _suspended = Suspended::new(pid)?;
+    let process = Process::open(pid)?;
+
+    let current = enum_mappings(pid).into_iter().filter(|m| is_private(&m.region)).collect::<Vec<_>>();
+    let mut mismatches = vec![];
+    let intact = state.regions.iter().map(|mapping| match check_region(mapping, &current) {
+        Some(mismatch) => {
+            mismatches.push(mismatch);
+            false
+        },
+        None => true
+    }).collect::<Vec<_>>();
+
+    mismatches.extend(new_regions(&state.regions, &current));
+
+    let mut restored = Restored { regions: 0, bytes: 0, mismatches: mismatches };
+    if restored.mismatches.iter().any(Mismatch::blocks) && !force {
+        return Ok(restored);
+    }
+
+    let total = state.regions.iter().zip(intact.iter()).filter(|(_, &intact)| intact).map(|(m, _)| m.region.size).sum::<usize>() as f64;
+    for (region, intact) in state.read_regions()?.zip(intact) {
+        let region = region?;
+        if !intact {
+            continue;
+        }
+        let base = region.mapping.region.base;
+        process.write(base, &region.data)
+            .map_err(|e| io::Error::new(e.kind(), format!("can't write region {:X}-{:X}: {}", base, base + region.data.len(), e)))?;
+
+        restored.regions += 1;
+        restored.bytes += region.data.len();
+        progress(restored.bytes as f64 / total);
+    }
+
+    Ok(restored)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(base: usize, size: usize, path: &str) -> Mapping {
+        let region = Region { base: base, size: size, readable: true, writable: true, executable: false, shared: false };
+        Mapping { region: region, offset: 0, path: path.to_string() }
+    }
+
+    #[test]
+    fn region_in_place() {
+        let current = [mapping(0x1000, 0x1000, ""), mapping(0x5000, 0x2000, "/lib/libc.so")];
+        assert!(check_region(&mapping(0x5000, 0x2000, "/lib/libc.so"), &current).is_none());
+        assert!(check_region(&mapping(0x1000, 0x1000, "[heap]"), &current).is_none());
+    }
+
+    #[test]
+    fn region_resized() {
+        let current = [mapping(0x1000, 0x3000, "[heap]")];
+        assert!(matches!(check_region(&mapping(0x1000, 0x1000, "[heap]"), &current),
+            Some(Mismatch::Resized { base: 0x1000, from: 0x1000, to: 0x3000 })));
+    }
+
+    #[test]
+    fn region_moved() {
+        let current = [mapping(0x9000, 0x2000, "/lib/libc.so")];
+        match check_region(&mapping(0x5000, 0x2000, "/lib/libc.so"), &current) {
+            Some(Mismatch::Moved { path, from, to }) => assert_eq!((path.as_str(), from, to), ("/lib/libc.so", 0x5000, 0x9000)),
+            _ => panic!("expected Moved")
+        }
+    }
+
+    #[test]
+    fn region_missing() {
+        // Anonymous regions can't be found elsewhere, nor files mapped with another size
+        let current = [mapping(0x9000, 0x2000, ""), mapping(0xA000, 0x1000, "/lib/libc.so")];
+        assert!(matches!(check_region(&mapping(0x5000, 0x2000, ""), &current), Some(Mismatch::Missing { base: 0x5000, size: 0x2000 })));
+        assert!(matches!(check_region(&mapping(0x5000, 0x2000, "/lib/libc.so"), &current), Some(Mismatch::Missing { .. })));
+    }
+
+    #[test]
+    fn region_appeared() {
+        let saved = [mapping(0x1000, 0x1000, "[heap]"), mapping(0x5000, 0x2000, "/lib/libc.so")];
+        // Resized and moved regions are reported as such, not as new
+        let current = [mapping(0x1000, 0x3000, "[heap]"), mapping(0x9000, 0x2000, "/lib/libc.so"), mapping(0x20000, 0x1000, "")];
+        match new_regions(&saved, &current).as_slice() {
+            [mismatch @ Mismatch::Appeared { base: 0x20000, size: 0x1000 }] => assert!(!mismatch.blocks()),
+            _ => panic!("expected the region at 20000 only")
+        }
+        assert!(new_regions(&saved, &saved).is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+//! First scans, filters and updates of results in another process
+
+use std::{
+    convert::TryInto,
+    io,
+};
+
+use crate::mem::{Memory,Datatype};
+use crate::os::{check_process, enum_regions, Process};
+use crate::process::Region;
+
+/// Regions a first scan reads: the writable ones, or the parts of readable ones inside ranges
+pub fn scan_regions(pid : u32, ranges: Option<&[(usize, usize)]>) -> Vec<Region> {
+    enum_regions(pid).into_iter()
+        .flat_map(|r| match ranges {
+            Some(ranges) => ranges.iter().filter_map(|&(start, end)| {
+                let base = r.base.max(start);
+                let size = (r.base + r.size).min(end).checked_sub(base)?;
+                (r.readable && size > 0).then_some(Region { base: base, size: size, ..r })
+            }).collect::<Vec<_>>(),
+            None => (r.readable && r.writable).then_some(r).into_iter().collect()
+        })
+        .collect()
+}
+
+/// Ranges picked in the memory map replace the writable regions, read-only memory included
+pub fn scan_memory(pid : u32, target_bytes: &[u8], target_type: &Datatype, ranges: Option<Vec<(usize, usize)>>, mut progress: impl FnMut(f64)) -> io::Result<Memory> {
+    let mut results = Memory::new();
+    let num_bytes = target_bytes.len();
+    let process = Process::open(pid)?;
+
+    let pages = scan_regions(pid, ranges.as_deref());
+
+    let mut sweeped_memory : usize = 0;
+    let total_memory = pages.iter().map(|p| p.size).sum::<usize>() as f64;
+
+    for page in pages.iter() {
+        let mut buffer: Vec<u8> = vec![0; page.size];
+
+        if process.read(page.base, &mut buffer)
+        {
+            buffer.windows(num_bytes).enumerate().for_each(|(offset, window)| {
+                if window == target_bytes {
+                    results.push(page.base + offset, target_type, target_bytes);
+                }
+            });
+        }
+
+        sweeped_memory += page.size;
+        progress(sweeped_memory as f64 / total_memory);
+    }
+
+    Ok(results)
+}
+
+
+/// Keeps the entries of target_type still equal to target_bytes, returns true if the process exited meanwhile
+pub fn filter_memory(pid : u32, memory: &mut Memory, target_bytes: &[u8], target_type: &Datatype, mut progress: impl FnMut(f64)) -> io::Result<bool> {
+    let num_bytes = target_bytes.len();
+    let process = Process::open(pid)?;
+
+    let mut buffer: Vec<u8> = vec![0;num_bytes];
+    // Failed reads drop entries, unless the whole process is gone
+    let mut exited = false;
+
+    let mut sweeped_memory : usize = 0;
+    let total_memory = memory.len();
+    let progress_update_freq = std::cmp::max(total_memory, total_memory / 100);
+
+    macro_rules! filter_mem_type{
+        ($($a:ident).+,$b:ty)=>{
+            {
+                $($a).+.retain_mut(|l| {
+                    sweeped_memory += 1;
+                    if sweeped_memory % progress_update_freq == 0 {
+                        progress(sweeped_memory as f64 / total_memory as f64);
+                    }
+
+                    if exited {
+                        return true;
+                    }
+                    if !process.read(l.address, &mut buffer) {
+                        exited = !check_process(pid);
+                        return exited;
+                    }
+                    l.old_value = l.value;
+                    l.value = <$b>::from_ne_bytes(buffer.clone().try_into().unwrap());
+                    target_bytes == buffer
+                });
+            }
+        }
+    }
+
+    match *target_type {
+        Datatype::B1 => filter_mem_type![memory.mem_u8,u8],
+        Datatype::B1S => filter_mem_type![memory.mem_i8,i8],
+        Datatype::B2 => filter_mem_type![memory.mem_u16,u16],
+        Datatype::B2S => filter_mem_type![memory.mem_i16,i16],
+        Datatype::B4 => filter_mem_type![memory.mem_u32,u32],
+        Datatype::B4S => filter_mem_type![memory.mem_i32,i32],
+        Datatype::B8 => filter_mem_type![memory.mem_u64,u64],
+        Datatype::B8S => filter_mem_type![memory.mem_i64,i64],
+        Datatype::B16 => filter_mem_type![memory.mem_u128,u128],
+        Datatype::B16S => filter_mem_type![memory.mem_i128,i128],
+        Datatype::F => filter_mem_type![memory.mem_f32,f32],
+        Datatype::D => filter_mem_type![memory.mem_f64,f64],
+    }
+
+    Ok(exited)
+}
+
+
+/// Reads every entry again, returns true if the process exited meanwhile
+pub fn update_memory(pid : u32, memory: &mut Memory, mut progress: impl FnMut(f64)) -> io::Result<bool> {
+    let process = Process::open(pid)?;
+
+    let mut i : usize = 0;
+    let mut exited = false;
+    let memory_size = memory.len();
+    let progress_update_freq = std::cmp::max(memory_size, memory_size / 100);
+
+    macro_rules! update_mem_type{
+        ($($a:ident).+,$b:ty)=>{
+            {
+                let num_bytes = <$b>::default().to_ne_bytes().len();
+                let mut buffer: Vec<u8> = vec![0;num_bytes];
+
+                $($a).+.retain_mut(|l| {
+                    i += 1;
+                    if i % progress_update_freq == 0 {
+                        progress(i as f64 / memory_size as f64);
+                    }
+
+                    if exited {
+                        return true;
+                    }
+                    if !process.read(l.address, &mut buffer) {
+                        exited = !check_process(pid);
+                        return exited;
+                    }
+                    l.old_value = l.value;
+                    l.value = <$b>::from_ne_bytes(buffer.clone().try_into().unwrap());
+                    true
+                });
+            }
+        }
+    }
+
+    update_mem_type![memory.mem_u8,u8];
+    update_mem_type![memory.mem_i8,i8];
+    update_mem_type![memory.mem_u16,u16];
+    update_mem_type![memory.mem_i16,i16];
+    update_mem_type![memory.mem_u32,u32];
+    update_mem_type![memory.mem_i32,i32];
+    update_mem_type![memory.mem_u64,u64];
+    update_mem_type![memory.mem_i64,i64];
+    update_mem_type![memory.mem_u128,u128];
+    update_mem_type![memory.mem_i128,i128];
+    update_mem_type![memory.mem_f32,f32];
+    update_mem_type![memory.mem_f64,f64];
+
+    Ok(exited)
+}
@@ -0,0 +1,357 @@
+use std::{
+    cell::RefCell,
+    path::PathBuf,
+    rc::Rc,
+    sync::{atomic::{AtomicBool, Ordering}, mpsc, Arc},
+    thread,
+    time::Duration,
+};
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, NativeCallContext, Scope, INT};
+use tui_input::Input;
+
+use crate::expr::resolve_address;
+use crate::freeze::Freezer;
+use crate::mem::{Datatype, Memory};
+use crate::os::{check_process, read_process, write_process};
+use crate::proclist::find_by_name;
+use crate::scan::{filter_memory, scan_memory, update_memory};
+
+
+// Rhai functions for scan loops:
+//
+//   attach(pid or name)            pid()
+//   scan(type, value)              filter(value)  filter(type, value)  update()
+//   count()  results()  show()
+//   read(address, type)            write(address, type, value)
+//   freeze(address, type, value)   unfreeze(address)  unfreeze_all()
+//   sleep(ms)                      run(path)
+//
+// Addresses are numbers or expressions like "game+0x10", print goes to the log panel.
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+struct Session {
+    pid: u32,
+    memory: Memory,
+    datatype: Option<Datatype>,
+    freezer: Freezer,
+}
+
+impl Session {
+    fn process(&self) -> ScriptResult<u32> {
+        match self.pid {
+            0 => Err("no process attached, call attach first".into()),
+            pid => Ok(pid)
+        }
+    }
+
+    fn address(&self, address: &Dynamic) -> ScriptResult<usize> {
+        if let Ok(address) = address.as_int() {
+            return Ok(address as usize);
+        }
+        match address.clone().into_string() {
+            Ok(expr) => resolve_address(self.process()?, &expr).map_err(|e| format!("invalid address '{}': {}", expr, e).into()),
+            Err(_) => Err("an address is a number or an expression string".into())
+        }
+    }
+
+    fn read(&self, address: usize, datatype: &Datatype) -> ScriptResult<Dynamic> {
+        let mut buffer = vec![0; datatype.size()];
+        if !read_process(self.process()?, address, &mut buffer) {
+            return Err(format!("can't read {:X}", address).into());
+        }
+        Ok(to_dynamic(&datatype.format(&buffer, cfg!(target_endian = "big")).unwrap_or_default(), datatype))
+    }
+}
+
+
+fn datatype(name: &str) -> ScriptResult<Datatype> {
+    Datatype::from_name(name).ok_or_else(|| format!("unknown type '{}'", name).into())
+}
+
+fn to_bytes(value: &Dynamic, datatype: &Datatype) -> ScriptResult<Vec<u8>> {
+    datatype.parse(&value.to_string()).map_err(|e| format!("invalid {} '{}': {}", datatype.name(), value, e).into())
+}
+
+// Integers that don't fit a script integer stay strings
+fn to_dynamic(value: &str, datatype: &Datatype) -> Dynamic {
+    match datatype {
+        Datatype::F | Datatype::D => value.parse::<f64>().map(Dynamic::from_float).unwrap_or_else(|_| value.into()),
+        _ => value.parse::<INT>().map(Dynamic::from_int).unwrap_or_else(|_| value.into())
+    }
+}
+
+fn count(memory: &Memory) -> INT {
+    memory.len() as INT
+}
+
+
+// Script functions work on session, results handed to show() go to publish
+fn engine(session: Rc<RefCell<Session>>, stop: Arc<AtomicBool>, print: impl Fn(&str) + 'static, publish: impl Fn(&Memory) + 'static) -> Engine {
+    let mut engine = Engine::new();
+    let print = Rc::new(print);
+
+    let output = Rc::clone(&print);
+    engine.on_print(move |text| output(text));
+    let output = Rc::clone(&print);
+    engine.on_debug(move |text, _, position| output(&format!("{} @ {}", text, position)));
+
+    let cancel = Arc::clone(&stop);
+    engine.on_progress(move |_| cancel.load(Ordering::Relaxed).then(|| Dynamic::from("stopped")));
+
+    let s = Rc::clone(&session);
+    engine.register_fn("attach", move |pid: INT| -> ScriptResult<INT> {
+        if !check_process(pid as u32) {
+            return Err(format!("process {} is not running or can't be opened", pid).into());
+        }
+        s.borrow_mut().pid = pid as u32;
+        Ok(pid)
+    });
+    let s = Rc::clone(&session);
+    engine.register_fn("attach", move |name: &str| -> ScriptResult<INT> {
+        let pid = find_by_name(name).map(|p| p.pid).ok_or_else(|| format!("{} is not running", name))?;
+        s.borrow_mut().pid = pid;
+        Ok(pid as INT)
+    });
+    let s = Rc::clone(&session);
+    engine.register_fn("pid", move || s.borrow().pid as INT);
+
+    let s = Rc::clone(&session);
+    engine.register_fn("scan", move |name: &str, value: Dynamic| -> ScriptResult<INT> {
+        let mut session = s.borrow_mut();
+        let datatype = datatype(name)?;
+        let bytes = to_bytes(&value, &datatype)?;
+        session.memory = scan_memory(session.process()?, &bytes, &datatype, None, |_| {}).map_err(|e| e.to_string())?;
+        session.datatype = Some(datatype);
+        Ok(count(&session.memory))
+    });
+    let s = Rc::clone(&session);
+    let filter = move |datatype: Datatype, value: Dynamic| -> ScriptResult<INT> {
+        let mut session = s.borrow_mut();
+        let bytes = to_bytes(&value, &datatype)?;
+        let pid = session.process()?;
+        if filter_memory(pid, &mut session.memory, &bytes, &datatype, |_| {}).map_err(|e| e.to_string())? {
+            return Err(format!("process {} exited", pid).into());
+        }
+        Ok(count(&session.memory))
+    };
+    let f = filter.clone();
+    let s = Rc::clone(&session);
+    engine.register_fn("filter", move |value: Dynamic| -> ScriptResult<INT> {
+        let datatype = s.borrow().datatype.ok_or("nothing to filter, call scan first")?;
+        f(datatype, value)
+    });
+    engine.register_fn("filter", move |name: &str, value: Dynamic| filter(datatype(name)?, value));
+    let s = Rc::clone(&session);
+    engine.register_fn("update", move || -> ScriptResult<INT> {
+        let mut session = s.borrow_mut();
+        let pid = session.process()?;
+        if update_memory(pid, &mut session.memory, |_| {}).map_err(|e| e.to_string())? {
+            return Err(format!("process {} exited", pid).into());
+        }
+        Ok(count(&session.memory))
+    });
+
+    let s = Rc::clone(&session);
+    engine.register_fn("count", move || count(&s.borrow().memory));
+    let s = Rc::clone(&session);
+    engine.register_fn("results", move || -> Array {
+        s.borrow().memory.iter().map(|[location, value, old_value]| {
+            let (address, name) = location.split_once(':').unwrap_or((&location, "u8"));
+            let datatype = Datatype::from_name(name).unwrap_or(Datatype::B1);
+            let mut result = Map::new();
+            result.insert("address".into(), Dynamic::from_int(INT::from_str_radix(address, 16).unwrap_or_default()));
+            result.insert("type".into(), name.into());
+            result.insert("value".into(), to_dynamic(&value, &datatype));
+            result.insert("old_value".into(), to_dynamic(&old_value, &datatype));
+            Dynamic::from_map(result)
+        }).collect()
+    });
+    let s = Rc::clone(&session);
+    engine.register_fn("show", move || publish(&s.borrow().memory));
+
+    let s = Rc::clone(&session);
+    engine.register_fn("read", move |address: Dynamic, name: &str| -> ScriptResult<Dynamic> {
+        let session = s.borrow();
+        session.read(session.address(&address)?, &datatype(name)?)
+    });
+    let s = Rc::clone(&session);
+    engine.register_fn("write", move |address: Dynamic, name: &str, value: Dynamic| -> ScriptResult<()> {
+        let session = s.borrow();
+        let (address, datatype) = (session.address(&address)?, datatype(name)?);
+        write_process(session.process()?, address, &to_bytes(&value, &datatype)?)
+            .map_err(|e| format!("can't write {:X}: {}", address, e).into())
+    });
+    let s = Rc::clone(&session);
+    engine.register_fn("freeze", move |address: Dynamic, name: &str, value: Dynamic| -> ScriptResult<()> {
+        let session = s.borrow();
+        let (address, datatype) = (session.address(&address)?, datatype(name)?);
+        let bytes = to_bytes(&value, &datatype)?;
+        // Fails now rather than silently in the freezer
+        write_process(session.process()?, address, &bytes).map_err(|e| format!("can't write {:X}: {}", address, e))?;
+        session.freezer.freeze(session.pid, address, bytes);
+        Ok(())
+    });
+    let s = Rc::clone(&session);
+    engine.register_fn("unfreeze", move |address: Dynamic| -> ScriptResult<bool> {
+        let session = s.borrow();
+        Ok(session.freezer.unfreeze(session.pid, session.address(&address)?))
+    });
+    let s = Rc::clone(&session);
+    engine.register_fn("unfreeze_all", move || s.borrow().freezer.unfreeze_process(s.borrow().pid) as INT);
+
+    let cancel = Arc::clone(&stop);
+    engine.register_fn("sleep", move |ms: INT| -> ScriptResult<()> {
+        let mut left = Duration::from_millis(ms.max(0) as u64);
+        while !left.is_zero() {
+            if cancel.load(Ordering::Relaxed) {
+                return Err("stopped".into());
+            }
+            let step = left.min(Duration::from_millis(50));
+            thread::sleep(step);
+            left -= step;
+        }
+        Ok(())
+    });
+    engine.register_fn("run", |context: NativeCallContext, path: &str| -> ScriptResult<()> {
+        context.engine().run_file(PathBuf::from(path))
+    });
+
+    engine
+}
+
+
+// Runs a script file outside the TUI, printing to stdout
+pub fn run_file(path: &str, pid: u32) -> Result<(), String> {
+    let freezer = Freezer::new();
+    let session = Rc::new(RefCell::new(Session { pid: pid, memory: Memory::new(), datatype: None, freezer: freezer.clone() }));
+    let engine = engine(session, Arc::new(AtomicBool::new(false)),
+        |text| println!("{}", text),
+        |memory| for [location, value, _] in memory.iter() { println!("{} = {}", location, value) });
+
+    engine.run_file(PathBuf::from(path)).map_err(|e| e.to_string())?;
+
+    if freezer.len() > 0 {
+        eprintln!("Holding {} frozen values, press Ctrl-C to stop.", freezer.len());
+        while freezer.len() > 0 {
+            thread::sleep(Duration::from_millis(500));
+        }
+    }
+    Ok(())
+}
+
+
+enum Request {
+    Run(String),
+    Attach(u32),
+}
+
+// Console pane of the TUI, scripts run one at a time on a thread of their own
+pub struct Console {
+    pub input: Input,
+    history: Vec<String>,
+    browsing: Option<usize>,
+    requests: mpsc::Sender<Request>,
+    results: mpsc::Receiver<Memory>,
+    running: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Console {
+    pub fn start(pid: u32, freezer: Freezer) -> Console {
+        let (requests, requests_rx) = mpsc::channel();
+        let (results_tx, results) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(false));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let (running_flag, stop_flag) = (Arc::clone(&running), Arc::clone(&stop));
+        thread::spawn(move || {
+            let session = Rc::new(RefCell::new(Session { pid: pid, memory: Memory::new(), datatype: None, freezer: freezer }));
+            let engine = engine(Rc::clone(&session), Arc::clone(&stop_flag),
+                |text| log::info!(" {}", text),
+                move |memory| _ = results_tx.send(memory.clone()));
+            let mut scope = Scope::new();
+
+            for request in requests_rx {
+                match request {
+                    Request::Attach(pid) => session.borrow_mut().pid = pid,
+                    Request::Run(code) => {
+                        stop_flag.store(false, Ordering::Relaxed);
+                        running_flag.store(true, Ordering::Relaxed);
+                        if let Err(error) = engine.run_with_scope(&mut scope, &code) {
+                            log::error!(" Script error: {}", error);
+                        }
+                        running_flag.store(false, Ordering::Relaxed);
+                    }
+                }
+            }
+        });
+
+        Console {
+            input: Input::default(),
+            history: vec![],
+            browsing: None,
+            requests: requests,
+            results: results,
+            running: running,
+            stop: stop,
+        }
+    }
+
+    pub fn submit(&mut self) {
+        let code = self.input.value().trim().to_string();
+        self.input.reset();
+        self.browsing = None;
+        if code.is_empty() {
+            return;
+        }
+        if self.is_running() {
+            log::warn!(" A script is still running, this one runs after it.");
+        }
+        log::info!(" > {}", code);
+        self.history.push(code.clone());
+        _ = self.requests.send(Request::Run(code));
+    }
+
+    // Scripts attached with attach() keep their own process
+    pub fn attach(&self, pid: u32) {
+        _ = self.requests.send(Request::Attach(pid));
+    }
+
+    pub fn stop(&self) {
+        if self.is_running() {
+            self.stop.store(true, Ordering::Relaxed);
+            log::info!(" Stopping the script.");
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    // Latest results passed to show()
+    pub fn shown_results(&self) -> Option<Memory> {
+        self.results.try_iter().last()
+    }
+
+    pub fn previous(&mut self) {
+        if self.history.is_empty() { return; }
+        let i = self.browsing.map_or(self.history.len() - 1, |i| i.saturating_sub(1));
+        self.browsing = Some(i);
+        self.input = Input::new(self.history[i].clone());
+    }
+
+    pub fn next(&mut self) {
+        match self.browsing {
+            Some(i) if i + 1 < self.history.len() => {
+                self.browsing = Some(i + 1);
+                self.input = Input::new(self.history[i + 1].clone());
+            },
+            _ => {
+                self.browsing = None;
+                self.input.reset();
+            }
+        }
+    }
+}
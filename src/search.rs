@@ -0,0 +1,192 @@
+use std::{path::Path, sync::Arc};
+
+use tokio::sync::Mutex;
+
+use crate::app::App;
+use crate::mem::{Memory, Datatype};
+use crate::scan::{scan_memory, filter_memory, update_memory};
+use crate::snapshot::{save_snapshot, compare_snapshot, filter_snapshot, Change, Snapshot, Target};
+use crate::savestate::{save_state, restore_state, Mismatch};
+
+
+// Scans of the library run here with the results and progress kept in the App
+
+fn report_progress(app_mutex: &Arc<Mutex<App>>) -> impl FnMut(f64) + '_ {
+    move |progress| {
+        if let Ok(mut app) = app_mutex.try_lock() {
+            app.search_progress = progress;
+        }
+    }
+}
+
+
+pub async fn scan_process(pid : u32, target_bytes: &[u8], target_type: &Datatype, ranges: Option<Vec<(usize, usize)>>, app_mutex: Arc<Mutex<App>>) {
+    let mut app = app_mutex.lock().await;
+    app.memory = Memory::new();
+    drop(app);
+
+    match scan_memory(pid, target_bytes, target_type, ranges, report_progress(&app_mutex)) {
+        Ok(results) => {
+            let mut app = app_mutex.lock().await;
+            app.search_progress = 1f64;
+            app.memory = results;
+            log::info!(" First Scan found {} entries.", app.memory.len());
+        },
+        Err(error) => {
+            log::error!(" Can't open process {}: {}", pid, error);
+        }
+    }
+}
+
+
+pub async fn filter_process(pid : u32, target_bytes: &[u8], target_type: &Datatype, app_mutex: Arc<Mutex<App>>) {
+    let mut app = app_mutex.lock().await;
+    let mut memory = std::mem::take(&mut app.memory);
+    drop(app);
+
+    let result = filter_memory(pid, &mut memory, target_bytes, target_type, report_progress(&app_mutex));
+
+    let mut app = app_mutex.lock().await;
+    app.memory = memory;
+    match result {
+        Ok(exited) => {
+            app.search_progress = 1f64;
+            if exited {
+                log::warn!(" Process {} exited while filtering, unread entries were kept.", pid);
+            } else {
+                log::info!(" {} entries remaining after filtering.", app.memory.len());
+            }
+        },
+        Err(error) => {
+            log::error!(" Can't open process {}: {}", pid, error);
+        }
+    }
+}
+
+
+pub async fn update_process(app_mutex : Arc<Mutex<App>>) {
+    let mut app = app_mutex.lock().await;
+    let pid = app.selected_process;
+    let mut memory = std::mem::take(&mut app.memory);
+    drop(app);
+
+    let result = update_memory(pid, &mut memory, report_progress(&app_mutex));
+
+    let mut app = app_mutex.lock().await;
+    app.memory = memory;
+    match result {
+        Ok(exited) => {
+            app.search_progress = 1f64;
+            if exited {
+                log::warn!(" Process {} exited while updating, unread entries were kept.", pid);
+            }
+        },
+        Err(error) => {
+            log::error!(" Can't open process {}: {}", pid, error);
+        }
+    }
+}
+
+
+pub async fn snapshot_process(pid : u32, ranges: Option<Vec<(usize, usize)>>, path: &Path, app_mutex: Arc<Mutex<App>>) {
+    match save_snapshot(pid, ranges, path, report_progress(&app_mutex)) {
+        Ok((regions, bytes)) => {
+            app_mutex.lock().await.search_progress = 1f64;
+            log::info!(" Saved {} regions ({:.1} MB) to {}.", regions, bytes as f64 / 1048576f64, path.display());
+        },
+        Err(error) => {
+            log::error!(" Can't save a snapshot to {}: {}", path.display(), error);
+        }
+    }
+}
+
+
+// Against the second snapshot if there is one, the process as it is now otherwise
+pub async fn compare_process(pid : u32, old: &Path, new: Option<&Path>, target_type: &Datatype, change: &Change, filter: bool, app_mutex: Arc<Mutex<App>>) {
+    let load = |path: &Path| Snapshot::load(path).map_err(|e| log::error!(" Can't read snapshot {}: {}", path.display(), e)).ok();
+    let old_snapshot = match load(old) {
+        Some(snapshot) => snapshot,
+        None => return
+    };
+    let new_snapshot = match new {
+        Some(path) => match load(path) {
+            Some(snapshot) => Some(snapshot),
+            None => return
+        },
+        None => None
+    };
+    if new_snapshot.is_none() && old_snapshot.pid != pid {
+        log::warn!(" Snapshot {} was taken from process {}.", old.display(), old_snapshot.pid);
+    }
+    let target = match &new_snapshot {
+        Some(snapshot) => Target::Snapshot(snapshot),
+        None => Target::Process(pid),
+    };
+
+    let mut app = app_mutex.lock().await;
+    let mut memory = std::mem::take(&mut app.memory);
+    drop(app);
+
+    let result = if filter {
+        filter_snapshot(&mut memory, &old_snapshot, target, change, report_progress(&app_mutex))
+    } else {
+        compare_snapshot(&old_snapshot, target, target_type, change, report_progress(&app_mutex))
+            .map(|results| memory = results)
+    };
+
+    let mut app = app_mutex.lock().await;
+    app.memory = memory;
+    match result {
+        Ok(()) => {
+            app.search_progress = 1f64;
+            log::info!(" Comparison with {} ({}) left {} entries.", old.display(), change.name(), app.memory.len());
+        },
+        Err(error) => {
+            log::error!(" Can't open process {}: {}", pid, error);
+        }
+    }
+}
+
+
+pub async fn save_state_process(pid : u32, path: &Path, app_mutex: Arc<Mutex<App>>) {
+    match save_state(pid, path, report_progress(&app_mutex)) {
+        Ok((regions, bytes)) => {
+            app_mutex.lock().await.search_progress = 1f64;
+            log::info!(" Saved the state of process {} ({} regions, {:.1} MB) to {}.", pid, regions, bytes as f64 / 1048576f64, path.display());
+        },
+        Err(error) => {
+            log::error!(" Can't save the state of process {}: {}", pid, error);
+        }
+    }
+}
+
+
+// Nothing is forced from here, mismem restore --force can
+pub async fn restore_state_process(pid : u32, path: &Path, app_mutex: Arc<Mutex<App>>) {
+    let state = match Snapshot::load(path) {
+        Ok(state) => state,
+        Err(error) => {
+            log::error!(" Can't read savestate {}: {}", path.display(), error);
+            return;
+        }
+    };
+
+    match restore_state(pid, &state, false, report_progress(&app_mutex)) {
+        Ok(restored) if !restored.mismatches.iter().any(Mismatch::blocks) => {
+            for mismatch in restored.mismatches.iter() {
+                log::warn!(" {}", mismatch);
+            }
+            app_mutex.lock().await.search_progress = 1f64;
+            log::info!(" Restored {} regions ({:.1} MB) from {}.", restored.regions, restored.bytes as f64 / 1048576f64, path.display());
+        },
+        Ok(restored) => {
+            for mismatch in restored.mismatches.iter() {
+                log::warn!(" {}", mismatch);
+            }
+            log::error!(" Memory of process {} doesn't match {} anymore, nothing was restored.", pid, path.display());
+        },
+        Err(error) => {
+            log::error!(" Can't restore process {}: {}", pid, error);
+        }
+    }
+}
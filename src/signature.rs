@@ -0,0 +1,228 @@
+use std::fmt;
+
+use iced_x86::{Decoder, DecoderOptions, Instruction, OpKind, Register};
+
+use crate::os::{enum_modules, enum_regions, pointer_size, read_process};
+use crate::process::Module;
+
+
+// Array-of-bytes pattern, None is a wildcard
+pub struct Signature {
+    pub module: String,
+    pub offset: usize,
+    pub pattern: Vec<Option<u8>>,
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bytes = self.pattern.iter()
+            .map(|b| b.map(|b| format!("{:02X}", b)).unwrap_or_else(|| String::from("??")))
+            .collect::<Vec<_>>();
+        write!(f, "{}", bytes.join(" "))
+    }
+}
+
+
+// Readable parts of a module, adjacent regions are joined so matches can't cross a seam
+struct ModuleImage {
+    chunks: Vec<(usize, Vec<u8>)>,
+}
+
+impl ModuleImage {
+    fn read(pid: u32, module: &Module) -> ModuleImage {
+        let end = module.base + module.size;
+        let mut chunks: Vec<(usize, Vec<u8>)> = vec![];
+        for r in enum_regions(pid).into_iter().filter(|r| r.readable && r.base < end && module.base < r.base + r.size) {
+            let start = r.base.max(module.base);
+            let mut buffer = vec![0u8; (r.base + r.size).min(end) - start];
+            if !read_process(pid, start, &mut buffer) {
+                continue;
+            }
+            match chunks.last_mut() {
+                Some((base, bytes)) if *base + bytes.len() == start => bytes.extend(buffer),
+                _ => chunks.push((start, buffer))
+            }
+        }
+        ModuleImage { chunks: chunks }
+    }
+
+    // Bytes from address up to the end of its chunk
+    fn bytes_at(&self, address: usize) -> &[u8] {
+        match self.chunks.partition_point(|(base, _)| *base <= address).checked_sub(1) {
+            Some(i) => self.chunks[i].1.get(address - self.chunks[i].0..).unwrap_or_default(),
+            None => &[]
+        }
+    }
+
+    // Addresses where the whole pattern matches, a match can't run past the end of a chunk
+    fn find(&self, pattern: &[Option<u8>]) -> Vec<usize> {
+        self.chunks.iter().flat_map(|(base, bytes)| {
+            bytes.windows(pattern.len())
+                .enumerate()
+                .filter(|(_, window)| window.iter().zip(pattern).all(|(b, p)| p.is_none() || *p == Some(*b)))
+                .map(move |(i, _)| base + i)
+        }).collect()
+    }
+
+    // Longest prefix of pattern found anywhere but at address
+    fn longest_match_elsewhere(&self, address: usize, pattern: &[Option<u8>]) -> usize {
+        self.chunks.iter().flat_map(|(base, bytes)| {
+            (0..bytes.len()).filter(move |i| base + i != address).map(move |i| {
+                bytes[i..].iter().zip(pattern).take_while(|(b, p)| p.is_none() || **p == Some(**b)).count()
+            })
+        }).max().unwrap_or(0)
+    }
+}
+
+
+// Relocatable operands: rip-relative and absolute displacements, branch offsets
+// and immediates pointing back into the module
+fn wildcard_mask(bitness: u32, bytes: &[u8], address: usize, module: &Module) -> Vec<bool> {
+    let mut mask = vec![false; bytes.len()];
+    let mut decoder = Decoder::with_ip(bitness, bytes, address as u64, DecoderOptions::NONE);
+    let mut instr = Instruction::default();
+    let in_module = |value: u64| (module.base as u64) <= value && value < (module.base + module.size) as u64;
+
+    while decoder.can_decode() {
+        let start = decoder.position();
+        decoder.decode_out(&mut instr);
+        if instr.is_invalid() {
+            break;
+        }
+        let offsets = decoder.get_constant_offsets(&instr);
+
+        let absolute = instr.memory_base() == Register::None && instr.memory_index() == Register::None;
+        if offsets.has_displacement() && (instr.is_ip_rel_memory_operand() || absolute) {
+            let from = start + offsets.displacement_offset();
+            mask[from..from + offsets.displacement_size()].iter_mut().for_each(|m| *m = true);
+        }
+
+        if offsets.has_immediate() {
+            let branch = (0..instr.op_count()).any(|i| matches!(instr.op_kind(i), OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64));
+            let pointer = (0..instr.op_count()).any(|i| matches!(instr.op_kind(i), OpKind::Immediate32 | OpKind::Immediate32to64 | OpKind::Immediate64)
+                && in_module(instr.immediate(i)));
+            if branch || pointer {
+                let from = start + offsets.immediate_offset();
+                mask[from..from + offsets.immediate_size()].iter_mut().for_each(|m| *m = true);
+            }
+        }
+    }
+    mask
+}
+
+
+// Shortest pattern starting at the address that matches only there within its module
+pub fn generate(pid: u32, address: usize) -> Result<Signature, String> {
+    const MAX_LEN: usize = 128;
+
+    let module = enum_modules(pid).into_iter()
+        .find(|m| m.base <= address && address < m.base + m.size)
+        .ok_or_else(|| format!("{:X} is not inside a module", address))?;
+    let image = ModuleImage::read(pid, &module);
+
+    let bytes = image.bytes_at(address);
+    let bytes = &bytes[..bytes.len().min(MAX_LEN)];
+    if bytes.is_empty() {
+        return Err(format!("{:X} is not readable", address));
+    }
+
+    let executable = enum_regions(pid).iter().any(|r| r.executable && r.contains(address));
+    let mask = if executable {
+        wildcard_mask(pointer_size(pid) as u32 * 8, bytes, address, &module)
+    } else {
+        vec![false; bytes.len()]
+    };
+    let full: Vec<Option<u8>> = bytes.iter().zip(mask).map(|(&b, wild)| (!wild).then_some(b)).collect();
+
+    let pattern = unique_prefix(&image, address, &full)?;
+    Ok(Signature { module: module.name, offset: address - module.base, pattern: pattern })
+}
+
+// One byte past the longest match elsewhere tells the address apart, unless it's a wildcard
+fn unique_prefix(image: &ModuleImage, address: usize, full: &[Option<u8>]) -> Result<Vec<Option<u8>>, String> {
+    let mut len = image.longest_match_elsewhere(address, full) + 1;
+    while full.get(len - 1).is_some_and(|b| b.is_none()) {
+        len += 1;
+    }
+    if len > full.len() {
+        return Err(format!("no unique signature within {} bytes", full.len()));
+    }
+
+    let pattern = full[..len].to_vec();
+    if image.find(&pattern) != [address] {
+        return Err(format!("no unique signature for {:X}", address));
+    }
+    Ok(pattern)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module() -> Module {
+        Module { name: String::from("game.exe"), base: 0x400000, size: 0x10000 }
+    }
+
+    fn masked(bytes: &[u8]) -> Vec<usize> {
+        wildcard_mask(64, bytes, 0x401000, &module()).iter().enumerate().filter(|(_, &m)| m).map(|(i, _)| i).collect()
+    }
+
+    #[test]
+    fn relocatable_operands() {
+        // lea rax, [rip+0x10]
+        assert_eq!(masked(&[0x48, 0x8D, 0x05, 0x10, 0x00, 0x00, 0x00]), [3, 4, 5, 6]);
+        // call rel32, jmp rel8
+        assert_eq!(masked(&[0xE8, 0x00, 0x01, 0x00, 0x00]), [1, 2, 3, 4]);
+        assert_eq!(masked(&[0xEB, 0x05]), [1]);
+        // mov eax, 0x401000 points into the module
+        assert_eq!(masked(&[0xB8, 0x00, 0x10, 0x40, 0x00]), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn fixed_operands() {
+        // mov eax, 1; add rsp, 0x28; mov rax, [rbx+8]
+        assert!(masked(&[0xB8, 0x01, 0x00, 0x00, 0x00]).is_empty());
+        assert!(masked(&[0x48, 0x83, 0xC4, 0x28]).is_empty());
+        assert!(masked(&[0x48, 0x8B, 0x43, 0x08]).is_empty());
+    }
+
+    fn image(chunks: &[(usize, &[u8])]) -> ModuleImage {
+        ModuleImage { chunks: chunks.iter().map(|&(base, bytes)| (base, bytes.to_vec())).collect() }
+    }
+
+    fn exact(bytes: &[u8]) -> Vec<Option<u8>> {
+        bytes.iter().map(|&b| Some(b)).collect()
+    }
+
+    #[test]
+    fn shortest_unique_prefix() {
+        let image = image(&[(0x1000, &[1, 2, 3, 9, 1, 2, 3, 4, 5])]);
+        assert_eq!(unique_prefix(&image, 0x1004, &exact(&[1, 2, 3, 4, 5])).unwrap(), exact(&[1, 2, 3, 4]));
+        assert_eq!(unique_prefix(&image, 0x1003, &exact(&[9, 1, 2])).unwrap(), exact(&[9]));
+    }
+
+    #[test]
+    fn prefix_never_ends_in_wildcard() {
+        // The match at 0x2000 stops at the end of its chunk, right before the wildcard
+        let image = image(&[(0x1000, &[1, 2, 7, 5]), (0x2000, &[1, 2])]);
+        let full = vec![Some(1), Some(2), None, Some(5)];
+        assert_eq!(unique_prefix(&image, 0x1000, &full).unwrap(), full);
+    }
+
+    #[test]
+    fn repeated_bytes_have_no_signature() {
+        let repeated = image(&[(0x1000, &[1, 2, 3]), (0x2000, &[1, 2, 3])]);
+        assert!(unique_prefix(&repeated, 0x1000, &exact(&[1, 2, 3])).is_err());
+
+        // Every byte is a wildcard
+        let single = image(&[(0x1000, &[1, 2, 3])]);
+        assert!(unique_prefix(&single, 0x1000, &[None, None]).is_err());
+    }
+
+    #[test]
+    fn display_wildcards() {
+        let signature = Signature { module: String::from("game.exe"), offset: 0x10, pattern: vec![Some(0xE8), None, None, Some(0x0F)] };
+        assert_eq!(signature.to_string(), "E8 ?? ?? 0F");
+    }
+}
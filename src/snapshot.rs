@@ -0,0 +1,562 @@
+//! Memory of a process saved to a compressed file, and comparisons between two states of it
+//!
+//! [`save_snapshot`] writes the regions a first scan would read. [`compare_snapshot`] then looks for
+//! values that changed in a given way between a snapshot and a second one or the live process, and
+//! [`filter_snapshot`] does the same for existing results only.
+
+use std::{
+    convert::TryInto,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::mem::{Datatype, Location, Memory};
+use crate::os::{enum_mappings, enum_processes, Process};
+use crate::process::{Mapping, Region};
+use crate::scan::scan_regions;
+
+
+// A gzip stream of this header as a JSON line, then for every region a Mapping
+// as a JSON line followed by its region.size bytes
+#[derive(Serialize, Deserialize)]
+struct Header {
+    format: String,
+    version: u32,
+    pid: u32,
+    name: String,
+    time: u64,
+}
+
+const FORMAT : &str = "mismem snapshot";
+const VERSION : u32 = 1;
+
+
+/// Memory of a process at one moment, as loaded by [`Snapshot::load`]
+///
+/// Only the mappings are kept in memory, the content of the regions stays in the file and is read one
+/// region at a time.
+pub struct Snapshot {
+    pub pid: u32,
+    pub name: String,
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    /// Sorted by address
+    pub regions: Vec<Mapping>,
+    path: PathBuf,
+}
+
+/// One region of a snapshot with its content
+pub struct SavedRegion {
+    pub mapping: Mapping,
+    pub data: Vec<u8>,
+}
+
+type Reader = BufReader<GzDecoder<BufReader<File>>>;
+
+impl Snapshot {
+    /// Reads the header and the mappings, and checks that every region is complete
+    pub fn load(path: &Path) -> io::Result<Snapshot> {
+        let (header, mut reader) = open(path)?;
+
+        let mut regions = vec![];
+        while let Some(mapping) = read_line::<Mapping>(&mut reader)? {
+            if mapping.region.base.checked_add(mapping.region.size).is_none() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "not a mismem snapshot: region past the end of the address space"));
+            }
+            let size = io::copy(&mut (&mut reader).take(mapping.region.size as u64), &mut io::sink())?;
+            check_size(&mapping, size as usize)?;
+            regions.push(mapping);
+        }
+
+        Ok(Snapshot { pid: header.pid, name: header.name, time: header.time, regions: regions, path: path.to_path_buf() })
+    }
+
+    /// Regions with their content in address order, read from the file one at a time
+    pub fn read_regions(&self) -> io::Result<Regions> {
+        open(&self.path).map(|(_, reader)| Regions { reader: reader })
+    }
+
+    /// Bytes of memory saved
+    pub fn size(&self) -> usize {
+        self.regions.iter().map(|m| m.region.size).sum()
+    }
+}
+
+fn open(path: &Path) -> io::Result<(Header, Reader)> {
+    let mut reader = BufReader::new(GzDecoder::new(BufReader::new(File::open(path)?)));
+    let invalid = |e: &str| io::Error::new(io::ErrorKind::InvalidData, format!("not a mismem snapshot: {}", e));
+
+    let header : Header = read_line(&mut reader)?
+        .ok_or_else(|| invalid("empty file"))?;
+    if header.format != FORMAT {
+        return Err(invalid("unknown format"));
+    }
+    if header.version > VERSION {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("snapshot version {} is newer than this mismem", header.version)));
+    }
+    Ok((header, reader))
+}
+
+fn check_size(mapping: &Mapping, size: usize) -> io::Result<()> {
+    if size != mapping.region.size {
+        let region = &mapping.region;
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("region {:X}-{:X} is cut short", region.base, region.base + region.size)));
+    }
+    Ok(())
+}
+
+fn read_line<T: DeserializeOwned>(reader: &mut impl BufRead) -> io::Result<Option<T>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    serde_json::from_str(&line).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+
+/// Iterator over the regions of a snapshot, from [`Snapshot::read_regions`]
+pub struct Regions {
+    reader: Reader,
+}
+
+impl Regions {
+    fn next_mapping(&mut self) -> io::Result<Option<Mapping>> {
+        read_line(&mut self.reader)
+    }
+
+    // The buffer grows with the bytes actually in the file, not with the size the mapping claims
+    fn data(&mut self, mapping: &Mapping) -> io::Result<Vec<u8>> {
+        let mut data = vec![];
+        (&mut self.reader).take(mapping.region.size as u64).read_to_end(&mut data)?;
+        check_size(mapping, data.len())?;
+        Ok(data)
+    }
+
+    fn skip(&mut self, mapping: &Mapping) -> io::Result<()> {
+        let size = io::copy(&mut (&mut self.reader).take(mapping.region.size as u64), &mut io::sink())?;
+        check_size(mapping, size as usize)
+    }
+}
+
+impl Iterator for Regions {
+    type Item = io::Result<SavedRegion>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mapping = match self.next_mapping() {
+            Ok(Some(mapping)) => mapping,
+            Ok(None) => return None,
+            Err(error) => return Some(Err(error)),
+        };
+        Some(self.data(&mapping).map(|data| SavedRegion { mapping: mapping, data: data }))
+    }
+}
+
+
+// Reads a snapshot at growing addresses, keeping only the regions the last read touched and the one after
+// them. Reading below the last address starts over from the beginning of the file.
+struct Cursor<'a> {
+    snapshot: &'a Snapshot,
+    regions: Option<Regions>,
+    window: Vec<SavedRegion>,
+    last: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(snapshot: &'a Snapshot) -> Cursor<'a> {
+        Cursor { snapshot: snapshot, regions: None, window: vec![], last: 0 }
+    }
+
+    // Fills buffer from address like os::read_process, false if any of it wasn't saved
+    fn read(&mut self, address: usize, buffer: &mut [u8]) -> io::Result<bool> {
+        if address < self.last {
+            self.regions = None;
+            self.window.clear();
+        }
+        self.last = address;
+        self.window.retain(|r| r.mapping.region.base + r.mapping.region.size > address);
+
+        let end = address.saturating_add(buffer.len());
+        while self.window.last().is_none_or(|r| r.mapping.region.base + r.mapping.region.size < end) {
+            let regions = match &mut self.regions {
+                Some(regions) => regions,
+                None => self.regions.insert(self.snapshot.read_regions()?),
+            };
+            let mapping = match regions.next_mapping()? {
+                Some(mapping) => mapping,
+                None => break
+            };
+            if mapping.region.base + mapping.region.size <= address {
+                regions.skip(&mapping)?;
+            } else {
+                let data = regions.data(&mapping)?;
+                self.window.push(SavedRegion { mapping: mapping, data: data });
+            }
+        }
+
+        let mut done = 0;
+        let mut regions = self.window.iter();
+        while done < buffer.len() {
+            let region = match regions.next() {
+                Some(region) if region.mapping.region.contains(address + done) => region,
+                _ => return Ok(false)
+            };
+            let offset = address + done - region.mapping.region.base;
+            let count = (buffer.len() - done).min(region.data.len() - offset);
+            buffer[done..done + count].copy_from_slice(&region.data[offset..offset + count]);
+            done += count;
+        }
+        Ok(true)
+    }
+}
+
+
+/// Saves the regions scan_memory would read with the same ranges, returns how many regions and bytes were saved
+///
+/// Regions that can't be read are left out. Nothing is kept in memory, so snapshots can be larger than
+/// what is free.
+pub fn save_snapshot(pid: u32, ranges: Option<Vec<(usize, usize)>>, path: &Path, progress: impl FnMut(f64)) -> io::Result<(usize, usize)> {
+    save_regions(pid, scan_regions(pid, ranges.as_deref()), path, progress)
+}
+
+// Also writes savestates, which pick other regions
+pub(crate) fn save_regions(pid: u32, mut regions: Vec<Region>, path: &Path, mut progress: impl FnMut(f64)) -> io::Result<(usize, usize)> {
+    // Snapshots are read at growing addresses, ranges may come in any order
+    regions.sort_by_key(|r| r.base);
+    let process = Process::open(pid)?;
+    let mappings = enum_mappings(pid);
+
+    let header = Header {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        pid: pid,
+        name: enum_processes().into_iter().find(|p| p.pid == pid).map(|p| p.name).unwrap_or_default(),
+        time: SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs()).unwrap_or_default(),
+    };
+    let mut writer = GzEncoder::new(BufWriter::new(File::create(path)?), Compression::fast());
+    write_line(&mut writer, &header)?;
+
+    let total = regions.iter().map(|r| r.size).sum::<usize>() as f64;
+    let mut sweeped = 0;
+    let mut saved = (0, 0);
+
+    for region in regions {
+        let mut buffer = vec![0; region.size];
+        sweeped += region.size;
+
+        if process.read(region.base, &mut buffer) {
+            // Ranges may cut a mapping, the file offset moves with its start
+            let backing = mappings.iter().find(|m| m.region.contains(region.base));
+            let mapping = Mapping {
+                offset: backing.map_or(0, |m| m.offset + (region.base - m.region.base) as u64),
+                path: backing.map(|m| m.path.clone()).unwrap_or_default(),
+                region: region,
+            };
+            write_line(&mut writer, &mapping)?;
+            writer.write_all(&buffer)?;
+            saved = (saved.0 + 1, saved.1 + buffer.len());
+        }
+
+        progress(sweeped as f64 / total);
+    }
+
+    writer.finish()?.flush()?;
+    Ok(saved)
+}
+
+fn write_line(writer: &mut impl Write, value: &impl Serialize) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, value)?;
+    writer.write_all(b"\n")
+}
+
+
+/// How a value must have changed between the old and the new state to be kept
+#[derive(Clone)]
+pub enum Change {
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+    /// The new value is these bytes, in native byte order
+    Equal(Vec<u8>),
+}
+
+impl Change {
+    pub const NAMES : [&'static str;4] = ["changed", "unchanged", "increased", "decreased"];
+
+    /// Parses the names in NAMES, Equal is built from the bytes of a value instead
+    pub fn from_name(name: &str) -> Option<Change> {
+        match name {
+            "changed" => Some(Change::Changed),
+            "unchanged" => Some(Change::Unchanged),
+            "increased" => Some(Change::Increased),
+            "decreased" => Some(Change::Decreased),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Change::Changed => "changed",
+            Change::Unchanged => "unchanged",
+            Change::Increased => "increased",
+            Change::Decreased => "decreased",
+            Change::Equal(_) => "equal",
+        }
+    }
+
+    fn holds<T: PartialOrd>(&self, old: T, new: T, old_bytes: &[u8], new_bytes: &[u8]) -> bool {
+        // Bytes, so that NaN is unchanged and -0.0 changed
+        match self {
+            Change::Changed => old_bytes != new_bytes,
+            Change::Unchanged => old_bytes == new_bytes,
+            Change::Increased => new > old,
+            Change::Decreased => new < old,
+            Change::Equal(bytes) => new_bytes == bytes.as_slice(),
+        }
+    }
+}
+
+
+/// Newer state of a comparison
+pub enum Target<'a> {
+    Snapshot(&'a Snapshot),
+    /// The process as it is now
+    Process(u32),
+}
+
+enum Source<'a> {
+    Snapshot(Box<Cursor<'a>>),
+    Process(Process),
+}
+
+impl Source<'_> {
+    fn open(target: Target<'_>) -> io::Result<Source<'_>> {
+        match target {
+            Target::Snapshot(snapshot) => Ok(Source::Snapshot(Box::new(Cursor::new(snapshot)))),
+            Target::Process(pid) => Process::open(pid).map(Source::Process),
+        }
+    }
+
+    fn read(&mut self, address: usize, buffer: &mut [u8]) -> io::Result<bool> {
+        match self {
+            Source::Snapshot(cursor) => cursor.read(address, buffer),
+            Source::Process(process) => Ok(process.read(address, buffer)),
+        }
+    }
+}
+
+
+/// Every value of target_type in old whose change to new holds, like a first scan
+///
+/// Values are compared at addresses aligned to their size. Regions missing from new are skipped.
+/// Results hold the new value and the old one as old_value.
+pub fn compare_snapshot(old: &Snapshot, new: Target<'_>, target_type: &Datatype, change: &Change, mut progress: impl FnMut(f64)) -> io::Result<Memory> {
+    let mut results = Memory::new();
+    let mut new = Source::open(new)?;
+
+    let total = old.size() as f64;
+    let mut sweeped = 0;
+
+    for region in old.read_regions()? {
+        let region = region?;
+        let base = region.mapping.region.base;
+        let mut buffer = vec![0; region.data.len()];
+        sweeped += region.data.len();
+
+        if new.read(base, &mut buffer)? {
+            compare_region(&mut results, base, &region.data, &buffer, target_type, change);
+        }
+        progress(sweeped as f64 / total);
+    }
+
+    Ok(results)
+}
+
+fn compare_region(results: &mut Memory, base: usize, old: &[u8], new: &[u8], target_type: &Datatype, change: &Change) {
+    macro_rules! compare_type{
+        ($t:ty,$mem:ident)=>{{
+            let size = target_type.size();
+            let mut offset = (size - base % size) % size;
+
+            while offset + size <= old.len() {
+                let (old_bytes, new_bytes) = (&old[offset..offset + size], &new[offset..offset + size]);
+                let old_value = <$t>::from_ne_bytes(old_bytes.try_into().unwrap());
+                let value = <$t>::from_ne_bytes(new_bytes.try_into().unwrap());
+
+                if change.holds(old_value, value, old_bytes, new_bytes) {
+                    results.$mem.push(Location::<$t>{address: base + offset, value: value, old_value: old_value});
+                }
+                offset += size;
+            }
+        }}
+    }
+
+    match *target_type {
+        Datatype::B1 => compare_type!(u8,mem_u8),
+        Datatype::B1S => compare_type!(i8,mem_i8),
+        Datatype::B2 => compare_type!(u16,mem_u16),
+        Datatype::B2S => compare_type!(i16,mem_i16),
+        Datatype::B4 => compare_type!(u32,mem_u32),
+        Datatype::B4S => compare_type!(i32,mem_i32),
+        Datatype::B8 => compare_type!(u64,mem_u64),
+        Datatype::B8S => compare_type!(i64,mem_i64),
+        Datatype::B16 => compare_type!(u128,mem_u128),
+        Datatype::B16S => compare_type!(i128,mem_i128),
+        Datatype::F => compare_type!(f32,mem_f32),
+        Datatype::D => compare_type!(f64,mem_f64),
+    }
+}
+
+
+/// Keeps the results whose change from old to new holds, like a filter
+///
+/// Results missing from either state are dropped, the others get the old value as old_value. If a snapshot
+/// can't be read the results are left partly filtered.
+pub fn filter_snapshot(memory: &mut Memory, old: &Snapshot, new: Target<'_>, change: &Change, mut progress: impl FnMut(f64)) -> io::Result<()> {
+    let mut old = Cursor::new(old);
+    let mut new = Source::open(new)?;
+    let mut error = None;
+
+    let mut i : usize = 0;
+    let memory_size = memory.len();
+    let progress_update_freq = std::cmp::max(memory_size, memory_size / 100);
+
+    macro_rules! filter_mem_type{
+        ($mem:ident,$t:ty)=>{{
+            let num_bytes = std::mem::size_of::<$t>();
+            let mut old_bytes: Vec<u8> = vec![0;num_bytes];
+            let mut new_bytes: Vec<u8> = vec![0;num_bytes];
+
+            memory.$mem.retain_mut(|l| {
+                i += 1;
+                if i % progress_update_freq == 0 {
+                    progress(i as f64 / memory_size as f64);
+                }
+
+                if error.is_some() {
+                    return false;
+                }
+                let found = match old.read(l.address, &mut old_bytes) {
+                    Ok(true) => new.read(l.address, &mut new_bytes),
+                    other => other
+                };
+                match found {
+                    Ok(true) => {},
+                    Ok(false) => return false,
+                    Err(e) => {
+                        error = Some(e);
+                        return false;
+                    }
+                }
+                l.old_value = <$t>::from_ne_bytes(old_bytes.clone().try_into().unwrap());
+                l.value = <$t>::from_ne_bytes(new_bytes.clone().try_into().unwrap());
+                change.holds(l.old_value, l.value, &old_bytes, &new_bytes)
+            });
+        }}
+    }
+
+    filter_mem_type![mem_u8,u8];
+    filter_mem_type![mem_i8,i8];
+    filter_mem_type![mem_u16,u16];
+    filter_mem_type![mem_i16,i16];
+    filter_mem_type![mem_u32,u32];
+    filter_mem_type![mem_i32,i32];
+    filter_mem_type![mem_u64,u64];
+    filter_mem_type![mem_i64,i64];
+    filter_mem_type![mem_u128,u128];
+    filter_mem_type![mem_i128,i128];
+    filter_mem_type![mem_f32,f32];
+    filter_mem_type![mem_f64,f64];
+
+    error.map_or(Ok(()), Err)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Writes a snapshot of these regions like save_regions and loads it back
+    fn save(name: &str, regions: &[(usize, Vec<u8>)]) -> Snapshot {
+        let path = std::env::temp_dir().join(format!("mismem-{}-{}", std::process::id(), name));
+        let mut writer = GzEncoder::new(File::create(&path).unwrap(), Compression::fast());
+        let header = Header { format: FORMAT.to_string(), version: VERSION, pid: 1, name: String::from("test"), time: 0 };
+        write_line(&mut writer, &header).unwrap();
+        for (base, data) in regions {
+            let region = Region { base: *base, size: data.len(), readable: true, writable: true, executable: false, shared: false };
+            write_line(&mut writer, &Mapping { region: region, offset: 0, path: String::new() }).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap();
+        Snapshot::load(&path).unwrap()
+    }
+
+    // 0x1000-0x1004 and 0x1004-0x1008 are adjacent, 0x2000-0x2004 comes after a gap
+    fn snapshot(name: &str) -> Snapshot {
+        save(name, &[(0x1000, vec![1, 2, 3, 4]), (0x1004, vec![5, 6, 7, 8]), (0x2000, vec![9, 10, 11, 12])])
+    }
+
+    #[test]
+    fn read_inside_region() {
+        let snapshot = snapshot("inside.snap");
+        let mut buffer = [0u8; 2];
+        assert!(Cursor::new(&snapshot).read(0x1001, &mut buffer).unwrap());
+        assert_eq!(buffer, [2, 3]);
+    }
+
+    #[test]
+    fn read_across_adjacent_regions() {
+        let snapshot = snapshot("adjacent.snap");
+        let mut buffer = [0u8; 6];
+        assert!(Cursor::new(&snapshot).read(0x1002, &mut buffer).unwrap());
+        assert_eq!(buffer, [3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn read_into_gap() {
+        let snapshot = snapshot("gap.snap");
+        let mut cursor = Cursor::new(&snapshot);
+        let mut buffer = [0u8; 4];
+        assert!(!cursor.read(0x1006, &mut buffer).unwrap());
+        assert!(!cursor.read(0x1FFE, &mut buffer).unwrap());
+        assert!(!cursor.read(0x3000, &mut buffer).unwrap());
+        // Going back starts over
+        assert!(cursor.read(0x2000, &mut buffer).unwrap());
+        assert_eq!(buffer, [9, 10, 11, 12]);
+        assert!(cursor.read(0x1000, &mut buffer).unwrap());
+        assert_eq!(buffer, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn sizes_bound_by_the_file() {
+        let path = std::env::temp_dir().join(format!("mismem-{}-huge.snap", std::process::id()));
+        let mut writer = GzEncoder::new(File::create(&path).unwrap(), Compression::fast());
+        let header = Header { format: FORMAT.to_string(), version: VERSION, pid: 1, name: String::new(), time: 0 };
+        write_line(&mut writer, &header).unwrap();
+        let region = Region { base: 0x1000, size: 1 << 60, readable: true, writable: true, executable: false, shared: false };
+        write_line(&mut writer, &Mapping { region: region, offset: 0, path: String::new() }).unwrap();
+        writer.write_all(&[1, 2, 3, 4]).unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(Snapshot::load(&path).err().unwrap().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn compare_two_snapshots() {
+        let old = snapshot("old.snap");
+        let new = save("new.snap", &[(0x1000, vec![0, 2, 9, 4]), (0x1004, vec![5, 6, 7, 8])]);
+
+        let results = compare_snapshot(&old, Target::Snapshot(&new), &Datatype::B1, &Change::Changed, |_| {}).unwrap();
+        assert_eq!(results.iter().collect::<Vec<_>>(), [[String::from("0000000000001000:u8"), String::from("0"), String::from("1")],
+                                                       [String::from("0000000000001002:u8"), String::from("9"), String::from("3")]]);
+
+        let results = compare_snapshot(&old, Target::Snapshot(&new), &Datatype::B2, &Change::Unchanged, |_| {}).unwrap();
+        assert_eq!(results.len(), 2);
+    }
+}
@@ -1,31 +1,46 @@
 use crate::app::App;
 use crate::app::AppState;
 use crate::app::EditState;
+use crate::hexview::{HexView, HexState};
+use crate::disasm::DisasmState;
+use crate::signature::Signature;
+use crate::proclist::{SortColumn, Branch, format_age};
+use crate::memmap::{MapColumn, backing};
+use crate::remote;
+use crate::coredump;
+use crate::snapshot::Change;
 
 use tui::Frame;
 use tui::backend::Backend;
 use tui::layout::{Layout,Constraint,Alignment,Direction,Rect};
 use tui::style::{Color,Modifier,Style};
 use tui::text::{Span, Spans, Text};
-use tui::widgets::{Table,Row,Cell,Block,Borders,Paragraph,Clear,Gauge,List,ListItem};
+use tui::widgets::{Table,Row,Cell,Block,Borders,Paragraph,Clear,Gauge,List,ListItem,Wrap};
 
+use tui_input::Input;
 use tui_logger::{TuiLoggerWidget,TuiLoggerLevelOutput};
 
 pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     match app.state {
         AppState::Home => {},
         AppState::SelectProcess => draw_select_process(f, app),
-        AppState::EditMemory => draw_edit_memory(f, app)
+        AppState::EditMemory => draw_edit_memory(f, app),
+        AppState::HexView => draw_hex_view(f, app),
+        AppState::Disassembly => draw_disassembly(f, app),
+        AppState::Watch => draw_watch(f, app),
+        AppState::Inspector => draw_inspector(f, app),
+        AppState::MemoryMap => draw_memory_map(f, app),
     };
 }
 
 fn draw_select_process<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     let size = f.size();
-    let main_height = size.height.checked_sub(4).unwrap_or_default();
+    let main_height = size.height.checked_sub(7).unwrap_or_default();
 
     let rects = Layout::default()
         .constraints([
             Constraint::Length(1),
+            Constraint::Length(3),
             Constraint::Length(main_height),
             Constraint::Length(1),
         ].as_ref())
@@ -33,59 +48,130 @@ fn draw_select_process<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         .split(size);
 
     // Top Messages
-    let msg = Text::from("Select a running process");
+    let msg = match &app.target {
+        Some(target) if target.waiting => Text::styled(format!("Waiting for {} to start...", target.describe()), Style::default().fg(Color::Yellow)),
+        _ => match (remote::address(), coredump::path()) {
+            (Some(address), _) => Text::from(format!("Select a running process on {}", address)),
+            (_, Some(path)) => Text::from(format!("Select the process saved in {}", path)),
+            _ => Text::from("Select a running process"),
+        },
+    };
     let top_message = Paragraph::new(msg).alignment(Alignment::Center);
     f.render_widget(top_message, rects[0]);
 
     // Help
-    let msg = vec![
-        Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
-        Span::raw(" quit | "),
-        Span::styled("u", Style::default().add_modifier(Modifier::BOLD)),
-        Span::raw(" update | "),
-        Span::styled("▲", Style::default().add_modifier(Modifier::BOLD)),
-        Span::raw("/"),
-        Span::styled("▼", Style::default().add_modifier(Modifier::BOLD)),
-        Span::raw(" navigate | "),
-        Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-        Span::raw(" select"),
-    ];
+    let mut msg = if app.processes.filtering {
+        vec![
+            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" clear filter | "),
+            Span::styled("▲", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw("/"),
+            Span::styled("▼", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" navigate | "),
+            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" select"),
+        ]
+    } else {
+        vec![
+            Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" quit | "),
+            Span::styled("u", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" update | "),
+            Span::styled("/", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" filter | "),
+            Span::styled("1", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw("-"),
+            Span::styled("5", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" sort | "),
+            Span::styled("t", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(if app.processes.tree { " list | " } else { " tree | " }),
+            Span::styled("▲", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw("/"),
+            Span::styled("▼", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" navigate | "),
+            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" select"),
+        ]
+    };
+    if app.processes.tree && !app.processes.filtering {
+        let at = msg.len() - 2;
+        msg.splice(at..at, [
+            Span::styled("◄", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw("/"),
+            Span::styled("►", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" collapse/expand | "),
+        ]);
+    }
     let msg = Text::from(Spans::from(msg));
     
     let help_message = Paragraph::new(msg).alignment(Alignment::Center);
-    f.render_widget(help_message, rects[2]);
+    f.render_widget(help_message, rects[3]);
+
+    // Filter
+    let width = rects[1].width.max(3) - 3;
+    let scroll = (app.processes.filter.cursor() as u16).max(width) - width;
+    let input = Paragraph::new(app.processes.filter.value())
+        .style(if app.processes.filtering && !app.show_popup {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        })
+        .scroll((0, scroll))
+        .block(Block::default().borders(Borders::ALL).title(" 🔎 Filter by name, PID or command line ").title_alignment(Alignment::Center));
+    f.render_widget(input, rects[1]);
+    if app.processes.filtering && !app.show_popup {
+        f.set_cursor(
+            rects[1].x + (app.processes.filter.cursor() as u16).min(width) + 1,
+            rects[1].y + 1,
+        )
+    }
 
     // Process List
     let selected_style = Style::default().add_modifier(Modifier::REVERSED);
-    let header_cells = ["PID", "Process Name", "Memory Usage [kB]"]
-        .iter()
-        .map( |h| Cell::from(*h) );
+    let header_cells = SortColumn::ALL.iter()
+        .map(|c| if *c == app.processes.sort {
+            format!("{} {}", c.name(), if app.processes.descending { "▼" } else { "▲" })
+        } else {
+            c.name().to_string()
+        })
+        .chain([String::from("Command Line")])
+        .map(Cell::from);
     let header = Row::new(header_cells)
         .style(Style::default().bg(Color::DarkGray).fg(Color::Black))
         .height(1)
         .bottom_margin(1);
-    let rows = app.processes.iter().map(|item| {
-        let height = item
-            .iter()
-            .map(|content| content.chars().filter(|c| *c == '\n').count())
-            .max()
-            .unwrap_or(0)
-            + 1;
-        let cells = item.iter().map(|c| Cell::from(c.clone()));
-        Row::new(cells).height(height as u16)
+    let rows = app.processes.iter().map(|(p, depth, branch)| {
+        let marker = match branch {
+            Branch::Leaf => if app.processes.tree { "  " } else { "" },
+            Branch::Expanded => "▾ ",
+            Branch::Collapsed => "▸ ",
+        };
+        Row::new([
+            p.pid.to_string(),
+            format!("{}{}{}", "  ".repeat(depth), marker, p.name),
+            p.user.clone(),
+            p.memory.to_string(),
+            format_age(p.start_time),
+            p.cmdline.clone(),
+        ])
     });
+    let title = format!("{} ({})", if app.processes.tree { "Process Tree" } else { "Process List" }, app.processes.len());
+    let widths = [
+        Constraint::Length(8),
+        Constraint::Length(if app.processes.tree { 36 } else { 24 }),
+        Constraint::Length(14),
+        Constraint::Length(14),
+        Constraint::Length(10),
+        Constraint::Min(20),
+    ];
     let t = Table::new(rows)
         .header(header)
         .column_spacing(1)
-        .block(Block::default().borders(Borders::ALL).title("Process List"))
+        .block(Block::default().borders(Borders::ALL).title(title))
         .highlight_style(selected_style)
         .highlight_symbol(">> ")
-        .widths(&[
-            Constraint::Length(8),
-            Constraint::Percentage(60),
-            Constraint::Percentage(40),
-        ]);
-    f.render_stateful_widget(t, rects[1], &mut app.table_state);
+        .widths(&widths);
+    f.render_stateful_widget(t, rects[2], &mut app.table_state);
 
     // Error Popup
     if app.show_popup {
@@ -124,10 +210,43 @@ fn draw_edit_memory<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         .split(f.size());
 
     // Top Messages
-    let msg = vec![
+    let mut msg = vec![
         Span::raw("Process "),
         Span::raw(app.selected_process.to_string()),
     ];
+    if let Some(address) = remote::address() {
+        msg.push(Span::raw(format!(" on {}", address)));
+    }
+    if let Some(path) = coredump::path() {
+        msg.push(Span::styled(format!(" from core file {} (read-only)", path), Style::default().fg(Color::Yellow)));
+    }
+    if let Some(ranges) = app.memory_map.scan_ranges() {
+        msg.push(Span::styled(format!(" (scanning {} selected regions)", ranges.len()), Style::default().fg(Color::Yellow)));
+    }
+    if let Some(launched) = &app.launched {
+        if let Some(exit) = &launched.exit {
+            msg.push(Span::styled(format!(" (exited: {})", exit), Style::default().fg(Color::Red)));
+        } else if launched.held {
+            msg.push(Span::styled(" (held at entry, c to run)", Style::default().fg(Color::Yellow)));
+        }
+    }
+    let frozen = app.freezer.len();
+    if frozen > 0 {
+        msg.push(Span::styled(format!(" ({} frozen, z to release)", frozen), Style::default().fg(Color::Cyan)));
+    }
+    if app.script_running() {
+        msg.push(Span::styled(" (script running, X to stop)", Style::default().fg(Color::Yellow)));
+    }
+    if let Some(exited) = &app.exited {
+        let next = match &app.target {
+            Some(target) if target.waiting => format!("waiting for {} to restart...", target.describe()),
+            _ => String::from("press R to reattach to a new instance"),
+        };
+        msg = vec![Span::styled(
+            format!("Process {} ({}) exited, results are read-only, {}", exited.pid, exited.name, next),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+        )];
+    }
     let msg = Text::from(Spans::from(msg));
     
     let top_message = Paragraph::new(msg).alignment(Alignment::Center);
@@ -136,7 +255,7 @@ fn draw_edit_memory<B: Backend>(f: &mut Frame<B>, app: &mut App) {
 
 
     // Help
-    let msg = vec![
+    let mut msg = vec![
         Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
         Span::raw(" quit | "),
         Span::styled("u", Style::default().add_modifier(Modifier::BOLD)),
@@ -147,9 +266,47 @@ fn draw_edit_memory<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         Span::raw("/"),
         Span::styled("▼", Style::default().add_modifier(Modifier::BOLD)),
         Span::raw(" navigate | "),
-        Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-        Span::raw(" select"),
+        Span::styled("a", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" add address | "),
+        Span::styled("e", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw("/"),
+        Span::styled("o", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" export/import | "),
+        Span::styled("S", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw("/"),
+        Span::styled("C", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" snapshot/compare | "),
+        Span::styled("k", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw("/"),
+        Span::styled("l", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" save/load state | "),
+        Span::styled("h", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" hex view | "),
+        Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" disassemble | "),
+        Span::styled("w", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw("/"),
+        Span::styled("r", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" find writes/accesses | "),
+        Span::styled("p", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" process info | "),
+        Span::styled("v", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" memory map | "),
+        Span::styled("f", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" freeze | "),
+        Span::styled("x", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" script | "),
     ];
+    if app.launched.as_ref().is_some_and(|l| l.held) {
+        msg.push(Span::styled("c", Style::default().add_modifier(Modifier::BOLD)));
+        msg.push(Span::raw(" run | "));
+    }
+    if app.exited.is_some() && !app.target.as_ref().is_some_and(|t| t.waiting) {
+        msg.push(Span::styled("R", Style::default().add_modifier(Modifier::BOLD)));
+        msg.push(Span::raw(" reattach | "));
+    }
+    msg.push(Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)));
+    msg.push(Span::raw(" select"));
     let msg = Text::from(Spans::from(msg));
     
     let help_message = Paragraph::new(msg).alignment(Alignment::Center);
@@ -181,8 +338,16 @@ fn draw_edit_memory<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     let num_rows_to_load = (app.table_state.selected().unwrap_or(0) / EAGER_CHUNK_SIZE + 2) * EAGER_CHUNK_SIZE;
     
     let rows = app.memory.iter().take(num_rows_to_load).map(|item| {
+        // Frozen entries are marked after their address
+        let frozen = item[0].split_once(':')
+            .and_then(|(address, _)| usize::from_str_radix(address, 16).ok())
+            .is_some_and(|address| app.freezer.is_frozen(app.selected_process, address));
         let cells = item.iter().map(|c| Cell::from(c.clone()));
-        Row::new(cells)
+        if frozen {
+            Row::new(cells.chain([Cell::from("❄")])).style(Style::default().fg(Color::Cyan))
+        } else {
+            Row::new(cells)
+        }
     });
    
     let t = Table::new(rows)
@@ -199,8 +364,9 @@ fn draw_edit_memory<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         .highlight_symbol(">> ")
         .widths(&[
             Constraint::Percentage(40),
-            Constraint::Percentage(30),
-            Constraint::Percentage(30),
+            Constraint::Percentage(28),
+            Constraint::Percentage(28),
+            Constraint::Length(2),
         ]);
     f.render_stateful_widget(t, rects[0], &mut app.table_state);
 
@@ -270,7 +436,52 @@ fn draw_edit_memory<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         .output_file(false)
         .output_line(false)
         .style(Style::default().fg(Color::White).bg(Color::Reset));
-    f.render_widget(tui_w, rects[3]);
+
+    // Script console under the logs, script output goes to the logs
+    let logs_area = match &app.console {
+        Some(console) => {
+            let rects = Layout::default()
+                .constraints([Constraint::Min(3), Constraint::Length(3)].as_ref())
+                .split(rects[3]);
+            let typing = matches!(app.edit_state, EditState::Script) && !app.show_popup;
+            let width = rects[1].width.max(3) - 3;
+            let scroll = (console.input.cursor() as u16).max(width) - width;
+            let input = Paragraph::new(console.input.value())
+                .style(if typing { Style::default().fg(Color::Yellow) } else { Style::default() })
+                .scroll((0, scroll))
+                .block(Block::default().borders(Borders::ALL).title(" 📝 Script (Enter run, ▲/▼ history, Esc leave) "));
+            f.render_widget(input, rects[1]);
+            if typing {
+                f.set_cursor(
+                    rects[1].x + (console.input.cursor() as u16).min(width) + 1,
+                    rects[1].y + 1,
+                )
+            }
+            rects[0]
+        },
+        None => rects[3]
+    };
+
+    // Output of a program started by mismem shares the space with the logs
+    if let Some(launched) = &app.launched {
+        let rects = Layout::default()
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(logs_area);
+        f.render_widget(tui_w, rects[0]);
+
+        let output = launched.output.lock().unwrap();
+        let height = rects[1].height.saturating_sub(2) as usize;
+        let lines : Vec<Spans> = output.iter().skip(output.len().saturating_sub(height))
+            .map(|line| Spans::from(Span::styled(line.text.clone(),
+                if line.stderr { Style::default().fg(Color::Red) } else { Style::default() }
+            )))
+            .collect();
+        let pane = Paragraph::new(lines)
+            .block(Block::default().title(format!(" 📺 Output of {} ", launched.command)).borders(Borders::ALL));
+        f.render_widget(pane, rects[1]);
+    } else {
+        f.render_widget(tui_w, logs_area);
+    }
 
     // Search Settings
     let rects = Layout::default()
@@ -307,72 +518,836 @@ fn draw_edit_memory<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     
 
     // Input Popup
-    if matches!(app.edit_state, EditState::Edit) {
-        let percent_x = 60;
-        let popup_layout = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints(
-                [
-                    Constraint::Percentage(50),
-                    Constraint::Length(3),
-                    Constraint::Percentage(40),
-                ]
-                .as_ref(),
-            )
-            .split(size);
-
-        let area = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(
-                [
-                    Constraint::Percentage((100 - percent_x) / 2),
-                    Constraint::Percentage(percent_x),
-                    Constraint::Percentage((100 - percent_x) / 2),
-                ]
-                .as_ref(),
-            )
-            .split(popup_layout[1])[1];
-        
+    if matches!(app.edit_state, EditState::Edit | EditState::Address | EditState::Export | EditState::Import | EditState::Snapshot | EditState::Compare |
+        EditState::SaveState | EditState::RestoreState) {
+        let title = match app.edit_state {
+            EditState::Address => String::from(" 📍 Address (e.g. [game.exe+0x1F0]+0x18) "),
+            EditState::Export => String::from(" 📤 Export Results to (.csv or .json) "),
+            EditState::Import => String::from(" 📥 Import Results from (.csv or .json) "),
+            EditState::Snapshot => String::from(" 📷 Save Snapshot of the Scanned Regions to "),
+            EditState::SaveState => String::from(" 💾 Save State (private writable memory) to "),
+            EditState::RestoreState => String::from(" ⏪ Restore State from "),
+            EditState::Compare => {
+                let values = match app.search_mode.selected() {
+                    Some(1) => "Results",
+                    _ => App::DATATYPE_OPTS[app.search_datatype.selected().unwrap_or(0)],
+                };
+                format!(" 🔍 {} {} since Snapshot (Tab), or OLD NEW ", values, Change::NAMES[app.change])
+            },
+            _ => format!(" 💉 New Value for {}",app.selected_address)
+        };
+        draw_input_popup(f, &app.mismem_input, title, !app.show_popup);
+    }
 
-        let width = area.width.max(3) - 3;
-        let scroll = (app.mismem_input.cursor() as u16).max(width) - width;
-        let input = Paragraph::new(app.mismem_input.value())
-            .style(Style::default().fg(Color::Yellow))
-            .scroll((0, scroll))
-            .block(Block::default().borders(Borders::ALL).title(format!(" 💉 New Value for {}",app.selected_address)).title_alignment(Alignment::Center));
+    // Error Popup
+    if app.show_popup {
+        draw_error_popup(f, &app.popup_error);
+    }
+    
+}
 
-        f.render_widget(Clear, area);
-        f.render_widget(input, area);
-        if !app.show_popup {
-            f.set_cursor(
-                area.x + (app.mismem_input.cursor() as u16).min(width) + 1,
-                area.y + 1,
-            );
+
+fn draw_hex_view<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let size = f.size();
+    let main_height = size.height.checked_sub(4).unwrap_or_default();
+
+    let rects = Layout::default()
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(main_height),
+            Constraint::Length(1),
+        ].as_ref())
+        .margin(1)
+        .split(size);
+
+    // Top Messages
+    let region = match app.hex_view.region() {
+        Some(r) => format!(" | Region {:016X}-{:016X} {}", r.base, r.base + r.size, r.permissions()),
+        None => String::from(" | Unmapped")
+    };
+    let msg = vec![
+        Span::raw("Process "),
+        Span::raw(app.selected_process.to_string()),
+        Span::raw(region),
+    ];
+    let msg = Text::from(Spans::from(msg));
+    let top_message = Paragraph::new(msg).alignment(Alignment::Center);
+    f.render_widget(top_message, rects[0]);
+
+    // Help
+    let mut msg = vec![
+        Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" quit | "),
+        Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(if matches!(app.hex_view.state, HexState::Edit) { " revert | " } else { " back | " }),
+        Span::styled("▲▼◄►", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" move | "),
+        Span::styled("PgUp", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw("/"),
+        Span::styled("PgDn", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" page | "),
+        Span::styled("Tab", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" hex/ascii | "),
+    ];
+    match app.hex_view.state {
+        HexState::Edit => msg.extend([
+            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" write"),
+        ]),
+        HexState::Inspect => msg.extend([
+            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" add to results | "),
+            Span::styled("i", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" close inspector"),
+        ]),
+        _ => msg.extend([
+            Span::styled("g", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" go to address | "),
+            Span::styled("e", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" edit | "),
+            Span::styled("i", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" inspect | "),
+            Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" disassemble | "),
+            Span::styled("s", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" signature | "),
+            Span::styled("w", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw("/"),
+            Span::styled("r", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" find writes/accesses"),
+        ]),
+    }
+    let msg = Text::from(Spans::from(msg));
+    let help_message = Paragraph::new(msg).alignment(Alignment::Center);
+    f.render_widget(help_message, rects[2]);
+
+    // Main Part
+    let rects = Layout::default()
+        .constraints([
+            Constraint::Length(88),
+            Constraint::Min(30)
+        ].as_ref())
+        .direction(Direction::Horizontal)
+        .split(rects[1]);
+
+    // Hex Dump
+    app.hex_view.rows = rects[0].height.saturating_sub(2).max(1) as usize;
+
+    let changed_style = Style::default().fg(Color::Black).bg(Color::Yellow);
+    let pending_style = Style::default().fg(Color::Black).bg(Color::Magenta);
+    let unreadable_style = Style::default().fg(Color::DarkGray);
+    let editing = matches!(app.hex_view.state, HexState::Edit);
+
+    let lines : Vec<Spans> = (0..app.hex_view.rows).map(|row| {
+        let offset = row * HexView::BYTES_PER_ROW;
+        let mut spans = vec![
+            Span::styled(format!("{:016X}  ", app.hex_view.address.wrapping_add(offset)), Style::default().fg(Color::Cyan))
+        ];
+
+        let mut ascii = vec![Span::raw(" |")];
+        for i in offset..offset + HexView::BYTES_PER_ROW {
+            let address = app.hex_view.address.wrapping_add(i);
+            let changed = app.hex_view.changed.get(i).copied().unwrap_or(false);
+            let pending = app.hex_view.pending.get(&address).copied();
+
+            let (hex, c, style) = match (pending, app.hex_view.bytes.get(i).copied().flatten()) {
+                (Some(b), _) | (None, Some(b)) => {
+                    let style = if pending.is_some() { 
+                        pending_style 
+                    } else if changed { 
+                        changed_style 
+                    } else { 
+                        Style::default() 
+                    };
+                    let c = if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' };
+                    (format!("{:02X}", b), c.to_string(), style)
+                },
+                (None, None) => (String::from("??"), String::from("?"), unreadable_style)
+            };
+
+            let (hex_style, ascii_style) = if address != app.hex_view.cursor {
+                (style, style)
+            } else {
+                let active = if editing { style.add_modifier(Modifier::REVERSED | Modifier::BOLD) } else { style.add_modifier(Modifier::REVERSED) };
+                let inactive = style.add_modifier(Modifier::UNDERLINED);
+                if app.hex_view.ascii { (inactive, active) } else { (active, inactive) }
+            };
+
+            spans.push(Span::styled(hex, hex_style));
+            ascii.push(Span::styled(c, ascii_style));
+            spans.push(Span::raw(if i % 8 == 7 { "  " } else { " " }));
         }
+        ascii.push(Span::raw("|"));
+
+        spans.extend(ascii);
+        Spans::from(spans)
+    }).collect();
+
+    let title = if editing {
+        format!(" ✏️ Editing {:016X} ({} pending) ", app.hex_view.cursor, app.hex_view.pending.len())
+    } else {
+        format!(" 🔬 Memory at {:016X} ", app.hex_view.cursor)
+    };
+    let dump = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title)
+            .style(Style::default().fg(Color::Yellow)))
+        .style(Style::default().fg(Color::White));
+    f.render_widget(dump, rects[0]);
+
+    // Data Inspector
+    let header_cells = ["Type", "Little Endian", "Big Endian"]
+        .iter()
+        .map( |h| Cell::from(*h) );
+    let header = Row::new(header_cells)
+        .style(Style::default().bg(Color::DarkGray).fg(Color::Black))
+        .height(1)
+        .bottom_margin(1);
+    // The selected column is underlined, it's the byte order Enter adds to results
+    let (selected, column) = (app.hex_view.inspector_state.selected(), if app.hex_view.big_endian { 2 } else { 1 });
+    let rows = app.hex_view.interpretations().into_iter().enumerate().map(|(i, item)| {
+        Row::new(item.into_iter().enumerate().map(|(j, text)| if Some(i) == selected && j == column {
+            Cell::from(text).style(Style::default().add_modifier(Modifier::UNDERLINED))
+        } else {
+            Cell::from(text)
+        }))
+    });
+    let t = Table::new(rows)
+        .header(header)
+        .column_spacing(1)
+        .block(Block::default().borders(Borders::ALL)
+            .title(" 🧮 Inspector ")
+            .style(if matches!(app.hex_view.state, HexState::Inspect) && !app.show_popup {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            }))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol(">> ")
+        .widths(&[
+            Constraint::Length(8),
+            Constraint::Percentage(50),
+            Constraint::Percentage(50),
+        ]);
+    f.render_stateful_widget(t, rects[1], &mut app.hex_view.inspector_state);
+
+    // Input Popup
+    if matches!(app.hex_view.state, HexState::Goto) {
+        draw_input_popup(f, &app.mismem_input, String::from(" 📍 Go to Address "), !app.show_popup);
     }
 
+    // Signature Popup
+    if let Some(signature) = &app.signature {
+        draw_signature_popup(f, signature);
+    }
 
     // Error Popup
     if app.show_popup {
-        let area = centered_rect(60, 20, size);
-        
-        let block = Block::default().title(" Error ").title_alignment(Alignment::Center).borders(Borders::ALL).style(Style::default().fg(Color::Yellow));
-        
-        let msg = Text::from(app.popup_error.clone());
-        let msg = Paragraph::new(msg).alignment(Alignment::Center);
-        
-        let rects = Layout::default()
-            .constraints([
-                Constraint::Percentage(50),
+        draw_error_popup(f, &app.popup_error);
+    }
+}
+
+
+fn draw_disassembly<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let size = f.size();
+    let main_height = size.height.checked_sub(4).unwrap_or_default();
+
+    let rects = Layout::default()
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(main_height),
+            Constraint::Length(1),
+        ].as_ref())
+        .margin(1)
+        .split(size);
+
+    // Top Messages
+    let mut msg = vec![
+        Span::raw("Process "),
+        Span::raw(app.selected_process.to_string()),
+    ];
+    if app.disassembly.debug.is_attached() {
+        msg.push(match app.disassembly.debug.thread {
+            Some(tid) => Span::styled(format!(" (stopped, thread {})", tid), Style::default().fg(Color::Red)),
+            None => Span::styled(" (running)", Style::default().fg(Color::Green)),
+        });
+    }
+    let msg = Text::from(Spans::from(msg));
+    let top_message = Paragraph::new(msg).alignment(Alignment::Center);
+    f.render_widget(top_message, rects[0]);
+
+    // Help
+    let mut msg = vec![
+        Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" quit | "),
+        Span::styled("◄", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" back | "),
+        Span::styled("▲", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw("/"),
+        Span::styled("▼", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" navigate | "),
+        Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" follow | "),
+        Span::styled("Backspace", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" return | "),
+        Span::styled("g", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" go to address | "),
+        Span::styled("s", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" signature | "),
+        Span::styled("n", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" nop | "),
+        Span::styled("p", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" patch | "),
+        Span::styled("r", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" restore | "),
+        Span::styled("b", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" breakpoint"),
+    ];
+    if app.disassembly.debug.is_attached() {
+        msg.extend([
+            Span::raw(" | "),
+            Span::styled("t", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" step | "),
+            Span::styled("c", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" continue | "),
+            Span::styled("Tab", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" registers | "),
+            Span::styled("x", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" detach"),
+        ]);
+    }
+    let msg = Text::from(Spans::from(msg));
+    let help_message = Paragraph::new(msg).alignment(Alignment::Center);
+    f.render_widget(help_message, rects[2]);
+
+    // Patch list is only shown once something has been patched
+    let patch_height = if app.disassembly.patches.patches.is_empty() {
+        0
+    } else {
+        (app.disassembly.patches.patches.len() as u16 + 2).min(rects[1].height / 3)
+    };
+    let chunks = Layout::default()
+        .constraints([Constraint::Min(0), Constraint::Length(patch_height)].as_ref())
+        .split(rects[1]);
+
+    // Registers are shown next to the code while the debugger is attached
+    let register_width = if app.disassembly.debug.is_attached() { 30 } else { 0 };
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(register_width)].as_ref())
+        .split(chunks[0]);
+
+    // Instructions
+    app.disassembly.rows = columns[0].height.saturating_sub(4).max(1) as usize;
+
+    let selected_style = Style::default().add_modifier(Modifier::REVERSED);
+    let header_cells = ["Address", "Bytes", "Instruction", ""]
+        .iter()
+        .map( |h| Cell::from(*h) );
+    let header = Row::new(header_cells)
+        .style(Style::default().bg(Color::DarkGray).fg(Color::Black))
+        .height(1)
+        .bottom_margin(1);
+    let debug = &app.disassembly.debug;
+    let rows = app.disassembly.lines.iter().map(|line| {
+        let bytes = line.bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+        let instruction = Spans::from(vec![
+            Span::styled(format!("{:<8}", line.mnemonic), Style::default().fg(if line.target.is_some() { Color::Cyan } else { Color::White })),
+            Span::raw(line.operands.clone()),
+        ]);
+        let address_style = if debug.breakpoints.contains_key(&line.address) {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default()
+        };
+        let row = Row::new(vec![
+            Cell::from(Span::styled(format!("{:016X}", line.address), address_style)),
+            Cell::from(bytes),
+            Cell::from(instruction),
+            Cell::from(Span::styled(line.annotation.clone(), Style::default().fg(Color::Green))),
+        ]);
+        if debug.position == Some(line.address) {
+            row.style(Style::default().bg(Color::DarkGray))
+        } else {
+            row
+        }
+    });
+    let t = Table::new(rows)
+        .header(header)
+        .column_spacing(2)
+        .block(Block::default().borders(Borders::ALL)
+            .title(" ⚙️ Disassembly ")
+            .style(Style::default().fg(Color::Yellow)))
+        .highlight_style(selected_style)
+        .highlight_symbol(">> ")
+        .widths(&[
+            Constraint::Length(16),
+            Constraint::Length(30),
+            Constraint::Percentage(50),
+            Constraint::Percentage(30),
+        ]);
+    f.render_stateful_widget(t, columns[0], &mut app.disassembly.table_state);
+
+    // Registers
+    if register_width > 0 {
+        let rows = app.disassembly.debug.registers.iter().map(|r| {
+            Row::new(vec![
+                Cell::from(r.name),
+                Cell::from(format!("{:016X}", r.value)),
+            ])
+        });
+        let focused = matches!(app.disassembly.state, DisasmState::Registers | DisasmState::EditRegister);
+        let t = Table::new(rows)
+            .column_spacing(1)
+            .block(Block::default().borders(Borders::ALL)
+                .title(" 🧮 Registers ")
+                .style(Style::default().fg(if focused { Color::Yellow } else { Color::White })))
+            .highlight_style(if focused { selected_style } else { Style::default() })
+            .widths(&[
+                Constraint::Length(6),
+                Constraint::Length(16),
+            ]);
+        f.render_stateful_widget(t, columns[1], &mut app.disassembly.debug.register_state);
+    }
+
+    // Patches
+    if patch_height > 0 {
+        let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+        let rows = app.disassembly.patches.patches.iter().map(|p| {
+            Row::new(vec![
+                Cell::from(format!("{:016X}", p.address)),
+                Cell::from(hex(&p.original)),
+                Cell::from(Span::styled(hex(&p.patched), Style::default().fg(Color::Magenta))),
+            ])
+        });
+        let t = Table::new(rows)
+            .column_spacing(2)
+            .block(Block::default().borders(Borders::ALL)
+                .title(" 🩹 Patches ")
+                .style(Style::default().fg(Color::Yellow)))
+            .widths(&[
+                Constraint::Length(16),
+                Constraint::Percentage(40),
+                Constraint::Percentage(40),
+            ]);
+        f.render_widget(t, chunks[1]);
+    }
+
+    // Input Popup
+    match app.disassembly.state {
+        DisasmState::Goto => draw_input_popup(f, &app.mismem_input, String::from(" 📍 Disassemble at "), !app.show_popup),
+        DisasmState::Patch => draw_input_popup(f, &app.mismem_input, String::from(" 🩹 Patch bytes (e.g. 90 90) "), !app.show_popup),
+        DisasmState::EditRegister => {
+            let name = app.disassembly.debug.selected_register().map(|r| r.name).unwrap_or_default();
+            draw_input_popup(f, &app.mismem_input, format!(" 🧮 Set {} ", name), !app.show_popup)
+        },
+        DisasmState::Browse | DisasmState::Registers => {}
+    }
+
+    // Signature Popup
+    if let Some(signature) = &app.signature {
+        draw_signature_popup(f, signature);
+    }
+
+    // Error Popup
+    if app.show_popup {
+        draw_error_popup(f, &app.popup_error);
+    }
+}
+
+
+fn draw_input_popup<B: Backend>(f: &mut Frame<B>, input: &Input, title: String, focused: bool) {
+    let percent_x = 60;
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
                 Constraint::Percentage(50),
-            ].as_ref())
-            .split(block.inner(area));
-            
-        f.render_widget(Clear, area);
-        f.render_widget(block, area);
-        f.render_widget(msg, rects[1]);
+                Constraint::Length(3),
+                Constraint::Percentage(40),
+            ]
+            .as_ref(),
+        )
+        .split(f.size());
+
+    let area = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(popup_layout[1])[1];
+
+    let width = area.width.max(3) - 3;
+    let scroll = (input.cursor() as u16).max(width) - width;
+    let paragraph = Paragraph::new(input.value())
+        .style(Style::default().fg(Color::Yellow))
+        .scroll((0, scroll))
+        .block(Block::default().borders(Borders::ALL).title(title).title_alignment(Alignment::Center));
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+    if focused {
+        f.set_cursor(
+            area.x + (input.cursor() as u16).min(width) + 1,
+            area.y + 1,
+        );
+    }
+}
+
+
+fn draw_watch<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let size = f.size();
+    let main_height = size.height.checked_sub(4).unwrap_or_default();
+
+    let rects = Layout::default()
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(main_height),
+            Constraint::Length(1),
+        ].as_ref())
+        .margin(1)
+        .split(size);
+
+    // Top Messages
+    let status = if app.watch.is_running() {
+        Span::styled(" (watching)", Style::default().fg(Color::Green))
+    } else {
+        Span::styled(" (stopped)", Style::default().fg(Color::Red))
+    };
+    let msg = vec![
+        Span::raw("What "),
+        Span::raw(app.watch.kind.name()),
+        Span::raw(format!(" {:X} ({} bytes)", app.watch.address, app.watch.len)),
+        status,
+    ];
+    let msg = Text::from(Spans::from(msg));
+    let top_message = Paragraph::new(msg).alignment(Alignment::Center);
+    f.render_widget(top_message, rects[0]);
+
+    // Help
+    let msg = vec![
+        Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" quit | "),
+        Span::styled("◄", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" stop and back | "),
+        Span::styled("▲", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw("/"),
+        Span::styled("▼", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" navigate | "),
+        Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" disassemble"),
+    ];
+    let msg = Text::from(Spans::from(msg));
+    let help_message = Paragraph::new(msg).alignment(Alignment::Center);
+    f.render_widget(help_message, rects[2]);
+
+    // Hits
+    let selected_style = Style::default().add_modifier(Modifier::REVERSED);
+    let header_cells = ["Count", "Address", "Instruction", "Symbol"]
+        .iter()
+        .map( |h| Cell::from(*h) );
+    let header = Row::new(header_cells)
+        .style(Style::default().bg(Color::DarkGray).fg(Color::Black))
+        .height(1)
+        .bottom_margin(1);
+    let rows = app.watch.hits.iter().map(|hit| {
+        Row::new(vec![
+            Cell::from(hit.count.to_string()),
+            Cell::from(format!("{:016X}", hit.address)),
+            Cell::from(hit.instruction.clone()),
+            Cell::from(Span::styled(hit.symbol.clone(), Style::default().fg(Color::Green))),
+        ])
+    });
+    let t = Table::new(rows)
+        .header(header)
+        .column_spacing(2)
+        .block(Block::default().borders(Borders::ALL)
+            .title(" 🔎 Watchpoint Hits ")
+            .style(Style::default().fg(Color::Yellow)))
+        .highlight_style(selected_style)
+        .highlight_symbol(">> ")
+        .widths(&[
+            Constraint::Length(8),
+            Constraint::Length(16),
+            Constraint::Percentage(50),
+            Constraint::Percentage(30),
+        ]);
+    f.render_stateful_widget(t, rects[1], &mut app.watch.table_state);
+
+    // Error Popup
+    if app.show_popup {
+        draw_error_popup(f, &app.popup_error);
+    }
+}
+
+
+fn draw_inspector<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let size = f.size();
+    let main_height = size.height.checked_sub(4).unwrap_or_default();
+
+    let rects = Layout::default()
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(main_height),
+            Constraint::Length(1),
+        ].as_ref())
+        .margin(1)
+        .split(size);
+
+    // Top Messages
+    let msg = Text::from(format!("Process {}", app.selected_process));
+    let top_message = Paragraph::new(msg).alignment(Alignment::Center);
+    f.render_widget(top_message, rects[0]);
+
+    // Help
+    let msg = vec![
+        Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" quit | "),
+        Span::styled("u", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" update | "),
+        Span::styled("◄", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" back | "),
+        Span::styled("▲", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw("/"),
+        Span::styled("▼", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" navigate | "),
+        Span::styled("PgUp", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw("/"),
+        Span::styled("PgDn", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" scroll details | "),
+        Span::styled("s", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" scan region | "),
+        Span::styled("m", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" scan module | "),
+        Span::styled("a", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" scan all | "),
+        Span::styled("v", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" memory map"),
+    ];
+    let msg = Text::from(Spans::from(msg));
+    let help_message = Paragraph::new(msg).alignment(Alignment::Center);
+    f.render_widget(help_message, rects[2]);
+
+    let rects = Layout::default()
+        .constraints([
+            Constraint::Percentage(40),
+            Constraint::Percentage(60),
+        ].as_ref())
+        .direction(Direction::Vertical)
+        .split(rects[1]);
+
+    // Details
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let field = |name: &str, value: String| Spans::from(vec![
+        Span::styled(format!("{:<14}", name), bold),
+        Span::raw(value),
+    ]);
+    let lines = match &app.inspector.details {
+        Some(details) => {
+            let threads = details.threads.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(" ");
+            let mut lines = vec![
+                field("Executable", details.exe.clone()),
+                field("Command line", details.cmdline.clone()),
+                field("User", details.user.clone()),
+                field("Group", details.group.clone()),
+                field("Threads", format!("{} {}", details.threads.len(), if threads.is_empty() { String::new() } else { format!("({})", threads) })),
+                field("Started", format!("{} ago", format_age(details.start_time))),
+                field("Memory", format!("{} kB resident, {} kB virtual", details.resident, details.virtual_size)),
+                field("Environment", String::new()),
+            ];
+            lines.extend(details.environment.iter().map(|e| Spans::from(Span::raw(format!("  {}", e)))));
+            lines
+        },
+        None => vec![Spans::from("Process details are not available.")]
+    };
+    let details = Paragraph::new(lines)
+        .scroll((app.inspector.scroll, 0))
+        .block(Block::default().borders(Borders::ALL).title(" 📋 Process "));
+    f.render_widget(details, rects[0]);
+
+    draw_memory_map_table(f, app, rects[1]);
+}
+
+
+fn draw_memory_map<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let size = f.size();
+    let main_height = size.height.checked_sub(7).unwrap_or_default();
+
+    let rects = Layout::default()
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Length(main_height),
+            Constraint::Length(1),
+        ].as_ref())
+        .margin(1)
+        .split(size);
+
+    // Top Messages
+    let mut msg = vec![Span::raw(format!("Memory map of process {}", app.selected_process))];
+    if let Some(ranges) = app.memory_map.scan_ranges() {
+        let total = ranges.iter().map(|(start, end)| end - start).sum::<usize>();
+        msg.push(Span::styled(format!(" (next scan: {} regions, {} kB)", ranges.len(), total / 1024), Style::default().fg(Color::Yellow)));
     }
+    let msg = Text::from(Spans::from(msg));
+    let top_message = Paragraph::new(msg).alignment(Alignment::Center);
+    f.render_widget(top_message, rects[0]);
+
+    // Help
+    let msg = if app.memory_map.filtering {
+        vec![
+            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" clear filter | "),
+            Span::styled("▲", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw("/"),
+            Span::styled("▼", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" navigate | "),
+            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" done"),
+        ]
+    } else {
+        vec![
+            Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" quit | "),
+            Span::styled("u", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" update | "),
+            Span::styled("◄", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" back | "),
+            Span::styled("/", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" filter | "),
+            Span::styled("1", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw("-"),
+            Span::styled("4", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" sort | "),
+            Span::styled("Space", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" toggle scan | "),
+            Span::styled("+", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw("/"),
+            Span::styled("-", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" include/exclude shown | "),
+            Span::styled("r", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" reset | "),
+            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" hex view"),
+        ]
+    };
+    let msg = Text::from(Spans::from(msg));
+    let help_message = Paragraph::new(msg).alignment(Alignment::Center);
+    f.render_widget(help_message, rects[3]);
+
+    // Filter
+    let width = rects[1].width.max(3) - 3;
+    let scroll = (app.memory_map.filter.cursor() as u16).max(width) - width;
+    let input = Paragraph::new(app.memory_map.filter.value())
+        .style(if app.memory_map.filtering {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        })
+        .scroll((0, scroll))
+        .block(Block::default().borders(Borders::ALL).title(" 🔎 Filter by file, permissions or address ").title_alignment(Alignment::Center));
+    f.render_widget(input, rects[1]);
+    if app.memory_map.filtering {
+        f.set_cursor(
+            rects[1].x + (app.memory_map.filter.cursor() as u16).min(width) + 1,
+            rects[1].y + 1,
+        )
+    }
+
+    draw_memory_map_table(f, app, rects[2]);
+}
+
+
+// Regions picked for the next first scan are marked and highlighted
+fn draw_memory_map_table<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let selected_style = Style::default().add_modifier(Modifier::REVERSED);
+    let header_cells = MapColumn::ALL.iter()
+        .map(|c| if *c == app.memory_map.sort {
+            format!("{} {}", c.name(), if app.memory_map.descending { "▼" } else { "▲" })
+        } else {
+            c.name().to_string()
+        })
+        .chain([String::from("Offset"), String::from("Scan")]);
+    let header_cells = header_cells.map(Cell::from);
+    let header = Row::new(header_cells)
+        .style(Style::default().bg(Color::DarkGray).fg(Color::Black))
+        .height(1)
+        .bottom_margin(1);
+    let rows = app.memory_map.iter().map(|m| {
+        let included = app.memory_map.is_included(m);
+        Row::new(vec![
+            Cell::from(format!("{:012X}-{:012X}", m.region.base, m.region.base + m.region.size)),
+            Cell::from(format!("{:X}", m.region.size)),
+            Cell::from(m.region.permissions()),
+            Cell::from(Span::styled(backing(m).to_string(), Style::default().fg(Color::Green))),
+            Cell::from(format!("{:X}", m.offset)),
+            Cell::from(if included { "✓" } else { "" }),
+        ]).style(if included && app.memory_map.is_customized() { Style::default().fg(Color::Yellow) } else { Style::default() })
+    });
+    let title = format!(" 🗺  Memory Map ({} regions) ", app.memory_map.len());
+    let t = Table::new(rows)
+        .header(header)
+        .column_spacing(2)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(selected_style)
+        .highlight_symbol(">> ")
+        .widths(&[
+            Constraint::Length(25),
+            Constraint::Length(10),
+            Constraint::Length(6),
+            Constraint::Percentage(50),
+            Constraint::Length(8),
+            Constraint::Length(4),
+        ]);
+    f.render_stateful_widget(t, area, &mut app.memory_map.table_state);
+}
+
+fn draw_signature_popup<B: Backend>(f: &mut Frame<B>, signature: &Signature) {
+    let area = centered_rect(60, 20, f.size());
+    
+    let block = Block::default().title(" 🔖 Signature ").title_alignment(Alignment::Center).borders(Borders::ALL).style(Style::default().fg(Color::Yellow));
+    
+    let msg = Text::from(vec![
+        Spans::from(Span::styled(signature.to_string(), Style::default().fg(Color::White).add_modifier(Modifier::BOLD))),
+        Spans::from(format!("{}+{:X}, unique in module", signature.module, signature.offset)),
+    ]);
+    let msg = Paragraph::new(msg).alignment(Alignment::Center).wrap(Wrap { trim: true });
+    
+    let rects = Layout::default()
+        .constraints([
+            Constraint::Percentage(30),
+            Constraint::Percentage(70),
+        ].as_ref())
+        .split(block.inner(area));
+        
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+    f.render_widget(msg, rects[1]);
+}
+
+
+fn draw_error_popup<B: Backend>(f: &mut Frame<B>, error: &str) {
+    let area = centered_rect(60, 20, f.size());
+    
+    let block = Block::default().title(" Error ").title_alignment(Alignment::Center).borders(Borders::ALL).style(Style::default().fg(Color::Yellow));
     
+    let msg = Text::from(error.to_string());
+    let msg = Paragraph::new(msg).alignment(Alignment::Center);
+    
+    let rects = Layout::default()
+        .constraints([
+            Constraint::Percentage(50),
+            Constraint::Percentage(50),
+        ].as_ref())
+        .split(block.inner(area));
+        
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+    f.render_widget(msg, rects[1]);
 }
 
 
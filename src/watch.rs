@@ -0,0 +1,148 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    sync::{Arc, Mutex},
+};
+
+use tui::widgets::TableState;
+
+use crate::disasm::{disassemble_at, instruction_before};
+use crate::expr::{AddressResolver, ProcessResolver};
+use crate::os::Watcher;
+
+
+#[derive(Clone, Copy)]
+pub enum WatchKind {
+    Write,
+    Access,
+}
+
+impl WatchKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WatchKind::Write => "writes to",
+            WatchKind::Access => "accesses",
+        }
+    }
+}
+
+pub struct WatchHit {
+    pub address: usize,
+    pub count: usize,
+    pub instruction: String,
+    pub symbol: String,
+}
+
+// Instructions that touched a watched address, found with a hardware watchpoint
+pub struct Watch {
+    pub address: usize,
+    pub len: usize,
+    pub kind: WatchKind,
+    pub hits: Vec<WatchHit>,
+    pub table_state: TableState,
+
+    counts: Arc<Mutex<BTreeMap<usize, usize>>>,
+    watcher: Option<Watcher>,
+    resolver: ProcessResolver,
+    bitness: u32,
+    // Instruction pointer after the access -> (instruction, text)
+    decoded: HashMap<usize, (usize, String)>,
+}
+
+impl Watch {
+    pub fn new() -> Watch {
+        Watch {
+            address: 0,
+            len: 0,
+            kind: WatchKind::Write,
+            hits: vec![],
+            table_state: TableState::default(),
+
+            counts: Arc::new(Mutex::new(BTreeMap::new())),
+            watcher: None,
+            resolver: ProcessResolver::default(),
+            bitness: 64,
+            decoded: HashMap::new(),
+        }
+    }
+
+    // Largest length the debug registers accept, they need an aligned address
+    fn watch_len(address: usize, size: usize) -> usize {
+        [8, 4, 2, 1].into_iter()
+            .find(|&len| len <= size && address % len == 0)
+            .unwrap_or(1)
+    }
+
+    pub fn start(&mut self, pid: u32, address: usize, size: usize, kind: WatchKind) -> io::Result<()> {
+        self.stop();
+
+        self.address = address;
+        self.len = Watch::watch_len(address, size);
+        self.kind = kind;
+        self.hits.clear();
+        self.decoded.clear();
+        self.table_state.select(None);
+        self.resolver = ProcessResolver::new(pid);
+        self.bitness = self.resolver.pointer_size() as u32 * 8;
+        self.counts = Arc::new(Mutex::new(BTreeMap::new()));
+
+        let access = matches!(kind, WatchKind::Access);
+        self.watcher = Some(Watcher::start(pid, address, self.len, access, Arc::clone(&self.counts))?);
+        Ok(())
+    }
+
+    // Detaches from the target and clears the debug registers
+    pub fn stop(&mut self) {
+        if let Some(mut watcher) = self.watcher.take() {
+            watcher.stop();
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.watcher.as_ref().is_some_and(|w| w.is_running())
+    }
+
+    // Traps come after the instruction, the one before is the culprit
+    pub fn refresh(&mut self, pid: u32) {
+        let counts = self.counts.lock().unwrap().clone();
+
+        let mut hits: Vec<WatchHit> = counts.into_iter().map(|(ip, count)| {
+            let bitness = self.bitness;
+            let (address, instruction) = self.decoded.entry(ip).or_insert_with(|| {
+                let address = instruction_before(pid, bitness, ip);
+                (address, disassemble_at(pid, bitness, address).unwrap_or_else(|| String::from("??")))
+            }).clone();
+
+            WatchHit {
+                address: address,
+                count: count,
+                instruction: instruction,
+                symbol: self.resolver.symbolize(address).unwrap_or_default(),
+            }
+        }).collect();
+        hits.sort_by_key(|h| h.address);
+
+        self.hits = hits;
+        if self.table_state.selected().is_none() && !self.hits.is_empty() {
+            self.table_state.select(Some(0));
+        }
+    }
+
+    pub fn selected(&self) -> Option<&WatchHit> {
+        self.hits.get(self.table_state.selected()?)
+    }
+
+    pub fn next(&mut self) {
+        if self.hits.is_empty() { return; }
+        self.table_state.select(Some(
+            self.table_state.selected().map_or(0, |i| (i + 1) % self.hits.len())
+        ));
+    }
+
+    pub fn previous(&mut self) {
+        if self.hits.is_empty() { return; }
+        self.table_state.select(Some(
+            self.table_state.selected().map_or(0, |i| (i + self.hits.len() - 1) % self.hits.len())
+        ));
+    }
+}
@@ -1,57 +1,99 @@
+//! Windows backend, on top of the Win32 process, memory and debugging APIs
+
 use std::{
+    collections::BTreeMap,
+    io,
+    sync::{Arc, Mutex},
     mem::{size_of_val, size_of}, 
     ops::BitAnd, 
-    convert::TryInto, 
-    sync::Arc
+    os::windows::process::CommandExt,
+    process::{Child, Command},
 };
 
-use tokio::sync::Mutex;
-
+use windows::core::{PCWSTR, PWSTR};
 use windows::Win32::{
     Foundation::{
-        HINSTANCE, HANDLE,
+        HINSTANCE, HANDLE, BOOL, FILETIME,
         CloseHandle,
-    }, 
+    },
+    Security::{
+        GetTokenInformation,
+        LookupAccountSidW,
+        TokenUser,
+        TOKEN_USER,
+        TOKEN_QUERY,
+        SID_NAME_USE,
+    },
     System::{
         ProcessStatus::{
             K32EnumProcesses,
             K32EnumProcessModules,
+            K32EnumProcessModulesEx,
             K32GetModuleBaseNameW,
+            K32GetModuleInformation,
             K32GetProcessMemoryInfo,
+            K32GetMappedFileNameW,
             PROCESS_MEMORY_COUNTERS,
+            MODULEINFO,
+            LIST_MODULES_ALL,
         },
         Threading::{
             OpenProcess,
+            OpenProcessToken,
+            QueryFullProcessImageNameW,
+            OpenThread,
+            ResumeThread,
+            SuspendThread,
+            CREATE_SUSPENDED,
+            THREAD_SUSPEND_RESUME,
+            PROCESS_NAME_WIN32,
+            IsWow64Process,
+            GetProcessTimes,
+            NtQueryInformationProcess,
+            ProcessBasicInformation,
+            PROCESS_BASIC_INFORMATION,
             PROCESS_QUERY_INFORMATION,
             PROCESS_VM_READ,
             PROCESS_VM_WRITE,
+            PROCESS_VM_OPERATION,
         },
         Memory::{
             VirtualQueryEx,
+            VirtualProtectEx,
             MEMORY_BASIC_INFORMATION,
+            PAGE_PROTECTION_FLAGS,
             PAGE_READWRITE,
+            PAGE_WRITECOPY,
+            PAGE_EXECUTE,
+            PAGE_EXECUTE_READ,
+            PAGE_EXECUTE_READWRITE,
+            PAGE_EXECUTE_WRITECOPY,
+            PAGE_NOACCESS,
+            PAGE_GUARD,
             MEM_COMMIT,
+            MEM_MAPPED,
+        },
+        Diagnostics::ToolHelp::{
+            CreateToolhelp32Snapshot,
+            Thread32First,
+            Thread32Next,
+            THREADENTRY32,
+            TH32CS_SNAPTHREAD,
         },
         Diagnostics::Debug::{
             ReadProcessMemory, 
             WriteProcessMemory,
+            FlushInstructionCache,
         },
     },
 };
 
-use crate::app::App;
-use crate::mem::{Memory,Datatype};
+use crate::process::{ProcessInfo, ProcessDetails, Module, Region, Mapping, Register, DebugEvent};
 
 
-pub struct WinProc {
-    pub name: String,
-    pub memory: f64,
-    pub pid: u32,
-}
-
-
-pub fn enum_processes() -> Vec<WinProc> {
-    let mut processes = Vec::<WinProc>::new();
+/// Every process the current user can see, unsorted
+pub fn enum_processes() -> Vec<ProcessInfo> {
+    let mut processes = Vec::<ProcessInfo>::new();
 
     let mut pids: [u32; 4096] = [0; 4096];
     let mut np: u32 = 0;
@@ -80,17 +122,111 @@ pub fn enum_processes() -> Vec<WinProc> {
                         0.0
                     };
 
-                    processes.push(WinProc{name: name, memory: mem_usage, pid: *pid});
+                    processes.push(ProcessInfo{
+                        name: name,
+                        memory: mem_usage,
+                        pid: *pid,
+                        ppid: basic_information(process).map_or(0, |info| info.Reserved3 as u32),
+                        user: process_user(process),
+                        cmdline: process_cmdline(process),
+                        start_time: process_start_time(process),
+                    });
                 }
                 CloseHandle(process);
             }
         }
     }
-    processes.sort_by(|a, b| b.memory.partial_cmp(&a.memory).unwrap());
     processes
 }
 
 
+unsafe fn process_user(process: HANDLE) -> String {
+    let mut token = HANDLE::default();
+    if !OpenProcessToken(process, TOKEN_QUERY, &mut token).as_bool() {
+        return String::new();
+    }
+
+    let mut buffer = [0u64; 64];
+    let mut len = 0;
+    let mut user = String::new();
+    if GetTokenInformation(token, TokenUser, Some(buffer.as_mut_ptr() as _), size_of_val(&buffer) as u32, &mut len).as_bool() {
+        let sid = (*(buffer.as_ptr() as *const TOKEN_USER)).User.Sid;
+        let mut name = [0u16; 256];
+        let mut domain = [0u16; 256];
+        let mut name_len = name.len() as u32;
+        let mut domain_len = domain.len() as u32;
+        let mut kind = SID_NAME_USE::default();
+        if LookupAccountSidW(PCWSTR::null(), sid, PWSTR(name.as_mut_ptr()), &mut name_len,
+                             PWSTR(domain.as_mut_ptr()), &mut domain_len, &mut kind).as_bool() {
+            user = String::from_utf16_lossy(&name[..name_len as usize]);
+        }
+    }
+    CloseHandle(token);
+    user
+}
+
+
+// PEB address, Reserved3 is the parent PID
+unsafe fn basic_information(process: HANDLE) -> Option<PROCESS_BASIC_INFORMATION> {
+    let mut info = PROCESS_BASIC_INFORMATION::default();
+    let mut len = 0;
+    NtQueryInformationProcess(process, ProcessBasicInformation, &mut info as *mut _ as _, size_of_val(&info) as u32, &mut len).ok()?;
+    Some(info)
+}
+
+
+// Command line from the process parameters in the PEB, the layout depends on our own bitness
+unsafe fn process_cmdline(process: HANDLE) -> String {
+    let info = match basic_information(process) {
+        Some(info) => info,
+        None => return String::new()
+    };
+
+    let pointer = size_of::<usize>();
+    let read = |address: usize, buffer: &mut [u8]| {
+        ReadProcessMemory(process, address as _, buffer.as_mut_ptr() as _, buffer.len(), None).as_bool()
+    };
+
+    let mut parameters = [0u8; size_of::<usize>()];
+    if !read(info.PebBaseAddress as usize + 4 * pointer, &mut parameters) {
+        return String::new();
+    }
+    let parameters = usize::from_le_bytes(parameters);
+
+    // RTL_USER_PROCESS_PARAMETERS.CommandLine, a UNICODE_STRING at 0x70 on x64 and 0x40 on x86:
+    // length, maximum length, padding, buffer
+    let mut string = [0u8; 2 * size_of::<usize>()];
+    if !read(parameters + 16 + 12 * pointer, &mut string) {
+        return String::new();
+    }
+    let length = u16::from_le_bytes([string[0], string[1]]) as usize;
+    let buffer = usize::from_le_bytes(string[pointer..].try_into().unwrap());
+
+    let mut cmdline = vec![0u8; length];
+    if !read(buffer, &mut cmdline) {
+        return String::new();
+    }
+    let cmdline: Vec<u16> = cmdline.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+    String::from_utf16_lossy(&cmdline)
+}
+
+
+unsafe fn process_start_time(process: HANDLE) -> u64 {
+    let mut creation = FILETIME::default();
+    let mut exit = FILETIME::default();
+    let mut kernel = FILETIME::default();
+    let mut user = FILETIME::default();
+    if !GetProcessTimes(process, &mut creation, &mut exit, &mut kernel, &mut user).as_bool() {
+        return 0;
+    }
+
+    // 100 ns intervals since 1601
+    let ticks = (creation.dwHighDateTime as u64) << 32 | creation.dwLowDateTime as u64;
+    (ticks / 10_000_000).saturating_sub(11_644_473_600)
+}
+
+
+/// True if the process is running and its memory can be opened
 pub fn check_process(pid : u32) -> bool {
     unsafe {
         let process = OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ | PROCESS_VM_WRITE, false, pid);
@@ -105,245 +241,325 @@ pub fn check_process(pid : u32) -> bool {
 }
 
 
-struct MemInfo<T> {
-    base: *const T,
-    size: usize,
-}
-unsafe impl<T> Send for MemInfo<T> {}
-unsafe impl<T> Sync for MemInfo<T> {}
-
-
-pub async fn scan_process(pid : u32, target_bytes: &[u8], target_type: &Datatype, app_mutex: Arc<Mutex<App>>) {
-    let mut results = Memory::new();
-    let num_bytes = target_bytes.len();
-
-    match unsafe { OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ | PROCESS_VM_WRITE, false, pid) } 
-    {
-        Ok(process) => {
-            let mut app = app_mutex.lock().await;
-            app.memory = Memory::new();
-            drop(app);
-
-            let pages = get_process_memory_pages(process).iter()
-                .map(|&x| MemInfo{base: x.BaseAddress as *const _, size: x.RegionSize})
-                .collect::<Vec<_>>();
-            
-            let mut sweeped_memory : usize = 0;
-            let total_memory = pages.iter().map(|p| p.size).sum::<usize>() as f64;
-
-            for page in pages.iter() {
-                let mut buffer: Vec<u8> = Vec::with_capacity(page.size);
-                let mut bytes_read: usize = 0;
-
-                unsafe { ReadProcessMemory(
-                    process,
-                    page.base,
-                    buffer.as_mut_ptr() as *mut _,
-                    page.size,
-                    Some(&mut bytes_read)
-                ) };
-
-                if page.size == bytes_read 
-                {
-                    unsafe { buffer.set_len(bytes_read) };
-    
-                    buffer.windows(num_bytes).enumerate().for_each(|(offset, window)| {
-                        if window == target_bytes {
-                            results.push(page.base as usize + offset, target_type, target_bytes);
-                        }
-                    });
-                }
+/// Executable and libraries loaded by the process
+pub fn enum_modules(pid : u32) -> Vec<Module> {
+    let mut modules = Vec::<Module>::new();
 
-                sweeped_memory += page.size;
-                let mut app = app_mutex.lock().await;
-                app.search_progress = sweeped_memory as f64 / total_memory;
-            }
+    unsafe {
+        if let Ok(process) = OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, pid) {
+            let mut handles: [HINSTANCE; 1024] = [HINSTANCE::default(); 1024];
+            let mut cb = 0;
 
-            unsafe { CloseHandle(process) };
+            if K32EnumProcessModulesEx(process, handles.as_mut_ptr(), size_of_val(&handles) as u32, &mut cb, LIST_MODULES_ALL).as_bool() {
+                let count = (cb as usize / size_of::<HINSTANCE>()).min(handles.len());
 
-            let mut app = app_mutex.lock().await;
-            app.search_progress = 1f64;
-            app.memory = std::mem::take(&mut results);
-            log::info!(" First Scan found {} entries.", app.memory.len());
-        },
-        Err(error) => {
-            log::error!("Error while analyzing process: {:?}", error);
+                for module in &handles[..count] {
+                    let mut name: [u16; 512] = [0; 512];
+                    let len = K32GetModuleBaseNameW(process, *module, &mut name);
+                    let name = String::from_utf16_lossy(&name[..len as usize]);
+
+                    let mut info = MODULEINFO::default();
+                    if K32GetModuleInformation(process, *module, &mut info, size_of_val(&info) as u32).as_bool() {
+                        modules.push(Module{name: name, base: info.lpBaseOfDll as usize, size: info.SizeOfImage as usize});
+                    }
+                }
+            }
+            CloseHandle(process);
         }
     }
+    modules
 }
 
 
-pub async fn filter_process(pid : u32, target_bytes: &[u8], target_type: &Datatype, app_mutex: Arc<Mutex<App>>) {
-    let num_bytes = target_bytes.len();
-
-    match unsafe { OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ | PROCESS_VM_WRITE, false, pid) } 
-    {
-        Ok(process) => {
-            let mut app = app_mutex.lock().await;
-            let mut memory = std::mem::take(&mut app.memory);
-            drop(app);
-
-            let mut buffer: Vec<u8> = vec![0;num_bytes];
-            let mut bytes_read: usize = 0;
-            
-            let mut sweeped_memory : usize = 0;
-            let total_memory = memory.len();
-            let progress_update_freq = std::cmp::max(total_memory, total_memory / 100);
-
-            macro_rules! filter_mem_type{
-                ($($a:ident).+,$b:ty)=>{
-                    {                        
-                        $($a).+.retain_mut(|l| {
-                            unsafe { ReadProcessMemory(process, l.address as *const _, buffer.as_mut_ptr() as *mut _, target_bytes.len(), Some(&mut bytes_read)) };
-                            l.old_value = l.value;
-                            l.value = <$b>::from_ne_bytes(buffer.clone().try_into().unwrap());
-                            sweeped_memory += 1;
-
-                            if sweeped_memory % progress_update_freq == 0 {
-                                if let Ok(mut app) = app_mutex.try_lock() {
-                                    app.search_progress = sweeped_memory as f64 / total_memory as f64; 
-                                }
-                            }
-                            bytes_read == num_bytes && target_bytes == buffer
-                        });
-                    }
+/// Memory map of the process, with the file backing each region
+pub fn enum_mappings(pid : u32) -> Vec<Mapping> {
+    let mut mappings = Vec::<Mapping>::new();
+    let mut lpaddress = 0;
+    let mut mbi = MEMORY_BASIC_INFORMATION::default();
+    const MBI_SIZE : usize = size_of::<MEMORY_BASIC_INFORMATION>();
+
+    unsafe {
+        if let Ok(process) = OpenProcess(PROCESS_QUERY_INFORMATION, false, pid) {
+            while VirtualQueryEx(process, Some(lpaddress as *const _), &mut mbi, MBI_SIZE) == MBI_SIZE {
+                let protect = mbi.Protect.0;
+                if mbi.State.bitand(MEM_COMMIT).0 != 0 && protect & (PAGE_NOACCESS.0 | PAGE_GUARD.0) == 0 {
+                    // Device path like \Device\HarddiskVolume1\..., empty for private memory
+                    let mut path: [u16; 512] = [0; 512];
+                    let len = K32GetMappedFileNameW(process, mbi.BaseAddress, &mut path);
+                    let path = String::from_utf16_lossy(&path[..len as usize]);
+
+                    mappings.push(Mapping {
+                        region: Region {
+                            base: mbi.BaseAddress as usize,
+                            size: mbi.RegionSize,
+                            readable: true,
+                            writable: protect & (PAGE_READWRITE.0 | PAGE_WRITECOPY.0 | PAGE_EXECUTE_READWRITE.0 | PAGE_EXECUTE_WRITECOPY.0) != 0,
+                            executable: protect & (PAGE_EXECUTE.0 | PAGE_EXECUTE_READ.0 | PAGE_EXECUTE_READWRITE.0 | PAGE_EXECUTE_WRITECOPY.0) != 0,
+                            // Views of sections, images are copy-on-write
+                            shared: mbi.Type == MEM_MAPPED,
+                        },
+                        offset: if path.is_empty() { 0 } else { (mbi.BaseAddress as usize - mbi.AllocationBase as usize) as u64 },
+                        path: path,
+                    });
                 }
+                lpaddress += mbi.RegionSize;
             }
+            CloseHandle(process);
+        }
+    }
+    mappings
+}
 
-            match *target_type {
-                Datatype::B1 => filter_mem_type![memory.mem_u8,u8],
-                Datatype::B1S => filter_mem_type![memory.mem_i8,i8],
-                Datatype::B2 => filter_mem_type![memory.mem_u16,u16],
-                Datatype::B2S => filter_mem_type![memory.mem_i16,i16],
-                Datatype::B4 => filter_mem_type![memory.mem_u32,u32],
-                Datatype::B4S => filter_mem_type![memory.mem_i32,i32],
-                Datatype::B8 => filter_mem_type![memory.mem_u64,u64],
-                Datatype::B8S => filter_mem_type![memory.mem_i64,i64],
-                Datatype::B16 => filter_mem_type![memory.mem_u128,u128],
-                Datatype::B16S => filter_mem_type![memory.mem_i128,i128],
-                Datatype::F => filter_mem_type![memory.mem_f32,f32],
-                Datatype::D => filter_mem_type![memory.mem_f64,f64],
-            }
 
-            unsafe { CloseHandle(process) };
+/// Committed memory of the process, sorted by address
+pub fn enum_regions(pid : u32) -> Vec<Region> {
+    enum_mappings(pid).into_iter().map(|m| m.region).collect()
+}
 
-            let mut app = app_mutex.lock().await;
-            app.memory = std::mem::take(&mut memory);
-            app.search_progress = 1f64;
-            log::info!(" {} entries remaining after filtering.", app.memory.len());
-        },
-        Err(error) => {
-            log::error!("Error while analyzing process: {:?}", error);
+
+/// Executable, environment, owner and threads of one process
+///
+/// Environment, group and threads aren't collected on Windows
+pub fn process_details(pid : u32) -> io::Result<ProcessDetails> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, pid)?;
+
+        let mut exe: [u16; 1024] = [0; 1024];
+        let mut len = exe.len() as u32;
+        let exe = if QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(exe.as_mut_ptr()), &mut len).as_bool() {
+            String::from_utf16_lossy(&exe[..len as usize])
+        } else {
+            String::new()
+        };
+
+        let mut counters = PROCESS_MEMORY_COUNTERS::default();
+        if !K32GetProcessMemoryInfo(process, &mut counters, size_of_val(&counters) as u32).as_bool() {
+            counters = PROCESS_MEMORY_COUNTERS::default();
         }
+
+        let details = ProcessDetails {
+            exe: exe,
+            cmdline: process_cmdline(process),
+            environment: vec![],
+            user: process_user(process),
+            group: String::new(),
+            threads: vec![],
+            start_time: process_start_time(process),
+            resident: (counters.WorkingSetSize / 1024) as u64,
+            virtual_size: (counters.PagefileUsage / 1024) as u64,
+        };
+        CloseHandle(process);
+        Ok(details)
     }
 }
 
 
-pub async fn update_process(app_mutex : Arc<Mutex<App>>) {
-    let mut app = app_mutex.lock().await;
-    let pid = app.selected_process;
-
-    match unsafe { OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ | PROCESS_VM_WRITE, false, pid) }
-    {
-        Ok(process) => {
-            let mut memory = std::mem::take(&mut app.memory);
-            drop(app);
-
-            let mut i : usize = 0;
-            let memory_size = memory.len();
-            let progress_update_freq = std::cmp::max(memory_size, memory_size / 100);
-
-            macro_rules! update_mem_type{
-                ($($a:ident).+,$b:ty)=>{
-                    {
-                        let num_bytes = <$b>::default().to_ne_bytes().len();
-                        let mut buffer: Vec<u8> = vec![0;num_bytes];
-                        let mut bytes_read: usize = 0;
-
-                        $($a).+.retain_mut(|l| {
-                            unsafe { ReadProcessMemory(process, l.address as *const _, buffer.as_mut_ptr() as *mut _, num_bytes, Some(&mut bytes_read)) };
-                            l.old_value = l.value;
-                            l.value = <$b>::from_ne_bytes(buffer.clone().try_into().unwrap());
-                            i += 1;
-                            
-                            if i % progress_update_freq == 0 {
-                                if let Ok(mut app) = app_mutex.try_lock() {
-                                    app.search_progress = i as f64 / memory_size as f64; 
-                                }
-                            }
-                            bytes_read == num_bytes
-                        });
-                    }
+/// Starts command held at its entry point, resume_process lets it run
+///
+/// Created suspended so nothing runs before we look at it
+pub fn launch(command : &mut Command) -> io::Result<Child> {
+    command.creation_flags(CREATE_SUSPENDED.0).spawn()
+}
+
+
+/// Lets a process started by launch run
+pub fn resume_process(pid : u32) -> io::Result<()> {
+    for_each_thread(pid, |thread| unsafe { ResumeThread(thread); })
+}
+
+fn for_each_thread(pid : u32, mut action : impl FnMut(HANDLE)) -> io::Result<()> {
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0)?;
+        let mut entry = THREADENTRY32 { dwSize: size_of::<THREADENTRY32>() as u32, ..Default::default() };
+        let mut more = Thread32First(snapshot, &mut entry).as_bool();
+        while more {
+            if entry.th32OwnerProcessID == pid {
+                if let Ok(thread) = OpenThread(THREAD_SUSPEND_RESUME, false, entry.th32ThreadID) {
+                    action(thread);
+                    CloseHandle(thread);
                 }
             }
-
-            update_mem_type![memory.mem_u8,u8];
-            update_mem_type![memory.mem_i8,i8];
-            update_mem_type![memory.mem_u16,u16];
-            update_mem_type![memory.mem_i16,i16];
-            update_mem_type![memory.mem_u32,u32];
-            update_mem_type![memory.mem_i32,i32];
-            update_mem_type![memory.mem_u64,u64];
-            update_mem_type![memory.mem_i64,i64];
-            update_mem_type![memory.mem_u128,u128];
-            update_mem_type![memory.mem_i128,i128];
-            update_mem_type![memory.mem_f32,f32];
-            update_mem_type![memory.mem_f64,f64];
-
-            unsafe { CloseHandle(process) };
-
-            let mut app = app_mutex.lock().await;
-            app.search_progress = 1f64;
-            app.memory = std::mem::take(&mut memory);
-        },
-        Err(error) => {
-            log::error!("Error while analyzing process: {:?}", error);
+            more = Thread32Next(snapshot, &mut entry).as_bool();
         }
+        CloseHandle(snapshot);
     }
+    Ok(())
 }
 
 
-pub fn write_process(pid : u32, address : usize, target_bytes: &[u8]) -> bool {
-    let num_bytes = target_bytes.len();
+/// Keeps a process stopped until dropped, a process that was stopped already stays stopped
+///
+/// Every thread is suspended and resumed, suspensions nest
+pub struct Suspended {
+    pid: u32,
+}
 
-    unsafe { 
-        match OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ | PROCESS_VM_WRITE, false, pid)
-        {
-            Ok(process) => {
-                let mut bytes_written: usize = 0;
+impl Suspended {
+    pub fn new(pid : u32) -> io::Result<Suspended> {
+        crate::os::local_only("stopping a process")?;
+        for_each_thread(pid, |thread| unsafe { SuspendThread(thread); })?;
+        Ok(Suspended { pid: pid })
+    }
+}
+
+impl Drop for Suspended {
+    fn drop(&mut self) {
+        let _ = resume_process(self.pid);
+    }
+}
 
-                WriteProcessMemory(
-                    process,
-                    address as *const _,
-                    target_bytes.to_vec().as_ptr() as *const _,
-                    num_bytes,
-                    Some(&mut bytes_written)
-                ); 
 
+/// 4 or 8, depending on the bitness of the process
+pub fn pointer_size(pid : u32) -> usize {
+    unsafe {
+        match OpenProcess(PROCESS_QUERY_INFORMATION, false, pid) {
+            Ok(process) => {
+                let mut wow64 = BOOL::default();
+                let is_wow64 = IsWow64Process(process, &mut wow64).as_bool() && wow64.as_bool();
                 CloseHandle(process);
 
-                bytes_written == num_bytes
+                if is_wow64 { 4 } else { size_of::<usize>() }
             },
-            Err(_) => {
-                false
-            }
+            Err(_) => size_of::<usize>()
         }
     }
 }
 
 
-fn get_process_memory_pages(hprocess : HANDLE) -> Vec<MEMORY_BASIC_INFORMATION> {
-    let mut pages = Vec::new();
-    let mut lpaddress = 0;
-    let mut mbi = MEMORY_BASIC_INFORMATION::default();
-    const MBI_SIZE : usize = size_of::<MEMORY_BASIC_INFORMATION>();
-    unsafe {
-        while VirtualQueryEx(hprocess, Some(lpaddress as *const _), &mut mbi, MBI_SIZE) == MBI_SIZE {
-            if mbi.AllocationProtect.bitand(PAGE_READWRITE).0 != 0 && mbi.State.bitand(MEM_COMMIT).0 != 0 {
-                pages.push(mbi);
+/// Fills buffer from address, false if any of it can't be read
+pub fn read_process(pid : u32, address : usize, buffer: &mut [u8]) -> bool {
+    match Process::open(pid) {
+        Ok(process) => process.read(address, buffer),
+        Err(_) => false
+    }
+}
+
+
+/// Writes all of target_bytes at address
+pub fn write_process(pid : u32, address : usize, target_bytes: &[u8]) -> io::Result<()> {
+    Process::open(pid)?.write(address, target_bytes)
+}
+
+
+/// Open process for many reads and writes in a row
+pub struct Process {
+    handle: HANDLE,
+}
+
+impl Process {
+    pub fn open(pid : u32) -> io::Result<Process> {
+        let handle = unsafe { 
+            OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ | PROCESS_VM_WRITE | PROCESS_VM_OPERATION, false, pid)? 
+        };
+        Ok(Process { handle: handle })
+    }
+
+    pub fn read(&self, address : usize, buffer: &mut [u8]) -> bool {
+        let mut bytes_read: usize = 0;
+
+        unsafe { ReadProcessMemory(
+            self.handle,
+            address as *const _,
+            buffer.as_mut_ptr() as *mut _,
+            buffer.len(),
+            Some(&mut bytes_read)
+        ) };
+
+        bytes_read == buffer.len()
+    }
+
+    /// Read-only pages (e.g. code) are made writable for the duration of the write
+    pub fn write(&self, address : usize, target_bytes: &[u8]) -> io::Result<()> {
+        let num_bytes = target_bytes.len();
+        let mut bytes_written: usize = 0;
+
+        unsafe {
+            if WriteProcessMemory(self.handle, address as *const _, target_bytes.as_ptr() as *const _, num_bytes, Some(&mut bytes_written)).as_bool() 
+                && bytes_written == num_bytes {
+                return Ok(());
+            }
+
+            let mut old_protect = PAGE_PROTECTION_FLAGS::default();
+            if !VirtualProtectEx(self.handle, address as *const _, num_bytes, PAGE_EXECUTE_READWRITE, &mut old_protect).as_bool() {
+                let error = io::Error::last_os_error();
+                return Err(io::Error::new(error.kind(), format!("can't unprotect {:X}: {}", address, error)));
+            }
+
+            let written = WriteProcessMemory(self.handle, address as *const _, target_bytes.as_ptr() as *const _, num_bytes, Some(&mut bytes_written)).as_bool();
+            let error = io::Error::last_os_error();
+
+            VirtualProtectEx(self.handle, address as *const _, num_bytes, old_protect, &mut old_protect);
+            FlushInstructionCache(self.handle, Some(address as *const _), num_bytes);
+
+            if !written {
+                Err(io::Error::new(error.kind(), format!("can't write at {:X}: {}", address, error)))
+            } else if bytes_written != num_bytes {
+                Err(io::Error::new(io::ErrorKind::WriteZero, format!("only {} of {} bytes written at {:X}", bytes_written, num_bytes, address)))
+            } else {
+                Ok(())
             }
-            lpaddress += mbi.RegionSize;
         }
     }
-    pages
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.handle) };
+    }
+}
+
+
+/// Watchpoints are implemented on top of ptrace, there's no debugger loop for Windows yet
+pub struct Watcher;
+
+impl Watcher {
+    pub fn start(_pid : u32, _address : usize, _len : usize, _access : bool, _hits: Arc<Mutex<BTreeMap<usize, usize>>>) -> io::Result<Watcher> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "hardware watchpoints are only supported on Linux"))
+    }
+
+    pub fn is_running(&self) -> bool {
+        false
+    }
+
+    pub fn stop(&mut self) {}
+}
+
+
+/// Breakpoints need a debugger loop as well, only the ptrace one exists
+pub struct Debugger;
+
+impl Debugger {
+    pub fn attach(_pid : u32) -> io::Result<Debugger> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "the debugger is only supported on Linux"))
+    }
+
+    pub fn set_breakpoint(&self, _address : usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn remove_breakpoint(&self, _address : usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn registers(&self, _tid : u32) -> io::Result<Vec<Register>> {
+        Ok(vec![])
+    }
+
+    pub fn set_register(&self, _tid : u32, _name : &'static str, _value : u64) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn step(&self, _tid : u32) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn resume(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn poll(&self) -> Option<DebugEvent> {
+        None
+    }
+
+    pub fn detach(&mut self) {}
 }